@@ -92,6 +92,63 @@ fn bench_batch_processing(c: &mut Criterion) {
             }
         });
     });
+
+    // Same batch through the writer API, which reuses one output buffer
+    c.bench_function("batch_100_lines_into", |b| {
+        let mut colorizer = Colorizer::new(rules.clone())
+            .with_theme(theme.clone())
+            .with_color_enabled(true);
+        let mut out = std::io::sink();
+        b.iter(|| {
+            for line in &lines {
+                black_box(colorizer.colorize_into(black_box(line), &mut out)).unwrap();
+            }
+        });
+    });
+}
+
+fn bench_large_rule_sets(c: &mut Criterion) {
+    let registry = programs::default_registry();
+    let theme = Theme::default_dark();
+
+    // Archived node logs: most lines only hit a few of the program's rules
+    let geth_lines = [
+        "INFO [01-15|10:30:45.123] Imported new chain segment number=19,000,000 hash=0x4f6a..e1f2 blocks=1 txs=142 mgas=14.2 elapsed=85.3ms",
+        "DEBUG[01-15|10:30:45.200] Served eth_getBlockByNumber conn=127.0.0.1:51234 reqid=42 duration=1.2ms",
+        "WARN [01-15|10:30:46.001] Snapshot extension registration failed peer=4f6a8b2c err=\"peer connected on snap without compatible eth support\"",
+        "plain line with nothing interesting in it at all",
+    ];
+    let nginx_lines = [
+        r#"192.168.1.100 - - [15/Jan/2024:10:30:45 +0000] "GET /api/v1/users HTTP/1.1" 200 1234 "-" "curl/8.0""#,
+        "2024/01/15 10:30:45 [error] 1234#0: *5 connect() failed (111: Connection refused) while connecting to upstream",
+        "plain line with nothing interesting in it at all",
+    ];
+
+    let mut group = c.benchmark_group("colorize_large_rule_set");
+
+    for (program_id, lines) in [
+        ("ethereum.geth", &geth_lines[..]),
+        ("network.nginx", &nginx_lines[..]),
+    ] {
+        let rules = registry.get(program_id).unwrap().rules();
+        let bytes: usize = lines.iter().map(|l| l.len()).sum();
+        group.throughput(Throughput::Bytes(bytes as u64));
+        group.bench_with_input(
+            BenchmarkId::new("program", program_id),
+            lines,
+            |b, lines| {
+                let mut colorizer = Colorizer::new(rules.clone())
+                    .with_theme(theme.clone())
+                    .with_color_enabled(true);
+                b.iter(|| {
+                    for line in lines {
+                        black_box(colorizer.colorize(black_box(line)));
+                    }
+                });
+            },
+        );
+    }
+    group.finish();
 }
 
 criterion_group!(
@@ -100,5 +157,6 @@ criterion_group!(
     bench_rule_counts,
     bench_themes,
     bench_batch_processing,
+    bench_large_rule_sets,
 );
 criterion_main!(benches);
@@ -37,6 +37,7 @@ pub use formatter::{AlertPayload, WebhookFormatter, WebhookService};
 pub use rate_limit::{RateLimitResult, RateLimiter};
 pub use sender::{SendError, WebhookSender};
 
+use chrono::SecondsFormat;
 use thiserror::Error;
 
 /// Error returned when building an `AlertManager` fails validation.
@@ -51,6 +52,7 @@ pub enum AlertBuilderError {
     MissingTelegramChatId,
 }
 
+use crate::timestamp;
 use discord::DiscordFormatter;
 use std::sync::Arc;
 use telegram::TelegramFormatter;
@@ -182,8 +184,16 @@ impl AlertManager {
             }
         }
 
-        // Send collected alerts
+        // Send collected alerts, with the time the line was logged if it has one
+        let log_time = (!alerts_to_send.is_empty())
+            .then(|| timestamp::find(line))
+            .flatten()
+            .map(|found| found.time.to_rfc3339_opts(SecondsFormat::Millis, true));
         for (payload, cond_type) in alerts_to_send {
+            let payload = match &log_time {
+                Some(time) => payload.with_field("log_time", time.clone()),
+                None => payload,
+            };
             self.send_alert(&payload, &cond_type);
         }
     }
@@ -0,0 +1,143 @@
+//! Handling of ANSI escape sequences already present in the input.
+//!
+//! Many wrapped tools (cargo, pytest, docker compose, Ethereum clients) emit
+//! their own colors. Matching rules against the raw text breaks regexes and
+//! phos would paint over the input with mismatched resets. Instead, the
+//! [`Colorizer`](crate::Colorizer) splits a line into plain text and the escape
+//! sequences it contained, matches rules on the plain text, then re-inserts the
+//! escapes according to the [`AnsiMode`].
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::colors::ANSI_REGEX;
+
+/// How escape sequences already present in input lines are handled.
+///
+/// Lines without escape sequences are unaffected by the mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnsiMode {
+    /// Match on plain text; keep the input's colors outside phos matches (default)
+    #[default]
+    Merge,
+    /// Match on plain text; drop the input's colors (other sequences are kept)
+    Strip,
+    /// Match rules against the raw line, escape sequences included
+    Raw,
+}
+
+/// An escape sequence removed from a line, anchored to a byte offset in the plain text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Escape<'a> {
+    /// Offset in the plain text the sequence preceded
+    pub pos: usize,
+    /// The full escape sequence
+    pub seq: &'a str,
+}
+
+impl Escape<'_> {
+    /// Whether this is an SGR (color/attribute) sequence.
+    #[inline]
+    pub fn is_sgr(&self) -> bool {
+        self.seq.starts_with("\x1b[") && self.seq.ends_with('m')
+    }
+}
+
+/// Split a line into plain text and the escape sequences it contained.
+pub(crate) fn split_escapes(line: &str) -> (String, Vec<Escape<'_>>) {
+    let mut plain = String::with_capacity(line.len());
+    let mut escapes = Vec::new();
+    let mut last = 0;
+
+    for m in ANSI_REGEX.find_iter(line) {
+        plain.push_str(&line[last..m.start()]);
+        escapes.push(Escape {
+            pos: plain.len(),
+            seq: m.as_str(),
+        });
+        last = m.end();
+    }
+    plain.push_str(&line[last..]);
+
+    (plain, escapes)
+}
+
+/// Re-insert escapes into uncolored plain text (SGR sequences only if `keep_sgr`).
+pub(crate) fn weave(out: &mut String, plain: &str, escapes: &[Escape<'_>], keep_sgr: bool) {
+    out.reserve(plain.len() + escapes.iter().map(|e| e.seq.len()).sum::<usize>());
+    let mut last = 0;
+
+    for esc in escapes.iter().filter(|esc| keep_sgr || !esc.is_sgr()) {
+        out.push_str(&plain[last..esc.pos]);
+        out.push_str(esc.seq);
+        last = esc.pos;
+    }
+    out.push_str(&plain[last..]);
+}
+
+/// Input SGR sequences in effect since the input's last reset.
+///
+/// Replayed after a phos-styled piece so the input's colors resume where it left off.
+#[derive(Debug, Default)]
+pub(crate) struct SgrState {
+    active: String,
+}
+
+impl SgrState {
+    /// Record an SGR sequence from the input.
+    pub fn apply(&mut self, seq: &str) {
+        let params = &seq[2..seq.len() - 1];
+        if params.is_empty() || params == "0" {
+            self.active.clear();
+            return;
+        }
+        if params.starts_with("0;") {
+            self.active.clear();
+        }
+        self.active.push_str(seq);
+    }
+
+    /// Whether any input colors are in effect.
+    pub fn is_active(&self) -> bool {
+        !self.active.is_empty()
+    }
+
+    /// The sequences to replay to restore the input's colors.
+    pub fn as_str(&self) -> &str {
+        &self.active
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_and_weave_round_trip() {
+        let line = "\x1b[1m\x1b[32mCompiling\x1b[0m phos v0.5.1\x1b[K";
+        let (plain, escapes) = split_escapes(line);
+        assert_eq!(plain, "Compiling phos v0.5.1");
+        assert_eq!(escapes.len(), 4);
+        assert_eq!(escapes[2].pos, "Compiling".len());
+        let mut merged = String::new();
+        weave(&mut merged, &plain, &escapes, true);
+        assert_eq!(merged, line);
+        // Strip keeps non-color sequences such as erase-line
+        let mut stripped = String::new();
+        weave(&mut stripped, &plain, &escapes, false);
+        assert_eq!(stripped, "Compiling phos v0.5.1\x1b[K");
+    }
+
+    #[test]
+    fn test_sgr_state() {
+        let mut state = SgrState::default();
+        state.apply("\x1b[1m");
+        state.apply("\x1b[31m");
+        assert_eq!(state.as_str(), "\x1b[1m\x1b[31m");
+        state.apply("\x1b[0;34m");
+        assert_eq!(state.as_str(), "\x1b[0;34m");
+        state.apply("\x1b[m");
+        assert!(!state.is_active());
+    }
+}
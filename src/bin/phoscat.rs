@@ -3,31 +3,77 @@
 //! Usage: `command | phoscat <program>`
 //!        `command | phoscat`              (auto-detect program)
 //!        `PHOS_PROGRAM=docker command | phoscat`
+//!        `command | phoscat docker -H 0xdeadbeef -H 'slot=\d+:error'`
+//!        `command | phoscat --detect-lines 50`
 
 use anyhow::{Context, Result};
 use is_terminal::IsTerminal;
-use phos::{programs, Colorizer, Theme};
+use phos::program::rank::{DEFAULT_DETECT_LINES, MIN_CONFIDENCE};
+use phos::{highlight, programs, ColorDepth, Colorizer, LineEnding, LineReader, RawLine, Theme};
 use std::env;
-use std::io::{self, BufRead, Write};
-
-/// Number of lines to buffer for auto-detection
-const AUTO_DETECT_LINES: usize = 10;
+use std::io::{self, Write};
 
 /// Process lines through a colorizer, writing to the given output.
+///
+/// Original line endings are kept and lines matching skip rules are dropped.
 fn colorize_lines<'a, I, W>(colorizer: &mut Colorizer, lines: I, out: &mut W) -> Result<()>
 where
-    I: Iterator<Item = &'a str>,
+    I: Iterator<Item = &'a RawLine>,
     W: Write,
 {
     for line in lines {
-        writeln!(out, "{}", colorizer.colorize(line))?;
+        let Some(colored) = colorizer.colorize_opt(&line.to_str_lossy()) else {
+            continue;
+        };
+        line.write_with(out, &colored)?;
+        if line.ending() == LineEnding::Cr {
+            out.flush()?;
+        }
     }
     Ok(())
 }
 
+/// Parsed command-line arguments.
+struct Args {
+    program: Option<String>,
+    highlights: Vec<String>,
+    /// Lines to buffer for auto-detection
+    detect_lines: usize,
+}
+
+/// Split arguments into the program name, `-H`/`--highlight` patterns and
+/// the `--detect-lines` sample size.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args> {
+    let mut parsed = Args {
+        program: None,
+        highlights: Vec::new(),
+        detect_lines: DEFAULT_DETECT_LINES,
+    };
+    while let Some(arg) = args.next() {
+        if let Some(spec) = arg.strip_prefix("--highlight=") {
+            parsed.highlights.push(spec.to_string());
+        } else if arg == "-H" || arg == "--highlight" {
+            parsed
+                .highlights
+                .push(args.next().context("--highlight needs a pattern")?);
+        } else if let Some(count) = arg.strip_prefix("--detect-lines=") {
+            parsed.detect_lines = count.parse().context("Invalid --detect-lines count")?;
+        } else if arg == "--detect-lines" {
+            let count = args.next().context("--detect-lines needs a count")?;
+            parsed.detect_lines = count.parse().context("Invalid --detect-lines count")?;
+        } else if parsed.program.is_none() {
+            parsed.program = Some(arg);
+        }
+    }
+    Ok(parsed)
+}
+
 fn main() -> Result<()> {
-    // Get program name from arg or env (optional now)
-    let program_name = env::args().nth(1).or_else(|| env::var("PHOS_PROGRAM").ok());
+    // Get program name from arg or env (optional now), plus highlight patterns
+    let args = parse_args(env::args().skip(1))?;
+    let program_name = args.program.or_else(|| env::var("PHOS_PROGRAM").ok());
+    let highlights =
+        highlight::highlight_rules(&args.highlights).context("Invalid --highlight pattern")?;
 
     // Get theme from env
     let theme_name = env::var("PHOS_THEME").unwrap_or_else(|_| "default-dark".into());
@@ -49,30 +95,45 @@ fn main() -> Result<()> {
     } else {
         // Auto-detect: buffer initial lines
         let stdin = io::stdin();
-        let mut buffer: Vec<String> = Vec::with_capacity(AUTO_DETECT_LINES);
+        let mut reader = LineReader::new(stdin.lock());
+        let mut buffer: Vec<RawLine> = Vec::with_capacity(args.detect_lines);
 
-        for line in stdin.lock().lines().take(AUTO_DETECT_LINES) {
+        for line in reader.by_ref().take(args.detect_lines) {
             buffer.push(line.context("Failed to read stdin")?);
         }
 
         // Try to detect program from buffered content
-        let line_refs: Vec<&str> = buffer.iter().map(String::as_str).collect();
-        let detected = registry.detect_from_lines(&line_refs);
-
-        let rules = detected.map_or_else(
-            || {
+        let texts: Vec<_> = buffer.iter().map(RawLine::to_str_lossy).collect();
+        let line_refs: Vec<&str> = texts.iter().map(AsRef::as_ref).collect();
+        let candidates = registry.rank_from_lines(&line_refs);
+        let best = candidates.first();
+
+        let rules = match best {
+            Some(best) if best.confidence >= MIN_CONFIDENCE => {
+                eprintln!(
+                    "phoscat: auto-detected program: {} ({:.0}% confidence)",
+                    best.program.info().id,
+                    best.confidence * 100.0
+                );
+                best.program.rules()
+            }
+            _ => {
+                if let Some(best) = best.filter(|best| best.confidence > 0.0) {
+                    eprintln!(
+                        "phoscat: best guess {} has only {:.0}% confidence (see 'phos detect')",
+                        best.program.info().id,
+                        best.confidence * 100.0
+                    );
+                }
                 eprintln!("phoscat: no program detected, using generic coloring");
                 registry.get("cargo").map(|p| p.rules()).unwrap_or_default()
-            },
-            |program| {
-                eprintln!("phoscat: auto-detected program: {}", program.info().id);
-                program.rules()
-            },
-        );
+            }
+        };
 
         // Create colorizer and process buffered + remaining lines
         let color_enabled = io::stdout().is_terminal();
-        let mut colorizer = Colorizer::new(rules)
+        let mut colorizer = Colorizer::new(highlight::prepend(rules, &highlights))
+            .with_color_depth(ColorDepth::detect())
             .with_theme(theme)
             .with_color_enabled(color_enabled);
 
@@ -80,12 +141,12 @@ fn main() -> Result<()> {
         let mut out = stdout.lock();
 
         // Process buffered lines
-        colorize_lines(&mut colorizer, buffer.iter().map(String::as_str), &mut out)?;
+        colorize_lines(&mut colorizer, buffer.iter(), &mut out)?;
 
         // Process remaining stdin
-        for line in stdin.lock().lines() {
+        for line in reader {
             let line = line.context("Failed to read stdin")?;
-            writeln!(out, "{}", colorizer.colorize(&line))?;
+            colorize_lines(&mut colorizer, std::iter::once(&line), &mut out)?;
         }
 
         return Ok(());
@@ -93,7 +154,8 @@ fn main() -> Result<()> {
 
     // Explicit program: colorize stdin to stdout
     let color_enabled = io::stdout().is_terminal();
-    let mut colorizer = Colorizer::new(rules)
+    let mut colorizer = Colorizer::new(highlight::prepend(rules, &highlights))
+        .with_color_depth(ColorDepth::detect())
         .with_theme(theme)
         .with_color_enabled(color_enabled);
 
@@ -0,0 +1,375 @@
+//! Terminal color capability detection and color downsampling.
+//!
+//! Themes resolve to 24-bit colors, which older SSH clients, tmux without `Tc`
+//! and the Linux console render as garbage or nearest guesses. [`ColorDepth`]
+//! describes what the terminal supports and maps styles down to it, so the
+//! [`Colorizer`](crate::Colorizer) can emit 256-color or 16-color escapes instead.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use nu_ansi_term::{Color as AnsiColor, Style};
+
+/// Number of colors a terminal can display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ColorDepth {
+    /// 24-bit RGB colors (default)
+    #[default]
+    #[value(name = "truecolor")]
+    TrueColor,
+    /// xterm 256-color palette
+    #[value(name = "256")]
+    Ansi256,
+    /// The 16 basic ANSI colors
+    #[value(name = "16")]
+    Ansi16,
+    /// No colors; text attributes such as bold are kept
+    #[value(name = "none")]
+    None,
+}
+
+impl ColorDepth {
+    /// Detect the color depth of the current terminal.
+    ///
+    /// Checks `COLORTERM` first, then the `colors` capability in the terminfo
+    /// entry for `TERM`, then well-known `TERM` names. Without a `TERM` the
+    /// terminal is assumed to support 24-bit color.
+    #[must_use]
+    pub fn detect() -> Self {
+        let colorterm = env::var("COLORTERM").ok();
+        let term = env::var("TERM").ok();
+        Self::from_env(colorterm.as_deref(), term.as_deref(), terminfo_max_colors)
+    }
+
+    /// Resolve a depth from `COLORTERM`, `TERM` and a terminfo `colors` lookup.
+    fn from_env(
+        colorterm: Option<&str>,
+        term: Option<&str>,
+        max_colors: impl FnOnce(&str) -> Option<i32>,
+    ) -> Self {
+        if matches!(colorterm, Some("truecolor" | "24bit")) {
+            return Self::TrueColor;
+        }
+        let term = match term {
+            None | Some("") => return Self::TrueColor,
+            Some("dumb") => return Self::None,
+            Some(term) => term,
+        };
+
+        match max_colors(term) {
+            Some(n) if n >= 1 << 24 => Self::TrueColor,
+            Some(n) if n >= 256 => Self::Ansi256,
+            Some(n) if n >= 8 => Self::Ansi16,
+            Some(_) => Self::None,
+            None if term.ends_with("-direct") || term.contains("truecolor") => Self::TrueColor,
+            None if term.contains("256color") => Self::Ansi256,
+            None => Self::Ansi16,
+        }
+    }
+
+    /// Map a style's colors to this depth (attributes are unchanged).
+    #[must_use]
+    pub fn downsample(self, style: Style) -> Style {
+        Style {
+            foreground: style.foreground.and_then(|color| self.map_color(color)),
+            background: style.background.and_then(|color| self.map_color(color)),
+            ..style
+        }
+    }
+
+    /// Map a color to this depth (None = drop the color).
+    fn map_color(self, color: AnsiColor) -> Option<AnsiColor> {
+        match (self, color) {
+            (Self::None, _) => None,
+            (Self::TrueColor, _) => Some(color),
+            (Self::Ansi256, AnsiColor::Rgb(r, g, b)) => {
+                Some(AnsiColor::Fixed(nearest_256(r, g, b)))
+            }
+            (Self::Ansi16, AnsiColor::Rgb(r, g, b)) => Some(nearest_16(r, g, b)),
+            (Self::Ansi16, AnsiColor::Fixed(n)) if n < 16 => Some(ansi16(n)),
+            (Self::Ansi16, AnsiColor::Fixed(n)) => {
+                let (r, g, b) = fixed_to_rgb(n);
+                Some(nearest_16(r, g, b))
+            }
+            _ => Some(color),
+        }
+    }
+}
+
+/// Channel levels of the 6x6x6 color cube in the 256-color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Nearest entry in the 256-color palette (color cube or grayscale ramp).
+fn nearest_256(r: u8, g: u8, b: u8) -> u8 {
+    let cube_index = |v: u8| match v {
+        0..=47 => 0,
+        48..=114 => 1,
+        v => (v - 35) / 40,
+    };
+    let (ri, gi, bi) = (cube_index(r), cube_index(g), cube_index(b));
+    let cube = (
+        CUBE_LEVELS[usize::from(ri)],
+        CUBE_LEVELS[usize::from(gi)],
+        CUBE_LEVELS[usize::from(bi)],
+    );
+
+    let average = (u16::from(r) + u16::from(g) + u16::from(b)) / 3;
+    let gray_index = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray = 8 + 10 * gray_index;
+
+    if distance((r, g, b), (gray, gray, gray)) < distance((r, g, b), cube) {
+        232 + gray_index
+    } else {
+        16 + 36 * ri + 6 * gi + bi
+    }
+}
+
+/// Squared distance between two RGB colors.
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| u32::from(x.abs_diff(y)).pow(2);
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// RGB value of a 256-color palette entry above the 16 basic colors.
+fn fixed_to_rgb(n: u8) -> (u8, u8, u8) {
+    if n >= 232 {
+        let level = 8 + 10 * (n - 232);
+        return (level, level, level);
+    }
+    let n = n - 16;
+    let level = |i: u8| CUBE_LEVELS[usize::from(i)];
+    (level(n / 36), level(n / 6 % 6), level(n % 6))
+}
+
+/// Nearest basic ANSI color, chosen by hue so themed pastels keep their hue.
+///
+/// Unsaturated colors map to the black/gray/white entries by lightness;
+/// bright variants are used for light colors.
+fn nearest_16(r: u8, g: u8, b: u8) -> AnsiColor {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let chroma = max - min;
+    let bright = max >= 0xC0;
+
+    if max == 0 || u16::from(chroma) * 4 < u16::from(max) {
+        return match max {
+            0..=63 => AnsiColor::Black,
+            64..=159 => AnsiColor::DarkGray,
+            160..=223 => AnsiColor::White,
+            _ => AnsiColor::LightGray,
+        };
+    }
+
+    let (rf, gf, bf, c) = (f32::from(r), f32::from(g), f32::from(b), f32::from(chroma));
+    let hue = if max == r {
+        60.0 * ((gf - bf) / c).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((bf - rf) / c + 2.0)
+    } else {
+        60.0 * ((rf - gf) / c + 4.0)
+    };
+
+    // Six hue sectors centred on red, yellow, green, cyan, blue and magenta
+    let (normal, light) = match hue {
+        h if !(30.0..330.0).contains(&h) => (AnsiColor::Red, AnsiColor::LightRed),
+        h if h < 90.0 => (AnsiColor::Yellow, AnsiColor::LightYellow),
+        h if h < 150.0 => (AnsiColor::Green, AnsiColor::LightGreen),
+        h if h < 210.0 => (AnsiColor::Cyan, AnsiColor::LightCyan),
+        h if h < 270.0 => (AnsiColor::Blue, AnsiColor::LightBlue),
+        _ => (AnsiColor::Magenta, AnsiColor::LightMagenta),
+    };
+    if bright {
+        light
+    } else {
+        normal
+    }
+}
+
+/// Basic ANSI color for a palette index below 16.
+fn ansi16(n: u8) -> AnsiColor {
+    match n {
+        0 => AnsiColor::Black,
+        1 => AnsiColor::Red,
+        2 => AnsiColor::Green,
+        3 => AnsiColor::Yellow,
+        4 => AnsiColor::Blue,
+        5 => AnsiColor::Magenta,
+        6 => AnsiColor::Cyan,
+        7 => AnsiColor::White,
+        8 => AnsiColor::DarkGray,
+        9 => AnsiColor::LightRed,
+        10 => AnsiColor::LightGreen,
+        11 => AnsiColor::LightYellow,
+        12 => AnsiColor::LightBlue,
+        13 => AnsiColor::LightMagenta,
+        14 => AnsiColor::LightCyan,
+        _ => AnsiColor::LightGray,
+    }
+}
+
+// ============================================================================
+// Terminfo
+// ============================================================================
+
+/// Look up the `colors` capability of `term` in the compiled terminfo database.
+fn terminfo_max_colors(term: &str) -> Option<i32> {
+    if term.contains(['/', '\\']) || term.starts_with('.') {
+        return None;
+    }
+    let first = term.chars().next()?;
+
+    terminfo_dirs()
+        .into_iter()
+        .flat_map(|dir| {
+            // Linux uses the first letter, macOS its hex code
+            [
+                dir.join(first.to_string()).join(term),
+                dir.join(format!("{:x}", u32::from(first))).join(term),
+            ]
+        })
+        .find_map(|path| fs::read(path).ok())
+        .and_then(|data| parse_max_colors(&data))
+}
+
+/// Terminfo search path, in ncurses order.
+fn terminfo_dirs() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = env::var_os("TERMINFO")
+        .map(PathBuf::from)
+        .into_iter()
+        .collect();
+    paths.extend(dirs::home_dir().map(|home| home.join(".terminfo")));
+    if let Ok(list) = env::var("TERMINFO_DIRS") {
+        paths.extend(list.split(':').map(|dir| {
+            PathBuf::from(if dir.is_empty() {
+                "/usr/share/terminfo"
+            } else {
+                dir
+            })
+        }));
+    }
+    paths.extend(
+        [
+            "/etc/terminfo",
+            "/lib/terminfo",
+            "/usr/share/terminfo",
+            "/usr/lib/terminfo",
+        ]
+        .into_iter()
+        .map(PathBuf::from),
+    );
+    paths
+}
+
+/// Read the `colors` number from a compiled terminfo entry (legacy or 32-bit format).
+fn parse_max_colors(data: &[u8]) -> Option<i32> {
+    /// Index of `colors` in the numbers section
+    const MAX_COLORS: usize = 13;
+
+    let header = |i: usize| {
+        let bytes = data.get(i * 2..i * 2 + 2)?;
+        usize::try_from(i16::from_le_bytes([bytes[0], bytes[1]])).ok()
+    };
+    let number_size = match header(0)? {
+        0o432 => 2,
+        0o1036 => 4,
+        _ => return None,
+    };
+    let (names_size, bools, numbers) = (header(1)?, header(2)?, header(3)?);
+    if numbers <= MAX_COLORS {
+        return None;
+    }
+
+    // The numbers section starts on an even offset after the 12-byte header
+    let mut offset = 12 + names_size + bools;
+    offset += offset % 2;
+    let at = offset + MAX_COLORS * number_size;
+    let value = match *data.get(at..at + number_size)? {
+        [a, b] => i32::from(i16::from_le_bytes([a, b])),
+        [a, b, c, d] => i32::from_le_bytes([a, b, c, d]),
+        _ => return None,
+    };
+    (value >= 0).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_from_env() {
+        let no_terminfo = |_: &str| None;
+        let depth = |colorterm, term| ColorDepth::from_env(colorterm, term, no_terminfo);
+        assert_eq!(
+            depth(Some("truecolor"), Some("xterm")),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(depth(None, None), ColorDepth::TrueColor);
+        assert_eq!(depth(None, Some("dumb")), ColorDepth::None);
+        assert_eq!(depth(None, Some("tmux-256color")), ColorDepth::Ansi256);
+        assert_eq!(depth(None, Some("linux")), ColorDepth::Ansi16);
+
+        // Terminfo wins over name heuristics
+        let depth = ColorDepth::from_env(None, Some("xterm-direct"), |_| Some(256));
+        assert_eq!(depth, ColorDepth::Ansi256);
+        let depth = ColorDepth::from_env(None, Some("linux"), |_| Some(8));
+        assert_eq!(depth, ColorDepth::Ansi16);
+    }
+
+    #[test]
+    fn test_parse_terminfo_max_colors() {
+        // Legacy header: magic, names size, 1 bool, 15 numbers, no strings
+        let mut data = Vec::new();
+        for word in [0o432i16, 4, 1, 15, 0, 0] {
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+        data.extend_from_slice(b"foo\0");
+        data.push(1);
+        data.push(0); // padding to an even offset
+        for i in 0..15i16 {
+            let value: i16 = if i == 13 { 256 } else { -1 };
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        assert_eq!(parse_max_colors(&data), Some(256));
+        assert_eq!(parse_max_colors(b"not terminfo"), None);
+    }
+
+    #[test]
+    fn test_downsample_256() {
+        assert_eq!(nearest_256(255, 0, 0), 196);
+        assert_eq!(nearest_256(0, 0, 0), 16);
+        assert_eq!(nearest_256(128, 128, 128), 244);
+        assert_eq!(fixed_to_rgb(196), (255, 0, 0));
+    }
+
+    #[test]
+    fn test_downsample_16_keeps_hue() {
+        // Dracula red, green, pink and foreground
+        assert_eq!(nearest_16(0xFF, 0x55, 0x55), AnsiColor::LightRed);
+        assert_eq!(nearest_16(0x50, 0xFA, 0x7B), AnsiColor::LightGreen);
+        assert_eq!(nearest_16(0xFF, 0x79, 0xC6), AnsiColor::LightMagenta);
+        assert_eq!(nearest_16(0xF8, 0xF8, 0xF2), AnsiColor::LightGray);
+        assert_eq!(nearest_16(0x62, 0x72, 0xA4), AnsiColor::Blue);
+    }
+
+    #[test]
+    fn test_downsample_style() {
+        let style = Style::new()
+            .fg(AnsiColor::Rgb(255, 0, 0))
+            .on(AnsiColor::Fixed(4))
+            .bold();
+        let mapped = ColorDepth::Ansi16.downsample(style);
+        assert_eq!(
+            mapped,
+            Style::new()
+                .fg(AnsiColor::LightRed)
+                .on(AnsiColor::Blue)
+                .bold()
+        );
+
+        let mapped = ColorDepth::None.downsample(style);
+        assert_eq!(mapped, Style::new().bold());
+        assert_eq!(ColorDepth::TrueColor.downsample(style), style);
+    }
+}
@@ -40,21 +40,252 @@
 //! ```
 
 use std::borrow::Cow;
+use std::cmp::Reverse;
 use std::io::{self, BufRead, Write};
+use std::num::NonZeroUsize;
+use std::ops::Range;
 use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
+use chrono::Utc;
 use nu_ansi_term::Style;
+use regex::{RegexSet, SetMatches};
 use smallvec::SmallVec;
 
+use crate::alert::AlertManager;
+use crate::ansi::{self, AnsiMode, Escape, SgrState};
+use crate::color_depth::ColorDepth;
+use crate::colors::{contains_ansi, stable_hash, strip_ansi, Color, SemanticColor};
+use crate::dedup::{Dedup, DedupMode};
+use crate::delta::DeltaTracker;
+use crate::filter::{FilteredOutput, LineFilter};
+use crate::level::{LevelFilter, LogLevel};
+use crate::line_reader::{LineReader, RawLine};
+use crate::record::{MultilineRules, Record, RecordAssembler};
 use crate::rule::{CountMode, Rule};
+use crate::stats::StatsCollector;
+use crate::structured::{self, FieldNames, Part, Rendered, ValueKind};
 use crate::theme::Theme;
+use crate::timestamp::{self, TimeFormat};
 
 /// Type alias for match ranges - stack-allocated for typical cases (0-8 matches)
-type MatchRanges = SmallVec<[(usize, usize, usize); 8]>;
+type MatchRanges = SmallVec<[(usize, usize, Style); 8]>;
 
-/// Maximum line length to colorize. Lines longer than this are passed through
-/// unchanged to prevent performance issues with pathological regex patterns.
-const MAX_LINE_LENGTH: usize = 10_000;
+/// Values of the fields field rules target, as (index into `field_keys`, range)
+type FieldValues = SmallVec<[(usize, Range<usize>); 4]>;
+
+/// Pre-computed styles for a single rule.
+///
+/// Rules with one color (or without capture groups) paint the whole match with
+/// `base`. Rules with capture groups and several colors follow grc semantics:
+/// the first color is the base for the whole match and the Nth additional color
+/// applies to the Nth capture group. [`Color::Hashed`] slots pick their style
+/// from `palette` by a hash of the text they cover.
+#[derive(Debug, Clone, Default)]
+struct RuleStyle {
+    /// Style for the whole match (or the uncaptured parts in group mode)
+    base: Style,
+    /// Styles for capture groups 1..=N (empty = whole-match coloring)
+    groups: SmallVec<[Style; 4]>,
+    /// Whether the base (0) and each group (N) are hashed (empty = none)
+    hashed: SmallVec<[bool; 4]>,
+    /// Hashed palette with the rule's styling applied
+    palette: Vec<Style>,
+}
+
+impl RuleStyle {
+    /// Style of `slot` (0 = base, N = group N) for the matched `text`.
+    fn pick(&self, slot: usize, style: Style, text: &str) -> Style {
+        if self.palette.is_empty() || !self.hashed.get(slot).copied().unwrap_or(false) {
+            return style;
+        }
+        let index = stable_hash(text) % self.palette.len() as u64;
+        usize::try_from(index).map_or(style, |index| self.palette[index])
+    }
+}
+
+/// A styled match competing for part of a line.
+#[derive(Debug, Clone, Copy)]
+struct Span {
+    start: usize,
+    end: usize,
+    style: Style,
+    /// Precedence rank (lower wins where spans nest)
+    rank: usize,
+}
+
+impl Span {
+    fn new(start: usize, end: usize, style: Style, rank: usize) -> Self {
+        Self {
+            start,
+            end,
+            style,
+            rank,
+        }
+    }
+}
+
+/// Theme styles for the parts of a rendered structured line.
+#[derive(Debug, Clone, Copy, Default)]
+struct FieldStyles {
+    timestamp: Style,
+    key: Style,
+    string: Style,
+    number: Style,
+    boolean: Style,
+    value: Style,
+    /// Error, warn, info, debug and trace
+    levels: [Style; 5],
+}
+
+impl FieldStyles {
+    fn new(theme: &Theme, depth: ColorDepth) -> Self {
+        let style = |color| depth.downsample(theme.resolve_style(&Color::Semantic(color)));
+        Self {
+            timestamp: style(SemanticColor::Timestamp),
+            key: style(SemanticColor::Key),
+            string: style(SemanticColor::String),
+            number: style(SemanticColor::Number),
+            boolean: style(SemanticColor::Boolean),
+            value: style(SemanticColor::Value),
+            levels: [
+                SemanticColor::Error,
+                SemanticColor::Warn,
+                SemanticColor::Info,
+                SemanticColor::Debug,
+                SemanticColor::Trace,
+            ]
+            .map(style),
+        }
+    }
+
+    /// Style of a part, and whether it takes precedence over rules.
+    fn get(&self, part: Part) -> Option<(Style, bool)> {
+        let style = match part {
+            Part::Timestamp => self.timestamp,
+            Part::Key => self.key,
+            Part::Level(level) => match level? {
+                SemanticColor::Error => self.levels[0],
+                SemanticColor::Warn => self.levels[1],
+                SemanticColor::Info => self.levels[2],
+                SemanticColor::Debug => self.levels[3],
+                SemanticColor::Trace => self.levels[4],
+                _ => return None,
+            },
+            Part::Value(kind) => {
+                let style = match kind {
+                    ValueKind::String => self.string,
+                    ValueKind::Number => self.number,
+                    ValueKind::Boolean | ValueKind::Null => self.boolean,
+                    ValueKind::Object => self.value,
+                };
+                return Some((style, false));
+            }
+        };
+        Some((style, true))
+    }
+}
+
+/// Layer `next` over `acc`: colors in `next` win, attributes accumulate.
+fn merge_styles(acc: Style, next: Style) -> Style {
+    Style {
+        foreground: next.foreground.or(acc.foreground),
+        background: next.background.or(acc.background),
+        is_bold: acc.is_bold || next.is_bold,
+        is_dimmed: acc.is_dimmed || next.is_dimmed,
+        is_italic: acc.is_italic || next.is_italic,
+        is_underline: acc.is_underline || next.is_underline,
+        is_blink: acc.is_blink || next.is_blink,
+        is_reverse: acc.is_reverse || next.is_reverse,
+        is_hidden: acc.is_hidden || next.is_hidden,
+        is_strikethrough: acc.is_strikethrough || next.is_strikethrough,
+        ..acc
+    }
+}
+
+/// Rules that can match a given line.
+///
+/// Computed with a single [`RegexSet`] pass so per-rule matching only runs on
+/// rules that are known to match somewhere in the line.
+enum Candidates {
+    /// No prefilter available; every rule is a candidate
+    All,
+    /// Rules whose pattern matched the line
+    Matched(SetMatches),
+}
+
+impl Candidates {
+    /// Whether rule `idx` may match (always true without a prefilter).
+    #[inline]
+    fn contains(&self, idx: usize) -> bool {
+        match self {
+            Self::All => true,
+            Self::Matched(set) => set.matched(idx),
+        }
+    }
+
+    /// Whether rule `idx` matches `line`, the text the candidates were computed for.
+    #[inline]
+    fn is_match(&self, idx: usize, rule: &Rule, line: &str) -> bool {
+        match self {
+            Self::All => rule.is_match(line),
+            Self::Matched(set) => set.matched(idx),
+        }
+    }
+}
+
+/// Default number of bytes colorized per line. Longer lines have only this head
+/// matched against rules; the tail is passed through, bounding per-line work.
+pub const DEFAULT_MAX_LINE_LENGTH: usize = 10_000;
+
+/// Maximum styled spans kept per line. Once reached, lower-precedence rules are
+/// not matched, bounding overlap resolution on lines with many matches.
+const MAX_SPANS_PER_LINE: usize = 512;
+
+/// Lines per worker chunk in parallel mode (batches hold one chunk per thread).
+const PARALLEL_CHUNK_LINES: usize = 2048;
+
+/// Colorized lines of one chunk, produced by a parallel worker.
+///
+/// Workers start outside block mode, so lines before the chunk's first
+/// block/unblock transition may need redoing once the real entry state is known.
+struct ChunkOutput {
+    /// Per-line results in input order (None = skipped)
+    lines: Vec<Option<(String, bool)>>,
+    /// Index of the first line where a block or unblock rule fired
+    first_transition: Option<usize>,
+    /// Block state at the end of the chunk (`in_block`, `block_style`)
+    exit_state: (bool, Option<Style>),
+}
+
+/// Prints compact stats to stderr at a fixed interval while input is processed.
+struct StatsTicker {
+    /// None when periodic output is off
+    interval: Option<Duration>,
+    last_output: Instant,
+}
+
+impl StatsTicker {
+    fn new(interval_secs: u64) -> Self {
+        Self {
+            interval: (interval_secs > 0).then(|| Duration::from_secs(interval_secs)),
+            last_output: Instant::now(),
+        }
+    }
+
+    /// Print `stats` if the interval has passed since the last output.
+    fn tick(&mut self, stats: Option<&StatsCollector>) -> io::Result<()> {
+        let (Some(interval), Some(stats)) = (self.interval, stats) else {
+            return Ok(());
+        };
+        if self.last_output.elapsed() >= interval {
+            writeln!(io::stderr(), "{}", stats.to_compact())?;
+            self.last_output = Instant::now();
+        }
+        Ok(())
+    }
+}
 
 /// The colorizer applies rules to text and outputs colored results.
 ///
@@ -64,10 +295,14 @@ const MAX_LINE_LENGTH: usize = 10_000;
 /// # Features
 ///
 /// - **Rule matching**: Applies regex-based rules to colorize patterns
+/// - **Capture groups**: Colors individual groups when a rule has several colors
 /// - **Theme support**: Resolves semantic colors through the active theme
+/// - **Backgrounds and attributes**: Background colors, italic, underline, dim, etc.
 /// - **Skip rules**: Can filter out lines matching certain patterns
 /// - **Replace rules**: Can transform text while colorizing
 /// - **Block mode**: Color entire sections between markers
+/// - **Conditional rules**: Gate rules on other patterns in the line or on named states
+/// - **Colored input**: Matches on plain text and merges or strips existing colors
 /// - **Statistics**: Optional tracking of match counts and patterns
 ///
 /// # Examples
@@ -90,17 +325,55 @@ pub struct Colorizer {
     /// Rules to apply in order (Arc to avoid cloning compiled regexes)
     rules: Arc<[Rule]>,
     /// Pre-computed styles for each rule (parallel to rules)
-    rule_styles: Arc<[Style]>,
-    /// Pre-computed indices of rules that can produce colored output
+    rule_styles: Arc<[RuleStyle]>,
+    /// Pre-computed indices of rules that can produce colored output, by precedence
     colorizable_indices: Arc<[usize]>,
+    /// All rule patterns compiled into one set (None if it could not be built)
+    prefilter: Option<Arc<RegexSet>>,
+    /// Field names targeted by field rules (empty = no field rules)
+    field_keys: Arc<[String]>,
+    /// How escape sequences already present in the input are handled
+    ansi_mode: AnsiMode,
     /// Theme for semantic color resolution
     theme: Theme,
+    /// Colors the terminal can display (styles are downsampled to it)
+    color_depth: ColorDepth,
     /// Whether currently in block coloring mode
     in_block: bool,
     /// Block coloring style (if `in_block` is true)
     block_style: Option<Style>,
+    /// Named states rules enter, leave or depend on
+    state_names: Arc<[String]>,
+    /// Whether each named state is active (parallel to `state_names`)
+    states: SmallVec<[bool; 4]>,
+    /// Field names for structured (JSON, logfmt) rendering (None = disabled)
+    structured: Option<FieldNames>,
+    /// Theme styles for the parts of rendered structured lines
+    field_styles: FieldStyles,
+    /// Format timestamps are rewritten in (None = as in the input)
+    time_format: Option<TimeFormat>,
+    /// Rules grouping lines into multi-line records (None = line by line)
+    multiline: Option<MultilineRules>,
+    /// Level style of the current record, used for its continuation lines
+    record_style: Option<Style>,
+    /// Whether the current record was dropped by a skip rule
+    record_skipped: bool,
+    /// Whether the line being colorized continues a record
+    in_continuation: bool,
+    /// Lines the stdio and parallel processors write (None = all)
+    filter: Option<LineFilter>,
+    /// Lowest level the stdio and parallel processors write (None = any)
+    min_level: Option<LogLevel>,
+    /// Time delta prefixes for the stdio and parallel processors (None = off)
+    delta: Option<DeltaTracker>,
+    /// Which repeated lines the stdio and parallel processors collapse (None = none)
+    dedup: Option<DedupMode>,
     /// Whether color output is enabled (false = pass-through mode)
     color_enabled: bool,
+    /// Bytes of each line matched against rules (0 = no limit)
+    max_line_length: usize,
+    /// Output buffer reused by `colorize_into`
+    buffer: String,
 }
 
 impl Colorizer {
@@ -108,56 +381,195 @@ impl Colorizer {
     pub fn new(rules: impl Into<Arc<[Rule]>>) -> Self {
         let rules: Arc<[Rule]> = rules.into();
         let theme = Theme::default();
-        let rule_styles = Self::compute_styles(&rules, &theme);
+        let color_depth = ColorDepth::default();
+        let rule_styles = Self::compute_styles(&rules, &theme, color_depth);
         let colorizable_indices = Self::compute_colorizable_indices(&rules);
+        let prefilter = Self::compute_prefilter(&rules);
+        let field_keys = Self::compute_field_keys(&rules);
+        let state_names = Self::compute_state_names(&rules);
+        let states = smallvec::smallvec![false; state_names.len()];
+        let field_styles = FieldStyles::new(&theme, color_depth);
 
         Self {
             rules,
             rule_styles,
             colorizable_indices,
+            prefilter,
+            field_keys,
+            ansi_mode: AnsiMode::default(),
             theme,
+            color_depth,
             in_block: false,
             block_style: None,
+            state_names,
+            states,
+            structured: None,
+            field_styles,
+            time_format: None,
+            multiline: None,
+            record_style: None,
+            record_skipped: false,
+            in_continuation: false,
+            filter: None,
+            min_level: None,
+            delta: None,
+            dedup: None,
             color_enabled: true,
+            max_line_length: DEFAULT_MAX_LINE_LENGTH,
+            buffer: String::new(),
         }
     }
 
     /// Set the theme (recomputes styles).
     #[must_use]
     pub fn with_theme(mut self, theme: Theme) -> Self {
-        self.rule_styles = Self::compute_styles(&self.rules, &theme);
+        self.rule_styles = Self::compute_styles(&self.rules, &theme, self.color_depth);
+        self.field_styles = FieldStyles::new(&theme, self.color_depth);
         self.theme = theme;
         self
     }
 
-    /// Compute styles for all rules with a given theme.
-    fn compute_styles(rules: &[Rule], theme: &Theme) -> Arc<[Style]> {
+    /// Set the terminal color depth (recomputes styles).
+    ///
+    /// Theme colors are mapped to the nearest 256-color or 16-color palette
+    /// entry; [`ColorDepth::None`] keeps only text attributes.
+    #[must_use]
+    pub fn with_color_depth(mut self, depth: ColorDepth) -> Self {
+        self.rule_styles = Self::compute_styles(&self.rules, &self.theme, depth);
+        self.field_styles = FieldStyles::new(&self.theme, depth);
+        self.color_depth = depth;
+        self
+    }
+
+    /// Compute styles for all rules with a given theme and color depth.
+    fn compute_styles(rules: &[Rule], theme: &Theme, depth: ColorDepth) -> Arc<[RuleStyle]> {
+        let hashed_palette = theme.hashed_palette();
         rules
             .iter()
             .map(|rule| {
+                // Rule-level background and attributes layer over the theme style
+                let background = rule
+                    .background
+                    .as_ref()
+                    .and_then(|bg| theme.resolve_color(bg).to_background_style().background);
+                let finish = |style: Style| {
+                    let style = rule.attributes.apply(style);
+                    let style = background.map_or(style, |bg| style.on(bg));
+                    let style = if rule.bold { style.bold() } else { style };
+                    depth.downsample(style)
+                };
+
+                let hashed: SmallVec<[bool; 4]> = if rule.has_group_colors() {
+                    rule.colors.iter().map(|c| *c == Color::Hashed).collect()
+                } else {
+                    smallvec::smallvec![rule.colors.contains(&Color::Hashed)]
+                };
+                let palette_over = |style: Style| {
+                    if !hashed.contains(&true) {
+                        return Vec::new();
+                    }
+                    hashed_palette
+                        .iter()
+                        .map(|color| finish(merge_styles(style, color.to_style())))
+                        .collect()
+                };
+
+                if rule.has_group_colors() {
+                    let style_of = |color| finish(theme.resolve_style(color));
+                    return RuleStyle {
+                        base: style_of(&rule.colors[0]),
+                        groups: rule.colors[1..].iter().map(style_of).collect(),
+                        palette: palette_over(Style::new()),
+                        hashed,
+                    };
+                }
+
+                // Later colors override earlier ones; attributes accumulate
                 let base = rule
                     .colors
                     .iter()
-                    .filter_map(|color| theme.resolve_color(color).to_style().foreground)
-                    .fold(Style::new(), |style, fg| style.fg(fg));
-
-                if rule.bold {
-                    base.bold()
-                } else {
-                    base
+                    .map(|color| theme.resolve_style(color))
+                    .fold(Style::new(), merge_styles);
+
+                RuleStyle {
+                    base: finish(base),
+                    groups: SmallVec::new(),
+                    palette: palette_over(base),
+                    hashed,
                 }
             })
             .collect()
     }
 
-    /// Pre-compute indices of rules that can produce colored output.
+    /// Pre-compute indices of rules that can produce colored output,
+    /// ordered by precedence (highest priority first, then rule order).
     fn compute_colorizable_indices(rules: &[Rule]) -> Arc<[usize]> {
-        rules
+        let mut indices: Vec<usize> = rules
             .iter()
             .enumerate()
-            .filter(|(_, rule)| !(rule.skip || rule.replace.is_some() && rule.colors.is_empty()))
+            .filter(|(_, rule)| {
+                // Replace rules and state changes without colors only transform or track
+                let silent = rule.replace.is_some() || rule.enter.is_some() || rule.leave.is_some();
+                !(rule.skip || silent && rule.colors.is_empty())
+            })
             .map(|(idx, _)| idx)
-            .collect()
+            .collect();
+        indices.sort_by_key(|&idx| Reverse(rules[idx].priority));
+        indices.into()
+    }
+
+    /// Compile all rule patterns into a single set for candidate selection.
+    ///
+    /// Field rules are matched against values rather than the whole line, so
+    /// they are always candidates. Falls back to per-rule matching if the set
+    /// exceeds regex size limits.
+    fn compute_prefilter(rules: &[Rule]) -> Option<Arc<RegexSet>> {
+        if rules.len() < 2 {
+            return None;
+        }
+        let patterns = rules.iter().map(|rule| match rule.field {
+            Some(_) => "",
+            None => rule.regex.as_str(),
+        });
+        RegexSet::new(patterns).ok().map(Arc::new)
+    }
+
+    /// Collect the distinct field names targeted by field rules.
+    fn compute_field_keys(rules: &[Rule]) -> Arc<[String]> {
+        let mut keys: Vec<String> = Vec::new();
+        for name in rules.iter().filter_map(|rule| rule.field.as_ref()) {
+            if !keys.contains(name) {
+                keys.push(name.clone());
+            }
+        }
+        keys.into()
+    }
+
+    /// Collect the distinct named states rules enter, leave or depend on.
+    fn compute_state_names(rules: &[Rule]) -> Arc<[String]> {
+        let mut names: Vec<String> = Vec::new();
+        let used = rules.iter().flat_map(|r| [&r.state, &r.enter, &r.leave]);
+        for name in used.flatten() {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+        names.into()
+    }
+
+    /// Find the rules that match somewhere in `line`.
+    fn candidates(&self, line: &str) -> Candidates {
+        match &self.prefilter {
+            Some(set) => Candidates::Matched(set.matches(line)),
+            None => Candidates::All,
+        }
+    }
+
+    /// Set how escape sequences already present in the input are handled.
+    #[must_use]
+    pub fn with_ansi_mode(mut self, mode: AnsiMode) -> Self {
+        self.ansi_mode = mode;
+        self
     }
 
     /// Enable or disable color output.
@@ -170,13 +582,151 @@ impl Colorizer {
         self
     }
 
+    /// Set how many bytes of each line are colorized (0 = no limit).
+    ///
+    /// Rules only see the first `max` bytes of longer lines, so levels and
+    /// other leading fields keep their colors while the tail is passed through.
+    #[must_use]
+    pub fn with_max_line_length(mut self, max: usize) -> Self {
+        self.max_line_length = max;
+        self
+    }
+
+    /// Render JSON and logfmt lines as `timestamp LEVEL message key=value ...`.
+    ///
+    /// Timestamp, level and keys take theme colors, values are colored by
+    /// type where no rule matches them, and rules still apply to the message.
+    /// Lines that do not parse are colorized as usual.
+    #[must_use]
+    pub fn with_structured(mut self, names: Option<FieldNames>) -> Self {
+        self.structured = names;
+        self
+    }
+
+    /// Rewrite the first timestamp of each line in `format`.
+    ///
+    /// In rendered structured lines, the timestamp field is rewritten instead.
+    /// See [`timestamp`] for the recognized formats.
+    #[must_use]
+    pub fn with_time_format(mut self, format: Option<TimeFormat>) -> Self {
+        self.time_format = format;
+        self
+    }
+
+    /// Group lines into multi-line records (stack traces, wrapped messages).
+    ///
+    /// The stdio and parallel processors then style, filter, count and alert
+    /// on each record as a unit; see [`colorize_record`](Self::colorize_record).
+    #[must_use]
+    pub fn with_multiline(mut self, rules: Option<MultilineRules>) -> Self {
+        self.multiline = rules;
+        self
+    }
+
+    /// The rules used to group lines into records, if enabled.
+    #[must_use]
+    pub fn multiline(&self) -> Option<&MultilineRules> {
+        self.multiline.as_ref()
+    }
+
+    /// Only write lines selected by `filter`, with its context lines.
+    ///
+    /// Applies to the stdio and parallel processors. Every line is still
+    /// colorized, so block and named states follow the whole stream, and
+    /// counted in stats and alerts.
+    #[must_use]
+    pub fn with_filter(mut self, filter: Option<LineFilter>) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// The output filter, if enabled.
+    #[must_use]
+    pub fn filter(&self) -> Option<&LineFilter> {
+        self.filter.as_ref()
+    }
+
+    /// Only write lines at `level` or above.
+    ///
+    /// Levels are recognized by the rules colored with a level color, so each
+    /// program's own vocabulary applies; see [`LevelFilter`]. Like
+    /// [`with_filter`](Self::with_filter), hidden lines are still colorized and
+    /// counted.
+    #[must_use]
+    pub fn with_min_level(mut self, level: Option<LogLevel>) -> Self {
+        self.min_level = level;
+        self
+    }
+
+    /// Prefix each written line with the time since the previous one.
+    ///
+    /// Gaps over the tracker's thresholds take the theme's warn and error colors.
+    #[must_use]
+    pub fn with_delta(mut self, delta: Option<DeltaTracker>) -> Self {
+        self.delta = delta;
+        self
+    }
+
+    /// Collapse runs of repeated lines into one line and a repeat count.
+    ///
+    /// Like [`with_filter`](Self::with_filter), dropped repeats are still
+    /// colorized and counted.
+    #[must_use]
+    pub fn with_dedup(mut self, mode: Option<DedupMode>) -> Self {
+        self.dedup = mode;
+        self
+    }
+
+    /// Wrap `out` in a writer applying the level and line filters, time deltas
+    /// and repeat collapsing.
+    #[must_use]
+    pub fn filtered_output<W: Write>(&self, out: W) -> FilteredOutput<W> {
+        let level = self.min_level.map(|min| {
+            LevelFilter::new(min)
+                .with_rules(&self.rules)
+                .with_field_names(self.structured.clone().unwrap_or_default())
+        });
+        let style = |color| {
+            let style = self.theme.resolve_style(&Color::Semantic(color));
+            self.color_depth.downsample(style)
+        };
+        let delta = self.delta.clone().map(|delta| {
+            if self.color_enabled {
+                let gaps = [
+                    SemanticColor::Metric,
+                    SemanticColor::Warn,
+                    SemanticColor::Error,
+                ];
+                delta.with_styles(gaps.map(style))
+            } else {
+                delta
+            }
+        });
+        let dedup = self.dedup.map(|mode| {
+            let dedup = Dedup::new(mode);
+            if self.color_enabled {
+                dedup.with_style(style(SemanticColor::Metric))
+            } else {
+                dedup
+            }
+        });
+        FilteredOutput::new(out, self.filter.clone())
+            .with_level(level)
+            .with_delta(delta)
+            .with_dedup(dedup)
+    }
+
     /// Reset colorization state between files or streams.
     ///
-    /// This clears block mode state that may persist from a previous stream.
-    /// Called automatically after `process_stdio()` and `process_stdio_with_stats()`.
+    /// This clears block mode, named states and record state that may persist
+    /// from a previous stream. Called automatically after `process_stdio()` and
+    /// `process_stdio_with_stats()`.
     pub fn reset(&mut self) {
         self.in_block = false;
         self.block_style = None;
+        self.states.fill(false);
+        self.record_style = None;
+        self.record_skipped = false;
     }
 
     /// Colorize a single line of text.
@@ -206,174 +756,778 @@ impl Colorizer {
     /// Colorize with skip support and match info.
     /// Returns None if a skip rule matched, otherwise Some((output, `had_matches`)).
     pub fn colorize_opt_with_match_info(&mut self, line: &str) -> Option<(String, bool)> {
+        let mut output = String::new();
+        let (had_matches, _) = self.colorize_line(line, &mut output);
+        had_matches.map(|had_matches| (output, had_matches))
+    }
+
+    /// Colorize a line straight into a writer, reusing an internal buffer.
+    ///
+    /// Avoids the per-line `String` of [`colorize`](Self::colorize) when embedding
+    /// phos in a pipeline. No line ending is written. Returns None if a skip rule
+    /// matched (nothing is written), otherwise Some(`had_matches`).
+    pub fn colorize_into<W: Write + ?Sized>(
+        &mut self,
+        line: &str,
+        out: &mut W,
+    ) -> io::Result<Option<bool>> {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        buffer.clear();
+        let (had_matches, _) = self.colorize_line(line, &mut buffer);
+        let written = out.write_all(buffer.as_bytes());
+        self.buffer = buffer;
+        written.map(|()| had_matches)
+    }
+
+    /// Colorize a line, appending the output to `buf`.
+    ///
+    /// For callers that manage their own reusable buffer. Returns None if a skip
+    /// rule matched (nothing is appended), otherwise Some(`had_matches`).
+    pub fn colorize_append(&mut self, line: &str, buf: &mut String) -> Option<bool> {
+        self.colorize_line(line, buf).0
+    }
+
+    /// Colorize the lines of a multi-line record.
+    ///
+    /// The head line decides for the whole record: if a skip rule drops it, all
+    /// lines are dropped, and the style of the first log-level rule matching it
+    /// (e.g. `ERROR`) colors the continuation lines' unmatched text. Rules still
+    /// apply to every line. A `continued` record extends the previous one.
+    ///
+    /// Returns one entry per line, as [`colorize_opt_with_match_info`](Self::colorize_opt_with_match_info) would.
+    pub fn colorize_record<S: AsRef<str>>(
+        &mut self,
+        lines: &[S],
+        continued: bool,
+    ) -> Vec<Option<(String, bool)>> {
+        let mut results = Vec::with_capacity(lines.len());
+        let mut continuation = lines;
+        if !continued {
+            let Some((head, rest)) = lines.split_first() else {
+                return results;
+            };
+            let head = head.as_ref();
+            let colored = self.colorize_opt_with_match_info(head);
+            self.record_skipped = colored.is_none();
+            self.record_style = self.level_style(head);
+            results.push(colored);
+            continuation = rest;
+        }
+
+        if self.record_skipped {
+            results.resize(lines.len(), None);
+            return results;
+        }
+        self.in_continuation = true;
+        for line in continuation {
+            results.push(self.colorize_opt_with_match_info(line.as_ref()));
+        }
+        self.in_continuation = false;
+        results
+    }
+
+    /// Base style of the highest-precedence log-level rule matching `line`.
+    fn level_style(&self, line: &str) -> Option<Style> {
+        let line = strip_ansi(line);
+        let values = self.field_values(&line, None);
+        self.colorizable_indices
+            .iter()
+            .copied()
+            .filter(|&idx| self.rules[idx].colors.iter().any(Color::is_level))
+            .find(|&idx| self.is_rule_match(&Candidates::All, &values, idx, &line))
+            .map(|idx| self.rule_styles[idx].base)
+    }
+
+    /// Style for text no rule colored: the block style, else the record style
+    /// on continuation lines.
+    #[inline]
+    fn gap_style(&self) -> Option<Style> {
+        match (self.in_block, self.in_continuation) {
+            (true, _) => self.block_style,
+            (false, true) => self.record_style,
+            (false, false) => None,
+        }
+    }
+
+    /// Colorize one line into `out`, also reporting whether a block or unblock rule fired on it.
+    ///
+    /// Returns None if the line was skipped, otherwise Some(`had_matches`).
+    fn colorize_line(&mut self, line: &str, out: &mut String) -> (Option<bool>, bool) {
         // Handle edge cases
-        match line.len() {
-            0 => return Some((String::new(), false)),
-            n if n > MAX_LINE_LENGTH => return Some((line.to_string(), false)),
-            _ => {}
+        if line.is_empty() {
+            return (Some(false), false);
         }
 
-        // Phase 1: Check skip rules
+        // Structured lines (JSON, logfmt) are laid out compactly first
+        let rendered = self.render_structured(line);
+        let rewritten = match self.time_format {
+            Some(format) if rendered.is_none() => timestamp::rewrite(line, format),
+            _ => Cow::Borrowed(line),
+        };
+        let (line, parts) = match &rendered {
+            Some(rendered) => (rendered.text.as_str(), rendered.parts.as_slice()),
+            None => (rewritten.as_ref(), &[][..]),
+        };
+
+        // Long lines: colorize a bounded head, pass the tail through
+        let (line, tail) = self.split_long_line(line);
+
+        // Phase 0: Split off escape sequences already in the input so rules see plain text
+        let stripped = (self.ansi_mode != AnsiMode::Raw && contains_ansi(line))
+            .then(|| ansi::split_escapes(line));
+        let (line, escapes, parts) = match &stripped {
+            // Part positions refer to the text with escapes; drop them
+            Some((plain, escapes)) => (plain.as_str(), escapes.as_slice(), &[][..]),
+            None => (line, &[][..], parts),
+        };
+
+        // Phase 1: Select candidate rules in one pass over the rule set, and
+        // locate the field values field rules match against
+        let candidates = self.candidates(line);
+        let values = self.field_values(line, rendered.as_ref().filter(|_| stripped.is_none()));
+
+        // Phase 2: Check skip rules
         let should_skip = self
             .rules
             .iter()
-            .any(|rule| rule.skip && rule.is_match(line));
+            .enumerate()
+            .filter(|(_, rule)| rule.skip)
+            .any(|(idx, _)| self.is_rule_match(&candidates, &values, idx, line));
         if should_skip {
-            return None;
+            return (None, false);
         }
 
-        // Phase 2: Apply replacements functionally (avoid clone when no replacement)
-        let line: Cow<str> =
-            self.rules
-                .iter()
-                .fold(Cow::Borrowed(line), |acc, rule| match &rule.replace {
-                    Some(replacement) if rule.is_match(&acc) => {
-                        Cow::Owned(rule.regex.replace_all(&acc, replacement).into_owned())
+        // Phase 3: Apply replacements functionally (avoid clone when no replacement).
+        // Candidates are only valid for the original text, so once a replacement
+        // rewrites the line, later rules are checked directly.
+        let (line, values) = self.rules.iter().enumerate().fold(
+            (Cow::Borrowed(line), values),
+            |(acc, values), (idx, rule)| {
+                let rewritten = matches!(acc, Cow::Owned(_));
+                match &rule.replace {
+                    Some(_) if !self.applies(rule, &acc) => (acc, values),
+                    Some(replacement) if rule.field.is_some() => {
+                        match self.replace_in_fields(rule, replacement, &acc, &values) {
+                            Some((text, values)) => (Cow::Owned(text), values),
+                            None => (acc, values),
+                        }
                     }
-                    _ => acc,
-                });
+                    Some(replacement)
+                        if Self::rule_matches(&candidates, &acc, rewritten, idx, rule) =>
+                    {
+                        let text = rule.regex.replace_all(&acc, replacement).into_owned();
+                        let values = self.field_values(&text, None);
+                        (Cow::Owned(text), values)
+                    }
+                    _ => (acc, values),
+                }
+            },
+        );
+        let (candidates, escapes, parts) = match line {
+            Cow::Borrowed(_) => (candidates, escapes, parts),
+            // Escape and part positions no longer line up with rewritten text; drop them
+            Cow::Owned(ref rewritten) => (self.candidates(rewritten), &[][..], &[][..]),
+        };
+
+        // Phase 4: Update block mode and named states (side effects isolated here)
+        let block_transition = self.update_block_state(&line, &candidates, &values);
+        self.update_named_states(&line, &candidates, &values);
+
+        // Phase 5: Collect colored ranges functionally
+        let colored_ranges = self.collect_colored_ranges(&line, &candidates, &values, parts);
+        let had_matches = !colored_ranges.is_empty();
 
-        // Phase 3: Update block mode state (side effect isolated here)
-        self.update_block_state(&line);
+        // Phase 6: Build output
+        let keep_sgr = self.ansi_mode == AnsiMode::Merge;
+        match (self.color_enabled, escapes.is_empty()) {
+            (false, true) => out.push_str(&line),
+            (false, false) => ansi::weave(out, &line, escapes, keep_sgr),
+            // Note: colored_ranges is already sorted and disjoint (flattened span tree)
+            (true, true) => self.build_colored_output(out, &line, &colored_ranges),
+            (true, false) => self.build_merged_output(out, &line, &colored_ranges, escapes),
+        }
+        match (tail.is_empty(), self.color_enabled) {
+            (true, _) => {}
+            (false, true) => self.write_segment(out, tail),
+            (false, false) => out.push_str(tail),
+        }
+        (Some(had_matches), block_transition)
+    }
 
-        // Phase 4: Collect colored ranges functionally
-        let colored_ranges = self.collect_colored_ranges(&line);
-        let had_matches = !colored_ranges.is_empty();
+    /// Lay out a JSON or logfmt line, if structured mode is on and the line parses.
+    fn render_structured(&self, line: &str) -> Option<Rendered> {
+        let names = self.structured.as_ref()?;
+        let mut fields = structured::parse(line)?;
+        if let Some(format) = self.time_format {
+            let field = names
+                .timestamp
+                .iter()
+                .find_map(|name| fields.iter().position(|f| f.key == name.as_str()));
+            if let Some(field) = field.map(|idx| &mut fields[idx]) {
+                if let Some(time) = timestamp::parse(&field.value) {
+                    field.value = Cow::Owned(format.format(time, Utc::now()));
+                }
+            }
+        }
+        Some(structured::render(&fields, names))
+    }
+
+    /// Split a line into the head matched against rules and the tail beyond the limit.
+    fn split_long_line<'a>(&self, line: &'a str) -> (&'a str, &'a str) {
+        if self.max_line_length == 0 || line.len() <= self.max_line_length {
+            return (line, "");
+        }
+        let mut cut = self.max_line_length;
+        while !line.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        line.split_at(cut)
+    }
+
+    /// Whether a rule matches `text`, trusting the prefilter unless `text` was rewritten.
+    #[inline]
+    fn rule_matches(
+        candidates: &Candidates,
+        text: &str,
+        rewritten: bool,
+        idx: usize,
+        rule: &Rule,
+    ) -> bool {
+        if rewritten {
+            rule.is_match(text)
+        } else {
+            candidates.is_match(idx, rule, text)
+        }
+    }
+
+    /// Locate the values of the fields field rules target.
+    ///
+    /// Uses the positions recorded while rendering `line`, if given, else
+    /// parses `line` as JSON or logfmt.
+    fn field_values(&self, line: &str, rendered: Option<&Rendered>) -> FieldValues {
+        if self.field_keys.is_empty() {
+            return FieldValues::new();
+        }
+        let key_index = |key: &str| self.field_keys.iter().position(|k| k == key);
+        match rendered {
+            Some(rendered) => rendered
+                .fields
+                .iter()
+                .filter_map(|(key, range)| Some((key_index(key)?, range.clone())))
+                .collect(),
+            None => structured::parse(line)
+                .into_iter()
+                .flatten()
+                .filter_map(|field| Some((key_index(&field.key)?, field.span)))
+                .collect(),
+        }
+    }
+
+    /// Ranges of `line` a rule is matched against: the whole line, or the
+    /// values of its field.
+    fn rule_ranges(
+        &self,
+        rule: &Rule,
+        line: &str,
+        values: &FieldValues,
+    ) -> SmallVec<[Range<usize>; 2]> {
+        let Some(name) = &rule.field else {
+            return smallvec::smallvec![0..line.len()];
+        };
+        let key = self.field_keys.iter().position(|k| k == name);
+        values
+            .iter()
+            .filter(|(idx, range)| Some(*idx) == key && range.end <= line.len())
+            .map(|(_, range)| range.clone())
+            .collect()
+    }
+
+    /// Whether rule `idx` matches `line`; field rules only look at their field's values.
+    fn is_rule_match(
+        &self,
+        candidates: &Candidates,
+        values: &FieldValues,
+        idx: usize,
+        line: &str,
+    ) -> bool {
+        let rule = &self.rules[idx];
+        if !self.applies(rule, line) {
+            return false;
+        }
+        match rule.field {
+            None => candidates.is_match(idx, rule, line),
+            Some(_) => self
+                .rule_ranges(rule, line, values)
+                .into_iter()
+                .any(|range| rule.is_match(&line[range])),
+        }
+    }
+
+    /// Whether a rule's `when`/`unless` conditions and named state allow it on `line`.
+    #[inline]
+    fn applies(&self, rule: &Rule, line: &str) -> bool {
+        let in_state = rule.state.as_ref().is_none_or(|name| {
+            let idx = self.state_names.iter().position(|n| n == name);
+            idx.is_some_and(|idx| self.states[idx])
+        });
+        in_state && rule.applies_to(line)
+    }
 
-        // Phase 5: Build output
-        if !self.color_enabled {
-            return Some((line.into_owned(), had_matches));
+    /// Apply a field rule's replacement inside its field's values.
+    ///
+    /// Returns the rewritten text with value positions shifted to match, or
+    /// None if nothing was replaced.
+    fn replace_in_fields(
+        &self,
+        rule: &Rule,
+        replacement: &str,
+        text: &str,
+        values: &FieldValues,
+    ) -> Option<(String, FieldValues)> {
+        let mut out = String::with_capacity(text.len());
+        let mut cursor = 0;
+        // Replaced ranges with the length of their new text
+        let mut edits: SmallVec<[(Range<usize>, usize); 2]> = SmallVec::new();
+        for range in self.rule_ranges(rule, text, values) {
+            let replaced = rule.regex.replace_all(&text[range.clone()], replacement);
+            if let Cow::Owned(replaced) = replaced {
+                out.push_str(&text[cursor..range.start]);
+                out.push_str(&replaced);
+                cursor = range.end;
+                edits.push((range, replaced.len()));
+            }
         }
+        if edits.is_empty() {
+            return None;
+        }
+        out.push_str(&text[cursor..]);
 
-        // Note: colored_ranges is already sorted by collect_colored_ranges (binary search insert)
-        Some((
-            self.build_colored_output(&line, &colored_ranges),
-            had_matches,
-        ))
+        let shift = |pos: usize| {
+            edits
+                .iter()
+                .filter(|(range, _)| range.end <= pos)
+                .fold(pos, |pos, (range, len)| pos - range.len() + len)
+        };
+        let values = values
+            .iter()
+            .map(|(key, range)| {
+                let start = shift(range.start);
+                let end = match edits.iter().find(|(edited, _)| edited == range) {
+                    Some((_, len)) => start + len,
+                    None => shift(range.end),
+                };
+                (*key, start..end)
+            })
+            .collect();
+        Some((out, values))
     }
 
     /// Update block mode state based on rules (isolated side effect).
-    fn update_block_state(&mut self, line: &str) {
+    /// Returns whether a block or unblock rule matched the line.
+    fn update_block_state(
+        &mut self,
+        line: &str,
+        candidates: &Candidates,
+        values: &FieldValues,
+    ) -> bool {
         // Find first matching block/unblock rule (early exit for common case)
         let block_match = self
             .rules
             .iter()
             .enumerate()
             .filter(|(_, rule)| matches!(rule.count_mode, CountMode::Block | CountMode::Unblock))
-            .find(|(_, rule)| rule.is_match(line));
+            .find(|&(idx, _)| self.is_rule_match(candidates, values, idx, line));
 
         // Apply update if found
-        if let Some((idx, rule)) = block_match {
-            match rule.count_mode {
-                CountMode::Block => {
-                    self.in_block = true;
-                    // Use pre-computed style
-                    self.block_style = Some(self.rule_styles[idx]);
-                }
-                CountMode::Unblock => {
-                    self.in_block = false;
-                    self.block_style = None;
-                }
-                _ => {}
+        let Some((idx, rule)) = block_match else {
+            return false;
+        };
+        match rule.count_mode {
+            CountMode::Block => {
+                self.in_block = true;
+                // Use pre-computed style
+                self.block_style = Some(self.rule_styles[idx].base);
             }
+            CountMode::Unblock => {
+                self.in_block = false;
+                self.block_style = None;
+            }
+            _ => {}
         }
+        true
     }
 
-    /// Collect non-overlapping colored ranges from all rules.
-    /// Returns (start, end, `style_index`) tuples using stack allocation for typical cases.
-    fn collect_colored_ranges(&self, line: &str) -> MatchRanges {
-        let mut ranges: MatchRanges = SmallVec::new();
+    /// Enter and leave named states on rules matching the line (isolated side effect).
+    ///
+    /// Conditions are checked against the states active before the line, so
+    /// all rules on one line see the same states.
+    fn update_named_states(&mut self, line: &str, candidates: &Candidates, values: &FieldValues) {
+        if self.state_names.is_empty() {
+            return;
+        }
+        let transitions: SmallVec<[(usize, bool); 2]> = self
+            .rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.enter.is_some() || rule.leave.is_some())
+            .filter(|&(idx, _)| self.is_rule_match(candidates, values, idx, line))
+            .flat_map(|(_, rule)| [(&rule.enter, true), (&rule.leave, false)])
+            .filter_map(|(name, active)| {
+                let name = name.as_ref()?;
+                let idx = self.state_names.iter().position(|n| n == name)?;
+                Some((idx, active))
+            })
+            .collect();
+        for (idx, active) in transitions {
+            self.states[idx] = active;
+        }
+    }
+
+    /// Collect colored ranges from all rules, resolving overlaps by precedence.
+    ///
+    /// Rules are visited from highest to lowest precedence (priority, then rule
+    /// order). A match is kept unless it partially overlaps a kept match, so kept
+    /// matches form a span tree: each is either disjoint from or nested in the
+    /// others. The tree is then flattened into sorted, disjoint
+    /// (start, end, style) ranges where the highest-precedence span covering a
+    /// position provides its style. Matching stops once the per-line span
+    /// budget is spent.
+    ///
+    /// `parts` of a rendered structured line join the tree too: timestamps,
+    /// levels and keys ahead of all rules, values behind them.
+    fn collect_colored_ranges(
+        &self,
+        line: &str,
+        candidates: &Candidates,
+        values: &FieldValues,
+        parts: &[(usize, usize, Part)],
+    ) -> MatchRanges {
+        let mut spans: SmallVec<[Span; 8]> = SmallVec::new();
+
+        let parts = parts
+            .iter()
+            .filter(|&&(_, end, _)| end <= line.len())
+            .filter_map(|&(start, end, part)| {
+                let (style, strong) = self.field_styles.get(part)?;
+                let rank = if strong { 0 } else { usize::MAX };
+                Some(Span::new(start, end, style, rank))
+            });
+        spans.extend(parts.clone().filter(|span| span.rank == 0));
 
-        // Process only pre-computed colorizable rules (skip filtering per-line)
-        for &idx in self.colorizable_indices.iter() {
+        // Process only colorizable rules the prefilter says can match this line
+        // (ranked after the strong parts)
+        let indices = self
+            .colorizable_indices
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|&(_, idx)| candidates.contains(idx) && self.applies(&self.rules[idx], line))
+            .map(|(rank, idx)| (rank + 1, idx));
+
+        for (rank, idx) in indices {
             let rule = &self.rules[idx];
-            let limit = match rule.count_mode {
+            let style = &self.rule_styles[idx];
+            let kept = spans.len();
+            let mut limit = match rule.count_mode {
                 CountMode::Once => 1,
                 _ => usize::MAX,
             };
 
-            // Find matches and add non-overlapping ones
-            for m in rule.find_iter(line).take(limit) {
-                let (start, end) = (m.start(), m.end());
-
-                // Check overlap with existing ranges using binary search on sorted ranges
-                // Find insertion point for this start position
-                let insert_pos = ranges
-                    .binary_search_by_key(&start, |(s, _, _)| *s)
-                    .unwrap_or_else(|pos| pos);
-
-                // Check for overlap with adjacent ranges only (O(1) instead of O(n))
-                let overlaps_prev = insert_pos > 0
-                    && ranges
-                        .get(insert_pos - 1)
-                        .is_some_and(|(_, prev_end, _)| *prev_end > start);
-                let overlaps_next = ranges
-                    .get(insert_pos)
-                    .is_some_and(|(next_start, _, _)| *next_start < end);
-
-                if !overlaps_prev && !overlaps_next {
-                    ranges.insert(insert_pos, (start, end, idx));
+            // Field rules match each value of their field on its own
+            for range in self.rule_ranges(rule, line, values) {
+                let (text, offset) = (&line[range.clone()], range.start);
+                if style.groups.is_empty() {
+                    for m in rule.find_iter(text).take(limit) {
+                        if spans.len() >= MAX_SPANS_PER_LINE {
+                            break;
+                        }
+                        limit -= 1;
+                        let (start, end) = (offset + m.start(), offset + m.end());
+                        if !m.is_empty() && Self::fits(&spans, start, end) {
+                            let base = style.pick(0, style.base, m.as_str());
+                            spans.push(Span::new(start, end, base, rank));
+                        }
+                    }
+                } else {
+                    for caps in rule.regex.captures_iter(text).take(limit) {
+                        if spans.len() >= MAX_SPANS_PER_LINE {
+                            break;
+                        }
+                        limit -= 1;
+                        let whole = caps.get(0).expect("group 0 always participates");
+                        let (start, end) = (offset + whole.start(), offset + whole.end());
+                        if whole.is_empty() || !Self::fits(&spans, start, end) {
+                            continue;
+                        }
+                        // The whole match carries the base style; group pieces nest inside it
+                        // unless they cross a stronger span already kept in this match's extent
+                        let base = style.pick(0, style.base, whole.as_str());
+                        spans.push(Span::new(start, end, base, rank));
+                        for (start, end, piece_style) in Self::group_pieces(&caps, style, offset) {
+                            if piece_style != base && Self::fits(&spans, start, end) {
+                                spans.push(Span::new(start, end, piece_style, rank));
+                            }
+                        }
+                    }
                 }
             }
+
+            // A stop rule that colored the line ends it for lower-precedence rules
+            if rule.count_mode == CountMode::Stop && spans.len() > kept {
+                break;
+            }
         }
 
-        ranges
-    }
+        // Values keep their type color only where no rule matched
+        for span in parts.filter(|span| span.rank == usize::MAX) {
+            if Self::fits(&spans, span.start, span.end) {
+                spans.push(span);
+            }
+        }
 
-    /// Build the final colored output string.
-    fn build_colored_output(&self, line: &str, ranges: &[(usize, usize, usize)]) -> String {
-        use std::fmt::Write;
+        Self::flatten_spans(&mut spans)
+    }
 
-        if ranges.is_empty() {
-            return self.format_segment(line).into_owned();
-        }
+    /// Whether `start..end` can join the span tree (no partial overlap with a kept span).
+    fn fits(spans: &[Span], start: usize, end: usize) -> bool {
+        spans.iter().all(|span| {
+            let disjoint = span.end <= start || end <= span.start;
+            let inside = span.start <= start && end <= span.end;
+            let around = start <= span.start && span.end <= end;
+            disjoint || inside || around
+        })
+    }
 
-        // Pre-allocate result buffer (line length + estimated ANSI overhead)
-        // Use checked_mul to prevent overflow on pathological inputs
-        let ansi_overhead = ranges.len().checked_mul(20).unwrap_or(0);
-        let capacity = line.len().saturating_add(ansi_overhead);
-        let mut result = String::with_capacity(capacity);
+    /// Flatten a span tree into sorted, disjoint ranges.
+    ///
+    /// Walks spans in pre-order with a stack of open spans; each open span
+    /// carries the effective style for its uncovered text (its own, unless an
+    /// enclosing span has higher precedence). Adjacent pieces with the same
+    /// style are merged so hidden nested spans leave no trace in the output.
+    fn flatten_spans(spans: &mut [Span]) -> MatchRanges {
+        // Stable sort: a match's extent stays ahead of an identical group piece
+        spans.sort_by_key(|span| (span.start, Reverse(span.end), span.rank));
+
+        let mut ranges: MatchRanges = SmallVec::new();
+        let mut push = |start: usize, end: usize, style: Style| {
+            if start >= end {
+                return;
+            }
+            match ranges.last_mut() {
+                Some(last) if last.1 == start && last.2 == style => last.1 = end,
+                _ => ranges.push((start, end, style)),
+            }
+        };
+
+        // Open spans as (end, effective span), innermost last
+        let mut stack: SmallVec<[(usize, Span); 8]> = SmallVec::new();
+        let mut cursor = 0;
+
+        for span in spans.iter() {
+            // Close spans that end before this one starts
+            while let Some(&(end, effective)) = stack.last() {
+                if end > span.start {
+                    break;
+                }
+                push(cursor, end, effective.style);
+                cursor = end;
+                stack.pop();
+            }
+
+            if let Some(&(_, effective)) = stack.last() {
+                push(cursor, span.start, effective.style);
+            }
+            cursor = span.start;
+
+            let effective = match stack.last() {
+                Some(&(_, parent)) if parent.rank < span.rank => parent,
+                _ => *span,
+            };
+            stack.push((span.end, effective));
+        }
+
+        while let Some((end, effective)) = stack.pop() {
+            push(cursor, end, effective.style);
+            cursor = end;
+        }
+
+        ranges
+    }
+
+    /// Split a match into per-group pieces, filling uncaptured gaps with the base style.
+    ///
+    /// Groups are taken in order; a group overlapping an earlier one (nested groups)
+    /// is ignored so the pieces stay sorted and disjoint. Positions are shifted
+    /// by `offset`, where the matched text starts in the line.
+    fn group_pieces(caps: &regex::Captures<'_>, style: &RuleStyle, offset: usize) -> MatchRanges {
+        let whole = caps.get(0).expect("group 0 always participates");
+        let base = style.pick(0, style.base, whole.as_str());
+        let mut pieces: MatchRanges = SmallVec::new();
+        let mut cursor = offset + whole.start();
+
+        let groups = style
+            .groups
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &group_style)| {
+                caps.get(i + 1)
+                    .map(|m| (m, style.pick(i + 1, group_style, m.as_str())))
+            });
+
+        for (m, group_style) in groups {
+            let (start, end) = (offset + m.start(), offset + m.end());
+            if start < cursor || m.is_empty() {
+                continue;
+            }
+            if start > cursor {
+                pieces.push((cursor, start, base));
+            }
+            pieces.push((start, end, group_style));
+            cursor = end;
+        }
+
+        if cursor < offset + whole.end() {
+            pieces.push((cursor, offset + whole.end(), base));
+        }
+
+        pieces
+    }
+
+    /// Append the final colored output to `result`.
+    fn build_colored_output(
+        &self,
+        result: &mut String,
+        line: &str,
+        ranges: &[(usize, usize, Style)],
+    ) {
+        use std::fmt::Write;
+
+        if ranges.is_empty() {
+            self.write_segment(result, line);
+            return;
+        }
+
+        // Reserve room for the line plus estimated ANSI overhead
+        // Use checked_mul to prevent overflow on pathological inputs
+        let ansi_overhead = ranges.len().checked_mul(20).unwrap_or(0);
+        result.reserve(line.len().saturating_add(ansi_overhead));
 
         // Process each range with fold to track position
         let last_end = ranges
             .iter()
-            .fold(0usize, |last_end, &(start, end, style_idx)| {
+            .fold(0usize, |last_end, &(start, end, style)| {
                 // Write uncolored gap
-                self.write_segment(&mut result, &line[last_end..start]);
-                // Write colored section (look up style by index)
-                let _ = write!(
-                    result,
-                    "{}",
-                    self.rule_styles[style_idx].paint(&line[start..end])
-                );
+                self.write_segment(result, &line[last_end..start]);
+                // Write colored section (plain pieces fall back to block style)
+                if style.is_plain() {
+                    self.write_segment(result, &line[start..end]);
+                } else {
+                    let _ = write!(result, "{}", style.paint(&line[start..end]));
+                }
                 end
             });
 
         // Write trailing uncolored text
-        self.write_segment(&mut result, &line[last_end..]);
-
-        result
+        self.write_segment(result, &line[last_end..]);
     }
 
-    /// Write a segment to a buffer, applying block style if in block mode.
-    #[inline]
-    fn write_segment(&self, buf: &mut String, text: &str) {
+    /// Append output for a line whose input escapes were split off, re-inserting them.
+    ///
+    /// Non-color escapes are restored at their original positions. In merge mode
+    /// the input's colors show through outside phos-styled pieces: they are
+    /// suspended inside a piece and replayed after its reset.
+    fn build_merged_output(
+        &self,
+        result: &mut String,
+        line: &str,
+        ranges: &[(usize, usize, Style)],
+        escapes: &[Escape<'_>],
+    ) {
         use std::fmt::Write;
-        match (&self.block_style, self.in_block) {
-            (Some(style), true) => {
-                let _ = write!(buf, "{}", style.paint(text));
+
+        let keep_sgr = self.ansi_mode == AnsiMode::Merge;
+        let gap_style = self.gap_style();
+        let escape_len: usize = escapes.iter().map(|esc| esc.seq.len()).sum();
+        result.reserve(line.len() + escape_len + ranges.len() * 20);
+        let mut input_sgr = SgrState::default();
+        let mut pending = escapes.iter().peekable();
+
+        // Cover the whole line with pieces: phos matches and the gaps between them
+        let mut pieces: SmallVec<[(usize, usize, Option<Style>); 16]> = SmallVec::new();
+        let mut last_end = 0;
+        for &(start, end, style) in ranges {
+            if start > last_end {
+                pieces.push((last_end, start, gap_style));
             }
-            _ => buf.push_str(text),
+            let style = (!style.is_plain()).then_some(style).or(gap_style);
+            pieces.push((start, end, style));
+            last_end = end;
+        }
+        pieces.push((last_end, line.len(), gap_style));
+
+        for (start, end, style) in pieces {
+            // Escapes before the piece behave as if outside any phos style
+            while let Some(esc) = pending.next_if(|esc| esc.pos <= start) {
+                Self::write_escape(result, &mut input_sgr, esc, keep_sgr);
+            }
+
+            let Some(style) = style else {
+                let mut cursor = start;
+                while let Some(esc) = pending.next_if(|esc| esc.pos < end) {
+                    result.push_str(&line[cursor..esc.pos]);
+                    Self::write_escape(result, &mut input_sgr, esc, keep_sgr);
+                    cursor = esc.pos;
+                }
+                result.push_str(&line[cursor..end]);
+                continue;
+            };
+
+            // Phos style wins inside the piece: clear input colors first
+            if keep_sgr && input_sgr.is_active() {
+                result.push_str("\x1b[0m");
+            }
+            let _ = write!(result, "{}", style.prefix());
+            let mut cursor = start;
+            while let Some(esc) = pending.next_if(|esc| esc.pos < end) {
+                result.push_str(&line[cursor..esc.pos]);
+                Self::write_escape(result, &mut input_sgr, esc, false);
+                cursor = esc.pos;
+            }
+            result.push_str(&line[cursor..end]);
+            let _ = write!(result, "{}", style.suffix());
+            if keep_sgr {
+                result.push_str(input_sgr.as_str());
+            }
+        }
+
+        // Escapes after the last character
+        for esc in pending {
+            Self::write_escape(result, &mut input_sgr, esc, keep_sgr);
         }
     }
 
-    /// Format a text segment, applying block style if in block mode.
-    /// Returns Cow to avoid allocation when no styling is needed.
+    /// Write an input escape, tracking color state (colors only written if `emit_sgr`).
     #[inline]
-    fn format_segment<'a>(&self, text: &'a str) -> Cow<'a, str> {
-        match (&self.block_style, self.in_block) {
-            (Some(style), true) => Cow::Owned(style.paint(text).to_string()),
-            _ => Cow::Borrowed(text),
+    fn write_escape(buf: &mut String, input_sgr: &mut SgrState, esc: &Escape<'_>, emit_sgr: bool) {
+        if !esc.is_sgr() {
+            buf.push_str(esc.seq);
+            return;
+        }
+        input_sgr.apply(esc.seq);
+        if emit_sgr {
+            buf.push_str(esc.seq);
+        }
+    }
+
+    /// Write a segment to a buffer, applying the block or record style if active.
+    #[inline]
+    fn write_segment(&self, buf: &mut String, text: &str) {
+        use std::fmt::Write;
+        match self.gap_style() {
+            Some(style) => {
+                let _ = write!(buf, "{}", style.paint(text));
+            }
+            None => buf.push_str(text),
         }
     }
 
@@ -385,18 +1539,15 @@ impl Colorizer {
 
     /// Process stdin with statistics collection.
     /// Lines matching skip rules are not output but are counted in stats.
-    pub fn process_stdio_with_stats(
-        &mut self,
-        stats: &mut crate::stats::StatsCollector,
-    ) -> io::Result<()> {
+    pub fn process_stdio_with_stats(&mut self, stats: &mut StatsCollector) -> io::Result<()> {
         self.process_stdio_inner(Some(stats), None, 0)
     }
 
     /// Process stdin with both statistics collection and alerting.
     pub fn process_stdio_with_alerts(
         &mut self,
-        stats: &mut crate::stats::StatsCollector,
-        alert_manager: &mut crate::alert::AlertManager,
+        stats: &mut StatsCollector,
+        alert_manager: &mut AlertManager,
     ) -> io::Result<()> {
         self.process_stdio_inner(Some(stats), Some(alert_manager), 0)
     }
@@ -406,7 +1557,7 @@ impl Colorizer {
     /// Outputs compact stats to stderr every `interval_secs` seconds.
     pub fn process_stdio_with_stats_interval(
         &mut self,
-        stats: &mut crate::stats::StatsCollector,
+        stats: &mut StatsCollector,
         interval_secs: u64,
     ) -> io::Result<()> {
         self.process_stdio_inner(Some(stats), None, interval_secs)
@@ -415,81 +1566,349 @@ impl Colorizer {
     /// Process stdin with statistics, alerting, and periodic interval output.
     pub fn process_stdio_with_alerts_interval(
         &mut self,
-        stats: &mut crate::stats::StatsCollector,
-        alert_manager: &mut crate::alert::AlertManager,
+        stats: &mut StatsCollector,
+        alert_manager: &mut AlertManager,
         interval_secs: u64,
     ) -> io::Result<()> {
         self.process_stdio_inner(Some(stats), Some(alert_manager), interval_secs)
     }
 
     /// Core stdin processing implementation.
-    ///
-    /// Handles colorization with optional stats, alerts, and periodic output.
     fn process_stdio_inner(
         &mut self,
-        mut stats: Option<&mut crate::stats::StatsCollector>,
-        mut alert_manager: Option<&mut crate::alert::AlertManager>,
+        stats: Option<&mut StatsCollector>,
+        alert_manager: Option<&mut AlertManager>,
         interval_secs: u64,
     ) -> io::Result<()> {
-        use std::time::Instant;
-
-        let stdin = io::stdin();
-        let stdout = io::stdout();
-        let mut stdout = stdout.lock();
-
-        let use_interval = interval_secs > 0 && stats.is_some();
-        let interval = std::time::Duration::from_secs(interval_secs);
-        let mut last_output = Instant::now();
-
-        for line in stdin.lock().lines() {
-            let line = line?;
-
-            // Process with or without match info based on whether stats are enabled
-            let (output, had_match) = stats
-                .as_ref()
-                .map(|_| {
-                    self.colorize_opt_with_match_info(&line)
-                        .map(|(colored, matched)| (Some(colored), matched))
-                        .unwrap_or((None, true)) // Skip rule matched
-                })
-                .unwrap_or_else(|| (self.colorize_opt(&line), false));
+        self.process_reader(
+            io::stdin().lock(),
+            io::stdout().lock(),
+            stats,
+            alert_manager,
+            interval_secs,
+        )
+    }
 
-            // Record stats if enabled
-            if let Some(ref mut s) = stats {
-                s.process_line(&line, had_match);
-                if output.is_none() {
-                    s.record_skipped();
-                }
+    /// Colorize a reader line by line, writing each line as soon as it is read.
+    ///
+    /// Handles colorization with optional stats, alerts, and compact stats
+    /// printed to stderr every `interval_secs` seconds (0 = never).
+    pub fn process_reader<R: BufRead, W: Write>(
+        &mut self,
+        reader: R,
+        writer: W,
+        mut stats: Option<&mut StatsCollector>,
+        mut alert_manager: Option<&mut AlertManager>,
+        interval_secs: u64,
+    ) -> io::Result<()> {
+        let mut stdout = self.filtered_output(writer);
+        let mut ticker = StatsTicker::new(interval_secs);
+
+        // Line and output buffers are reused across lines
+        let mut reader = LineReader::new(reader);
+        let mut line = RawLine::default();
+        let mut colored = String::new();
+        let mut records = self.multiline.clone().map(RecordAssembler::new);
+        while reader.read_line(&mut line)? {
+            if let Some(assembler) = records.as_mut() {
+                // Don't hold a record back while waiting for more input
+                let idle = !reader.has_buffered();
+                self.push_record_line(
+                    assembler,
+                    std::mem::take(&mut line),
+                    idle,
+                    &mut stdout,
+                    stats.as_deref_mut(),
+                    alert_manager.as_deref_mut(),
+                )?;
+            } else {
+                let text = line.to_str_lossy();
+
+                colored.clear();
+                let result = self
+                    .colorize_append(&text, &mut colored)
+                    .map(|had_match| (colored.as_str(), had_match));
+                Self::emit_line(
+                    &mut stdout,
+                    &line,
+                    result,
+                    stats.as_deref_mut(),
+                    alert_manager.as_deref_mut(),
+                )?;
+            }
+
+            ticker.tick(stats.as_deref())?;
+        }
+        if let Some(record) = records.as_mut().and_then(RecordAssembler::flush) {
+            self.emit_record(&mut stdout, &record, stats, alert_manager)?;
+        }
+        stdout.finish()?;
+
+        self.reset();
+        Ok(())
+    }
+
+    /// Feed a line to the record assembler and emit any records it completes.
+    ///
+    /// With `idle` set, the pending record is emitted too.
+    fn push_record_line<W: Write>(
+        &mut self,
+        assembler: &mut RecordAssembler,
+        line: RawLine,
+        idle: bool,
+        out: &mut FilteredOutput<W>,
+        mut stats: Option<&mut StatsCollector>,
+        mut alert_manager: Option<&mut AlertManager>,
+    ) -> io::Result<()> {
+        let complete = assembler.push(line);
+        let pending = if idle { assembler.flush() } else { None };
+        for record in complete.iter().chain(&pending) {
+            self.emit_record(
+                out,
+                record,
+                stats.as_deref_mut(),
+                alert_manager.as_deref_mut(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Colorize a record, record it in stats and alerts as one unit, then write its lines.
+    ///
+    /// A continued record was already counted with the record it extends.
+    fn emit_record<W: Write>(
+        &mut self,
+        out: &mut FilteredOutput<W>,
+        record: &Record,
+        stats: Option<&mut StatsCollector>,
+        alert_manager: Option<&mut AlertManager>,
+    ) -> io::Result<()> {
+        let texts: Vec<Cow<str>> = record.lines().iter().map(RawLine::to_str_lossy).collect();
+        let results = self.colorize_record(&texts, record.is_continued());
+
+        if let Some(s) = stats.filter(|_| !record.is_continued()) {
+            let text = record.text();
+            let skipped = self.record_skipped;
+            let had_match = skipped || results.iter().flatten().any(|(_, matched)| *matched);
+            s.process_line(&text, had_match);
+            if skipped {
+                s.record_skipped();
+            }
+
+            if let Some(alerts) = alert_manager {
+                alerts.check_line(&text, s.error_count(), s.peer_count(), s.slot());
+            }
+        }
+
+        let lines = record.lines().iter().zip(&results);
+        out.write_record(
+            record,
+            lines.filter_map(|(line, result)| Some((line, result.as_ref()?.0.as_str()))),
+        )
+    }
+
+    /// Record a processed line in stats and alerts, then write its output.
+    ///
+    /// `result` is None when a skip rule matched (counted, but not written).
+    fn emit_line<W: Write>(
+        out: &mut FilteredOutput<W>,
+        line: &RawLine,
+        result: Option<(&str, bool)>,
+        stats: Option<&mut StatsCollector>,
+        alert_manager: Option<&mut AlertManager>,
+    ) -> io::Result<()> {
+        let (output, had_match) =
+            result.map_or((None, true), |(colored, matched)| (Some(colored), matched));
+
+        // Record stats if enabled
+        if let Some(s) = stats {
+            let text = line.to_str_lossy();
+            s.process_line(&text, had_match);
+            if output.is_none() {
+                s.record_skipped();
             }
 
             // Check alerts if enabled
-            if let (Some(ref mut alerts), Some(ref s)) = (&mut alert_manager, &stats) {
-                alerts.check_line(&line, s.error_count(), s.peer_count(), s.slot());
+            if let Some(alerts) = alert_manager {
+                alerts.check_line(&text, s.error_count(), s.peer_count(), s.slot());
+            }
+        }
+
+        // Write colorized output
+        let Some(colored) = output else {
+            return Ok(());
+        };
+        out.write_line(line, colored)
+    }
+
+    /// Colorize a reader on `threads` worker threads, writing lines in input order.
+    ///
+    /// Meant for bounded input such as files or fully buffered stdin. Lines are
+    /// read in batches and colorized with [`colorize_parallel`](Self::colorize_parallel);
+    /// stats and alerts are updated in order as lines are written, with compact
+    /// stats printed every `interval_secs` seconds as in
+    /// [`process_reader`](Self::process_reader). `threads == 0` uses all
+    /// available cores. With [multi-line records](Self::with_multiline)
+    /// enabled, records are assembled and colorized on the calling thread.
+    pub fn process_parallel<R: BufRead, W: Write>(
+        &mut self,
+        reader: R,
+        writer: W,
+        mut stats: Option<&mut StatsCollector>,
+        mut alert_manager: Option<&mut AlertManager>,
+        threads: usize,
+        interval_secs: u64,
+    ) -> io::Result<()> {
+        let threads = Self::resolve_threads(threads);
+        let batch_size = threads.saturating_mul(PARALLEL_CHUNK_LINES);
+        let mut lines = LineReader::new(reader);
+        let mut writer = self.filtered_output(writer);
+        let mut ticker = StatsTicker::new(interval_secs);
+
+        // Records can span batch boundaries, so they are assembled in order
+        if let Some(rules) = self.multiline.clone() {
+            let mut assembler = RecordAssembler::new(rules);
+            for line in lines {
+                self.push_record_line(
+                    &mut assembler,
+                    line?,
+                    false,
+                    &mut writer,
+                    stats.as_deref_mut(),
+                    alert_manager.as_deref_mut(),
+                )?;
+                ticker.tick(stats.as_deref())?;
+            }
+            if let Some(record) = assembler.flush() {
+                self.emit_record(&mut writer, &record, stats, alert_manager)?;
             }
+            writer.finish()?;
+            self.reset();
+            return Ok(());
+        }
 
-            // Write colorized output
-            if let Some(colored) = output {
-                writeln!(stdout, "{colored}")?;
+        loop {
+            let batch = lines
+                .by_ref()
+                .take(batch_size)
+                .collect::<io::Result<Vec<RawLine>>>()?;
+            if batch.is_empty() {
+                break;
             }
 
-            // Periodic stats output
-            if use_interval && last_output.elapsed() >= interval {
-                if let Some(ref s) = stats {
-                    writeln!(io::stderr(), "{}", s.to_compact())?;
-                }
-                last_output = Instant::now();
+            let texts: Vec<Cow<str>> = batch.iter().map(RawLine::to_str_lossy).collect();
+            let results = self.colorize_parallel(&texts, threads);
+            for (line, result) in batch.iter().zip(&results) {
+                Self::emit_line(
+                    &mut writer,
+                    line,
+                    result
+                        .as_ref()
+                        .map(|(colored, had_match)| (colored.as_str(), *had_match)),
+                    stats.as_deref_mut(),
+                    alert_manager.as_deref_mut(),
+                )?;
+                ticker.tick(stats.as_deref())?;
             }
         }
 
+        writer.finish()?;
         self.reset();
         Ok(())
     }
+
+    /// Colorize many lines on `threads` worker threads, preserving order.
+    ///
+    /// Returns one entry per input line, as [`colorize_opt_with_match_info`](Self::colorize_opt_with_match_info)
+    /// would. Block mode state is reconciled at chunk boundaries, so the output
+    /// is identical to colorizing the lines one by one; rules with named states
+    /// are run on the calling thread. `threads == 0` uses all available cores.
+    pub fn colorize_parallel<S: AsRef<str> + Sync>(
+        &mut self,
+        lines: &[S],
+        threads: usize,
+    ) -> Vec<Option<(String, bool)>> {
+        let threads = Self::resolve_threads(threads);
+        // Named states can depend on each other across lines, so they are followed in order
+        if threads == 1 || lines.len() <= PARALLEL_CHUNK_LINES || !self.state_names.is_empty() {
+            return lines
+                .iter()
+                .map(|line| self.colorize_opt_with_match_info(line.as_ref()))
+                .collect();
+        }
+
+        let chunk_size = lines.len().div_ceil(threads);
+        let outputs: Vec<ChunkOutput> = thread::scope(|scope| {
+            let handles: Vec<_> = lines
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    let mut worker = self.clone();
+                    worker.reset();
+                    scope.spawn(move || worker.colorize_chunk(chunk))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("colorizer worker panicked"))
+                .collect()
+        });
+
+        // Stitch chunks together in order, carrying block state across boundaries
+        let mut results = Vec::with_capacity(lines.len());
+        for (chunk, output) in lines.chunks(chunk_size).zip(outputs) {
+            let mut colored = output.lines;
+            let prefix = output.first_transition.unwrap_or(chunk.len());
+
+            // The worker assumed no active block; redo lines it got wrong
+            if self.in_block {
+                for (slot, line) in colored[..prefix].iter_mut().zip(chunk) {
+                    *slot = self.colorize_opt_with_match_info(line.as_ref());
+                }
+            }
+            if output.first_transition.is_some() {
+                (self.in_block, self.block_style) = output.exit_state;
+            }
+            results.append(&mut colored);
+        }
+
+        results
+    }
+
+    /// Colorize a chunk from a reset state (runs on a worker thread).
+    fn colorize_chunk<S: AsRef<str>>(mut self, chunk: &[S]) -> ChunkOutput {
+        let mut first_transition = None;
+        let lines = chunk
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| {
+                let mut output = String::new();
+                let (had_matches, transition) = self.colorize_line(line.as_ref(), &mut output);
+                if transition && first_transition.is_none() {
+                    first_transition = Some(idx);
+                }
+                had_matches.map(|had_matches| (output, had_matches))
+            })
+            .collect();
+
+        ChunkOutput {
+            lines,
+            first_transition,
+            exit_state: (self.in_block, self.block_style),
+        }
+    }
+
+    /// Resolve a requested worker count (0 = all available cores).
+    fn resolve_threads(threads: usize) -> usize {
+        match threads {
+            0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            n => n,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::colors::SemanticColor;
 
     #[test]
     fn test_colorizer_creation() {
@@ -554,6 +1973,63 @@ mod tests {
         assert!(result.is_some());
     }
 
+    #[test]
+    fn test_colorize_into_matches_colorize() {
+        let rules = vec![
+            Rule::new(r"DEBUG").unwrap().skip().build(),
+            Rule::new(r"\d+")
+                .unwrap()
+                .semantic(SemanticColor::Number)
+                .build(),
+        ];
+        let mut colorizer = Colorizer::new(rules);
+        let expected = colorizer.clone().colorize("took 15ms");
+
+        let mut out = Vec::new();
+        assert_eq!(
+            colorizer.colorize_into("took 15ms", &mut out).unwrap(),
+            Some(true)
+        );
+        assert_eq!(
+            colorizer.colorize_into(" plain", &mut out).unwrap(),
+            Some(false)
+        );
+        assert_eq!(colorizer.colorize_into("DEBUG 1", &mut out).unwrap(), None);
+        assert_eq!(String::from_utf8(out).unwrap(), format!("{expected} plain"));
+
+        // Appending keeps what the buffer already holds
+        let mut buf = String::from("> ");
+        assert_eq!(colorizer.colorize_append("took 15ms", &mut buf), Some(true));
+        assert_eq!(buf, format!("> {expected}"));
+    }
+
+    #[test]
+    fn test_long_line_head_colorized() {
+        let rules = vec![Rule::new(r"ERROR|\d+").unwrap().named("red").build()];
+        let red = nu_ansi_term::Color::Red;
+        let payload = "x".repeat(DEFAULT_MAX_LINE_LENGTH);
+        let line = format!("ERROR {payload} 42");
+
+        // The level is colored; the number past the limit is passed through
+        let mut colorizer = Colorizer::new(rules.clone());
+        let result = colorizer.colorize(&line);
+        assert_eq!(result, format!("{} {payload} 42", red.paint("ERROR")));
+
+        let mut unlimited = Colorizer::new(rules).with_max_line_length(0);
+        assert!(unlimited
+            .colorize(&line)
+            .ends_with(&red.paint("42").to_string()));
+    }
+
+    #[test]
+    fn test_long_line_cut_on_char_boundary() {
+        let rules = vec![Rule::new(r"é").unwrap().named("red").build()];
+        let mut colorizer = Colorizer::new(rules).with_max_line_length(2);
+        // The limit falls inside the second character
+        let result = colorizer.colorize("aéb");
+        assert_eq!(result, "aéb");
+    }
+
     #[test]
     fn test_replace_rule() {
         let rules = vec![Rule::new(r"(\d{2}):(\d{2}):(\d{2})")
@@ -587,4 +2063,540 @@ mod tests {
         let result = colorizer.colorize_opt("[INFO] 2024-01-15 test").unwrap();
         assert!(result.contains("01/15/2024"));
     }
+
+    #[test]
+    fn test_prefilter_after_replacement() {
+        // The second rule only matches the rewritten text
+        let rules = vec![
+            Rule::new(r"WARNING").unwrap().replace("WARN").build(),
+            Rule::new(r"\bWARN\b")
+                .unwrap()
+                .semantic(SemanticColor::Warn)
+                .build(),
+            Rule::new(r"\d+")
+                .unwrap()
+                .semantic(SemanticColor::Number)
+                .build(),
+        ];
+        let mut colorizer = Colorizer::new(rules);
+        let (result, had_match) = colorizer.colorize_with_match_info("WARNING: disk full");
+        assert!(had_match);
+        assert!(result.contains("WARN\x1b[0m:"));
+
+        // Lines matching no rule pass through untouched
+        assert_eq!(colorizer.colorize("all quiet"), "all quiet");
+    }
+
+    #[test]
+    fn test_single_skip_rule_without_prefilter() {
+        let mut colorizer = Colorizer::new(vec![Rule::new(r"DEBUG").unwrap().skip().build()]);
+        assert!(colorizer.colorize_opt("INFO: kept").is_some());
+        assert!(colorizer.colorize_opt("DEBUG: dropped").is_none());
+    }
+
+    #[test]
+    fn test_stop_rule() {
+        let rules = vec![
+            Rule::new(r"^#")
+                .unwrap()
+                .semantic(SemanticColor::Debug)
+                .count(CountMode::Stop)
+                .build(),
+            Rule::new(r"\d+")
+                .unwrap()
+                .semantic(SemanticColor::Number)
+                .build(),
+        ];
+        let mut colorizer = Colorizer::new(rules);
+        let theme = Theme::default();
+        let debug = theme.resolve_style(&Color::Semantic(SemanticColor::Debug));
+        let number = theme.resolve_style(&Color::Semantic(SemanticColor::Number));
+
+        // The number rule never runs on comment lines
+        assert_eq!(
+            colorizer.colorize("# retry 3"),
+            format!("{} retry 3", debug.paint("#"))
+        );
+        // Lines the stop rule does not match go on to later rules
+        assert_eq!(
+            colorizer.colorize("retry 3"),
+            format!("retry {}", number.paint("3"))
+        );
+    }
+
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let rules = vec![
+            Rule::new(r"BEGIN")
+                .unwrap()
+                .named("yellow")
+                .count(CountMode::Block)
+                .build(),
+            Rule::new(r"END").unwrap().count(CountMode::Unblock).build(),
+            Rule::new(r"^#").unwrap().skip().build(),
+            Rule::new(r"\d+")
+                .unwrap()
+                .semantic(SemanticColor::Number)
+                .build(),
+        ];
+        // Blocks open and close across chunk boundaries
+        let lines: Vec<String> = (0..20_000)
+            .map(|i| match i % 3001 {
+                0 => format!("BEGIN section {i}"),
+                1700 => format!("END section {i}"),
+                n if n % 97 == 0 => format!("# comment {i}"),
+                _ => format!("line {i} value"),
+            })
+            .collect();
+
+        let mut sequential = Colorizer::new(rules.clone());
+        let expected: Vec<_> = lines
+            .iter()
+            .map(|line| sequential.colorize_opt_with_match_info(line))
+            .collect();
+
+        let mut parallel = Colorizer::new(rules);
+        assert_eq!(parallel.colorize_parallel(&lines, 4), expected);
+        assert_eq!(parallel.in_block, sequential.in_block);
+    }
+
+    #[test]
+    fn test_process_parallel_preserves_order() {
+        let rules = vec![Rule::new(r"^skip").unwrap().skip().build()];
+        let input: String = (0..10_000)
+            .map(|i| {
+                if i % 10 == 0 {
+                    format!("skip {i}\n")
+                } else {
+                    format!("keep {i}\n")
+                }
+            })
+            .collect();
+
+        let mut colorizer = Colorizer::new(rules).with_color_enabled(false);
+        let mut out = Vec::new();
+        colorizer
+            .process_parallel(input.as_bytes(), &mut out, None, None, 3, 0)
+            .unwrap();
+
+        let expected: String =
+            input
+                .lines()
+                .filter(|l| l.starts_with("keep"))
+                .fold(String::new(), |mut acc, l| {
+                    acc.push_str(l);
+                    acc.push('\n');
+                    acc
+                });
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+
+        let mut streamed = Vec::new();
+        colorizer
+            .process_reader(input.as_bytes(), &mut streamed, None, None, 0)
+            .unwrap();
+        assert_eq!(String::from_utf8(streamed).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_colored_input_matches_plain_text() {
+        // Escapes inside the word would defeat the rule in raw mode
+        let rules = vec![Rule::new(r"\bERROR\b").unwrap().named("red").build()];
+        let line = "\x1b[1mERR\x1b[0mOR: disk \x1b[33mfull\x1b[0m";
+        let red = nu_ansi_term::Color::Red;
+
+        let mut strip = Colorizer::new(rules.clone()).with_ansi_mode(AnsiMode::Strip);
+        assert_eq!(
+            strip.colorize(line),
+            format!("{}: disk full", red.paint("ERROR"))
+        );
+
+        let mut raw = Colorizer::new(rules.clone()).with_ansi_mode(AnsiMode::Raw);
+        assert_eq!(raw.colorize(line), line);
+
+        // Merge keeps the input's colors outside phos matches
+        let mut merge = Colorizer::new(rules);
+        let result = merge.colorize(line);
+        assert!(result.starts_with("\x1b[1m\x1b[0m\x1b[31mERROR\x1b[0m"));
+        assert!(result.ends_with(": disk \x1b[33mfull\x1b[0m"));
+    }
+
+    #[test]
+    fn test_colored_input_replayed_after_match() {
+        let rules = vec![Rule::new(r"\d+").unwrap().named("blue").build()];
+        let mut colorizer = Colorizer::new(rules);
+        let result = colorizer.colorize("\x1b[32mslot 42 ok\x1b[0m");
+        let blue = nu_ansi_term::Color::Blue;
+        assert_eq!(
+            result,
+            format!("\x1b[32mslot \x1b[0m{}\x1b[32m ok\x1b[0m", blue.paint("42"))
+        );
+
+        // Without color output the input passes through unchanged
+        let mut plain = Colorizer::new(vec![Rule::new(r"\d+").unwrap().named("blue").build()])
+            .with_color_enabled(false);
+        assert_eq!(
+            plain.colorize("\x1b[32mslot 42 ok\x1b[0m"),
+            "\x1b[32mslot 42 ok\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_group_colors() {
+        let rules = vec![Rule::new(r#"(\w+)=("[^"]*"|\S+)"#)
+            .unwrap()
+            .named("default")
+            .named("cyan")
+            .named("green")
+            .build()];
+        let mut colorizer = Colorizer::new(rules);
+        let result = colorizer.colorize(r#"user="alice" id=42"#);
+
+        let cyan = nu_ansi_term::Color::Cyan;
+        let green = nu_ansi_term::Color::Green;
+        assert!(result.contains(&cyan.paint("user").to_string()));
+        assert!(result.contains(&green.paint(r#""alice""#).to_string()));
+        assert!(result.contains(&cyan.paint("id").to_string()));
+        assert!(result.contains(&green.paint("42").to_string()));
+        // The `=` between key and value stays unstyled
+        assert!(result.contains("\x1b[0m=\x1b["));
+    }
+
+    #[test]
+    fn test_group_colors_with_base() {
+        let rules = vec![Rule::new(r"took (\d+)ms")
+            .unwrap()
+            .named("blue")
+            .named("red")
+            .build()];
+        let mut colorizer = Colorizer::new(rules);
+        let result = colorizer.colorize("request took 15ms");
+
+        let expected = format!(
+            "request {}{}{}",
+            nu_ansi_term::Color::Blue.paint("took "),
+            nu_ansi_term::Color::Red.paint("15"),
+            nu_ansi_term::Color::Blue.paint("ms"),
+        );
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_priority_nested_span() {
+        let rules = vec![
+            Rule::new(r#""[^"]*""#).unwrap().named("green").build(),
+            Rule::new(r"\b[0-9a-f]{8}\b")
+                .unwrap()
+                .named("magenta")
+                .priority(10)
+                .build(),
+        ];
+        let mut colorizer = Colorizer::new(rules);
+        let result = colorizer.colorize(r#"msg="commit deadbeef pushed""#);
+
+        let green = nu_ansi_term::Color::Green;
+        let expected = format!(
+            "msg={}{}{}",
+            green.paint("\"commit "),
+            nu_ansi_term::Color::Magenta.paint("deadbeef"),
+            green.paint(" pushed\""),
+        );
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_lower_priority_nested_span_hidden() {
+        // The hash rule matches inside the string but the string rule wins
+        let rules = vec![
+            Rule::new(r#""[^"]*""#)
+                .unwrap()
+                .named("green")
+                .priority(1)
+                .build(),
+            Rule::new(r"\b[0-9a-f]{8}\b")
+                .unwrap()
+                .named("magenta")
+                .build(),
+        ];
+        let mut colorizer = Colorizer::new(rules);
+        let result = colorizer.colorize(r#""commit deadbeef""#);
+        assert_eq!(
+            result,
+            nu_ansi_term::Color::Green
+                .paint(r#""commit deadbeef""#)
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_crossing_match_dropped() {
+        let rules = vec![
+            Rule::new(r"foo bar")
+                .unwrap()
+                .named("red")
+                .priority(1)
+                .build(),
+            Rule::new(r"bar baz").unwrap().named("blue").build(),
+        ];
+        let mut colorizer = Colorizer::new(rules);
+        let result = colorizer.colorize("foo bar baz");
+        assert_eq!(
+            result,
+            format!("{} baz", nu_ansi_term::Color::Red.paint("foo bar"))
+        );
+    }
+
+    #[test]
+    fn test_record_continuation_takes_level_style() {
+        let rules = vec![
+            Rule::new(r"\bERROR\b")
+                .unwrap()
+                .semantic(SemanticColor::Error)
+                .build(),
+            Rule::new(r"\bINFO\b")
+                .unwrap()
+                .semantic(SemanticColor::Info)
+                .build(),
+        ];
+        let mut colorizer = Colorizer::new(rules);
+        let error = Theme::default().resolve_style(&Color::Semantic(SemanticColor::Error));
+
+        let results = colorizer.colorize_record(&["ERROR boom", "\tat Foo.bar"], false);
+        assert_eq!(results[0], Some((colorizer.colorize("ERROR boom"), true)));
+        assert_eq!(
+            results[1],
+            Some((error.paint("\tat Foo.bar").to_string(), false))
+        );
+
+        // A continued record keeps the style; the next record resets it
+        let results = colorizer.colorize_record(&["\tat Baz.qux"], true);
+        assert_eq!(
+            results[0],
+            Some((error.paint("\tat Baz.qux").to_string(), false))
+        );
+        let results = colorizer.colorize_record(&["started", "  details"], false);
+        assert_eq!(results[1], Some(("  details".to_string(), false)));
+    }
+
+    #[test]
+    fn test_record_skipped_as_unit() {
+        let rules = vec![Rule::new(r"DEBUG").unwrap().skip().build()];
+        let mut colorizer = Colorizer::new(rules);
+        let results = colorizer.colorize_record(&["DEBUG dump", "  field=1"], false);
+        assert_eq!(results, [None, None]);
+        assert_eq!(colorizer.colorize_record(&["  field=2"], true), [None]);
+        assert!(colorizer.colorize_record(&["next", "  more"], false)[1].is_some());
+    }
+
+    #[test]
+    fn test_structured_rendering() {
+        let rules = vec![Rule::new(r"\bSynced\b").unwrap().named("green").build()];
+        let mut colorizer = Colorizer::new(rules).with_structured(Some(FieldNames::default()));
+        let styles = FieldStyles::new(&Theme::default(), ColorDepth::default());
+        let green = nu_ansi_term::Color::Green;
+
+        let result = colorizer.colorize(r#"{"level":"info","msg":"Synced","slot":12}"#);
+        let expected = format!(
+            "{}  {} {}={}",
+            styles.levels[2].paint("INFO"),
+            green.paint("Synced"),
+            styles.key.paint("slot"),
+            styles.number.paint("12"),
+        );
+        assert_eq!(result, expected);
+
+        // Unparseable lines fall back to the rules
+        let result = colorizer.colorize("INFO Synced slot=12");
+        assert_eq!(result, format!("INFO {} slot=12", green.paint("Synced")));
+    }
+
+    #[test]
+    fn test_time_format() {
+        let mut colorizer = Colorizer::new(Vec::new()).with_time_format(Some(TimeFormat::Iso));
+        assert_eq!(
+            colorizer.colorize("[2024-12-05 02:12:36,557+02:00] INFO Synced"),
+            "[2024-12-05T00:12:36.557Z] INFO Synced"
+        );
+
+        let mut colorizer = colorizer.with_structured(Some(FieldNames::default()));
+        let result = colorizer.colorize(r#"{"ts":1733357556557,"msg":"Synced"}"#);
+        assert_eq!(strip_ansi(&result), "2024-12-05T00:12:36.557Z Synced");
+    }
+
+    #[test]
+    fn test_field_rules() {
+        let rules = vec![
+            Rule::new(r"^debug$").unwrap().field("level").skip().build(),
+            Rule::new(r"^0x").unwrap().field("hash").replace("").build(),
+            Rule::new(r"^\d+$")
+                .unwrap()
+                .field("slot")
+                .named("red")
+                .build(),
+        ];
+        let mut colorizer = Colorizer::new(rules);
+        let red = nu_ansi_term::Color::Red;
+
+        // Only the value of `slot` is matched, after `hash` was rewritten in place
+        let line = r#"{"msg":"slot 9","hash":"0xab","parent_slot":8,"slot":9}"#;
+        let expected = format!(
+            r#"{{"msg":"slot 9","hash":"ab","parent_slot":8,"slot":{}}}"#,
+            red.paint("9")
+        );
+        assert_eq!(colorizer.colorize(line), expected);
+        assert!(colorizer.colorize_opt("level=debug msg=hidden").is_none());
+        assert_eq!(colorizer.colorize("INFO slot 9"), "INFO slot 9");
+
+        // Rendered lines match against the values where they were laid out
+        let mut colorizer = colorizer.with_structured(Some(FieldNames::default()));
+        let styles = FieldStyles::new(&Theme::default(), ColorDepth::default());
+        let expected = format!(
+            "{}  {}={}",
+            styles.levels[2].paint("INFO"),
+            styles.key.paint("slot"),
+            red.paint("9"),
+        );
+        assert_eq!(colorizer.colorize(r#"{"level":"info","slot":9}"#), expected);
+    }
+
+    #[test]
+    fn test_conditional_rules() {
+        let rules = vec![
+            Rule::new(r"\d+")
+                .unwrap()
+                .when(r"\bERROR\b")
+                .unwrap()
+                .named("red")
+                .build(),
+            Rule::new(r"\d+").unwrap().named("green").build(),
+        ];
+        let mut colorizer = Colorizer::new(rules);
+        let (red, green) = (nu_ansi_term::Color::Red, nu_ansi_term::Color::Green);
+
+        let result = colorizer.colorize("ERROR 3 failed");
+        assert_eq!(result, format!("ERROR {} failed", red.paint("3")));
+        let result = colorizer.colorize("INFO 3 synced");
+        assert_eq!(result, format!("INFO {} synced", green.paint("3")));
+    }
+
+    #[test]
+    fn test_named_states() {
+        let rules = vec![
+            Rule::new(r"^Plan:").unwrap().enter("plan").build(),
+            Rule::new(r"^$|^Apply").unwrap().leave("plan").build(),
+            Rule::new(r"^  \+ \S+")
+                .unwrap()
+                .state("plan")
+                .named("green")
+                .build(),
+        ];
+        let mut colorizer = Colorizer::new(rules);
+        let green = nu_ansi_term::Color::Green;
+
+        assert_eq!(colorizer.colorize("  + aws_s3.logs"), "  + aws_s3.logs");
+        assert_eq!(colorizer.colorize("Plan: 1 to add"), "Plan: 1 to add");
+        let result = colorizer.colorize("  + aws_s3.logs");
+        assert_eq!(result, green.paint("  + aws_s3.logs").to_string());
+        colorizer.colorize("Apply complete");
+        assert_eq!(colorizer.colorize("  + aws_s3.logs"), "  + aws_s3.logs");
+    }
+
+    #[test]
+    fn test_single_color_paints_whole_match() {
+        let rules = vec![Rule::new(r"id=(\d+)").unwrap().named("red").build()];
+        let mut colorizer = Colorizer::new(rules);
+        let result = colorizer.colorize("id=42");
+        assert_eq!(result, nu_ansi_term::Color::Red.paint("id=42").to_string());
+    }
+
+    #[test]
+    fn test_background_and_attributes() {
+        let rules = vec![Rule::new(r"FATAL")
+            .unwrap()
+            .named("white")
+            .bg_named("red")
+            .underline()
+            .build()];
+        let mut colorizer = Colorizer::new(rules);
+        let result = colorizer.colorize("FATAL");
+        let expected = nu_ansi_term::Color::White
+            .on(nu_ansi_term::Color::Red)
+            .underline()
+            .paint("FATAL")
+            .to_string();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_color_depth_downsamples_styles() {
+        let rules = vec![Rule::new(r"ERROR").unwrap().hex("#FF0000").bold().build()];
+
+        let mut colorizer = Colorizer::new(rules.clone()).with_color_depth(ColorDepth::Ansi256);
+        assert_eq!(colorizer.colorize("ERROR"), "\x1b[1;38;5;196mERROR\x1b[0m");
+
+        // Depth survives a later theme change
+        let mut colorizer = Colorizer::new(rules)
+            .with_color_depth(ColorDepth::None)
+            .with_theme(Theme::nord());
+        assert_eq!(colorizer.colorize("ERROR"), "\x1b[1mERROR\x1b[0m");
+    }
+
+    #[test]
+    fn test_hashed_colors() {
+        let theme = Theme::dracula();
+        let palette = theme.hashed_palette();
+        let expected = |text: &str| {
+            let index = stable_hash(text) % palette.len() as u64;
+            palette[usize::try_from(index).unwrap()].to_style()
+        };
+
+        // Whole matches: the same text always gets the same color
+        let rules = vec![Rule::new(r"peer-\w+").unwrap().hashed().bold().build()];
+        let mut colorizer = Colorizer::new(rules).with_theme(theme.clone());
+        let names = ["peer-alpha", "peer-bravo", "peer-charlie", "peer-delta"];
+        for name in names {
+            let expected = expected(name).bold().paint(name).to_string();
+            assert_eq!(colorizer.colorize(name), expected);
+        }
+        let distinct: std::collections::HashSet<String> =
+            names.iter().map(|n| colorizer.colorize(n)).collect();
+        assert!(distinct.len() > 1);
+
+        // Capture groups: only the hashed group varies
+        let rules = vec![Rule::new(r"peer=(\w+)")
+            .unwrap()
+            .semantic(SemanticColor::Key)
+            .hashed()
+            .build()];
+        let mut colorizer = Colorizer::new(rules).with_theme(theme.clone());
+        let key = theme.resolve_style(&Color::Semantic(SemanticColor::Key));
+        let output = colorizer.colorize("peer=alpha");
+        assert!(output.starts_with(&key.paint("peer=").to_string()));
+        assert!(output.ends_with(&expected("alpha").paint("alpha").to_string()));
+    }
+
+    #[test]
+    fn test_theme_semantic_style() {
+        use crate::colors::Attributes;
+        use crate::theme::SemanticStyle;
+
+        let mut theme = Theme::default_dark();
+        theme.set_style(
+            SemanticColor::Error,
+            SemanticStyle {
+                background: None,
+                attributes: Attributes {
+                    reverse: true,
+                    ..Attributes::default()
+                },
+            },
+        );
+        let rules = vec![Rule::new(r"ERROR")
+            .unwrap()
+            .semantic(SemanticColor::Error)
+            .build()];
+        let mut colorizer = Colorizer::new(rules).with_theme(theme);
+        let result = colorizer.colorize("ERROR");
+        // Reverse video is SGR 7
+        assert!(result.contains(";7m") || result.contains("[7;"));
+    }
 }
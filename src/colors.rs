@@ -2,9 +2,10 @@
 //!
 //! This module provides color representations for terminal output:
 //!
-//! - [`Color`]: Concrete color values (named, hex, RGB, or semantic)
+//! - [`Color`]: Concrete color values (named, hex, RGB, semantic, or hashed)
 //! - [`SemanticColor`]: Abstract colors resolved by themes (Error, Warn, Info, etc.)
 //! - [`ColorSpec`]: Flexible color specification for rule definitions
+//! - [`Attributes`]: Text attributes (bold, italic, underline, reverse, ...)
 //!
 //! # Examples
 //!
@@ -19,14 +20,16 @@
 //! ```
 
 use nu_ansi_term::{Color as AnsiColor, Style};
+use serde::Deserialize;
 
 /// Color representation for styling text.
 ///
 /// Colors can be specified in multiple formats:
-/// - **Named**: Standard ANSI colors like "red", "`bright_blue`"
+/// - **Named**: Standard ANSI colors like "red", "`bright_blue`" ("default" means unstyled)
 /// - **Hex**: Web colors like "#FF5555"
 /// - **RGB**: Explicit RGB values
 /// - **Semantic**: Abstract colors resolved by the current theme
+/// - **Hashed**: A theme color picked from a hash of the matched text
 ///
 /// # Examples
 ///
@@ -46,6 +49,8 @@ pub enum Color {
     Rgb { r: u8, g: u8, b: u8 },
     /// Semantic color resolved by theme
     Semantic(SemanticColor),
+    /// Color picked per match from the theme's hashed palette by a hash of the text
+    Hashed,
 }
 
 /// Semantic colors that themes resolve to actual colors.
@@ -104,9 +109,10 @@ pub enum SemanticColor {
 /// Used in configuration files to specify colors flexibly. The parser
 /// tries to interpret color names in this order:
 /// 1. Semantic color (error, warn, info, etc.)
-/// 2. Hex color (starts with #)
-/// 3. Named ANSI color (red, blue, etc.)
-/// 4. Domain-specific color (resolved by program)
+/// 2. `hashed`
+/// 3. Hex color (starts with #)
+/// 4. Named ANSI color (red, blue, etc.)
+/// 5. Domain-specific color (resolved by program)
 #[derive(Debug, Clone, PartialEq)]
 pub enum ColorSpec {
     /// Universal semantic color (resolved by theme)
@@ -117,6 +123,8 @@ pub enum ColorSpec {
     Named(String),
     /// Hex color
     Hex(String),
+    /// Color derived from the matched text
+    Hashed,
 }
 
 impl ColorSpec {
@@ -125,6 +133,8 @@ impl ColorSpec {
     pub fn from_name(name: &str) -> Self {
         if let Some(semantic) = SemanticColor::from_name(name) {
             Self::Semantic(semantic)
+        } else if name.eq_ignore_ascii_case("hashed") {
+            Self::Hashed
         } else if name.starts_with('#') {
             Self::Hex(name.to_string())
         } else if is_ansi_color(name) {
@@ -136,6 +146,83 @@ impl ColorSpec {
     }
 }
 
+/// Text attributes that can be layered on top of colors.
+///
+/// Used by rules and theme styles. In configuration files each attribute is a
+/// boolean key (`italic: true`) and may also be listed by name alongside colors.
+///
+/// # Examples
+///
+/// ```rust
+/// use phos::colors::Attributes;
+///
+/// let mut attrs = Attributes::default();
+/// assert!(attrs.insert("reverse"));
+/// assert!(!attrs.insert("sparkly"));
+/// assert!(attrs.reverse);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Attributes {
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub reverse: bool,
+    pub strikethrough: bool,
+}
+
+impl Attributes {
+    /// Check if a name refers to a text attribute rather than a color.
+    #[must_use]
+    pub fn is_attribute(name: &str) -> bool {
+        Self::default().insert(name)
+    }
+
+    /// Enable the attribute with the given name.
+    /// Returns false if the name is not a known attribute.
+    pub fn insert(&mut self, name: &str) -> bool {
+        let flag = match name.to_lowercase().as_str() {
+            "bold" => &mut self.bold,
+            "dim" | "faint" => &mut self.dim,
+            "italic" => &mut self.italic,
+            "underline" | "underlined" => &mut self.underline,
+            "reverse" | "reversed" => &mut self.reverse,
+            "strikethrough" | "strike" => &mut self.strikethrough,
+            _ => return false,
+        };
+        *flag = true;
+        true
+    }
+
+    /// Combine two attribute sets (an attribute is set if set in either).
+    #[must_use]
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            bold: self.bold || other.bold,
+            dim: self.dim || other.dim,
+            italic: self.italic || other.italic,
+            underline: self.underline || other.underline,
+            reverse: self.reverse || other.reverse,
+            strikethrough: self.strikethrough || other.strikethrough,
+        }
+    }
+
+    /// Apply these attributes to a style.
+    #[must_use]
+    pub fn apply(self, style: Style) -> Style {
+        Style {
+            is_bold: style.is_bold || self.bold,
+            is_dimmed: style.is_dimmed || self.dim,
+            is_italic: style.is_italic || self.italic,
+            is_underline: style.is_underline || self.underline,
+            is_reverse: style.is_reverse || self.reverse,
+            is_strikethrough: style.is_strikethrough || self.strikethrough,
+            ..style
+        }
+    }
+}
+
 impl SemanticColor {
     /// All semantic color variants for validation.
     pub const ALL: &'static [SemanticColor] = &[
@@ -186,13 +273,24 @@ impl SemanticColor {
             _ => None,
         }
     }
+
+    /// Whether this is a log level color (error, warn, info, debug, trace).
+    #[must_use]
+    pub fn is_level(self) -> bool {
+        matches!(
+            self,
+            Self::Error | Self::Warn | Self::Info | Self::Debug | Self::Trace
+        )
+    }
 }
 
 /// Check if a name is a standard ANSI color.
 fn is_ansi_color(name: &str) -> bool {
     matches!(
         name.to_lowercase().as_str(),
-        "black"
+        "default"
+            | "none"
+            | "black"
             | "red"
             | "green"
             | "yellow"
@@ -221,6 +319,12 @@ impl Color {
         Self::Semantic(s)
     }
 
+    /// Whether this is a semantic log level color.
+    #[must_use]
+    pub fn is_level(&self) -> bool {
+        matches!(self, Self::Semantic(semantic) if semantic.is_level())
+    }
+
     /// Create a named color.
     #[must_use]
     pub fn named(name: &str) -> Self {
@@ -239,6 +343,14 @@ impl Color {
         Self::Rgb { r, g, b }
     }
 
+    /// Convert to a `nu_ansi_term` Style that uses this color as background.
+    #[must_use]
+    pub fn to_background_style(&self) -> Style {
+        self.to_style()
+            .foreground
+            .map_or_else(Style::new, |color| Style::new().on(color))
+    }
+
     /// Convert to `nu_ansi_term` Style.
     #[must_use]
     pub fn to_style(&self) -> Style {
@@ -246,12 +358,13 @@ impl Color {
             Color::Named(name) => Self::named_to_style(name),
             Color::Hex(hex) => Self::hex_to_style(hex),
             Color::Rgb { r, g, b } => Style::new().fg(AnsiColor::Rgb(*r, *g, *b)),
-            Color::Semantic(_) => Style::new(), // Resolved by theme
+            Color::Semantic(_) | Color::Hashed => Style::new(), // Resolved by theme
         }
     }
 
     fn named_to_style(name: &str) -> Style {
         let color = match name.to_lowercase().as_str() {
+            "default" | "none" => return Style::new(),
             "black" => AnsiColor::Black,
             "red" => AnsiColor::Red,
             "green" => AnsiColor::Green,
@@ -280,6 +393,16 @@ impl Color {
     }
 }
 
+/// Stable hash of a matched text, used to pick its [`Color::Hashed`] color.
+///
+/// FNV-1a, so the same text gets the same color in every run and release.
+#[must_use]
+pub fn stable_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
 /// Parse a hex color string to RGB components.
 ///
 /// Accepts formats: "#RRGGBB", "RRGGBB"
@@ -310,7 +433,7 @@ use std::sync::LazyLock;
 /// - CSI sequences: \x1b\[[0-9;]*[a-zA-Z]
 /// - OSC sequences: \x1b\][^\x07]*\x07 (title setting, etc.)
 /// - Simple escapes: \x1b[()][AB0-2] (character set selection)
-static ANSI_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+pub(crate) static ANSI_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
     regex::Regex::new(
         r"(?x)
         \x1b\[[0-9;]*[a-zA-Z]  |  # CSI sequences (colors, cursor, etc.)
@@ -389,6 +512,43 @@ mod tests {
         let _style = color.to_style();
     }
 
+    #[test]
+    fn test_hashed_spec() {
+        assert_eq!(ColorSpec::from_name("hashed"), ColorSpec::Hashed);
+        assert_eq!(Color::Hashed.to_style(), Style::new());
+        assert_eq!(stable_hash("16Uiu2HAm"), stable_hash("16Uiu2HAm"));
+        assert_ne!(stable_hash("peer-a"), stable_hash("peer-b"));
+    }
+
+    #[test]
+    fn test_default_color_is_unstyled() {
+        assert!(Color::named("default").to_style().is_plain());
+        assert_eq!(
+            ColorSpec::from_name("none"),
+            ColorSpec::Named("none".to_string())
+        );
+    }
+
+    #[test]
+    fn test_background_style() {
+        let style = Color::named("red").to_background_style();
+        assert_eq!(style.background, Some(AnsiColor::Red));
+        assert_eq!(style.foreground, None);
+        assert!(Color::named("default").to_background_style().is_plain());
+    }
+
+    #[test]
+    fn test_attributes_apply() {
+        let mut attrs = Attributes::default();
+        assert!(attrs.insert("italic"));
+        assert!(attrs.insert("Strikethrough"));
+        let style = attrs.apply(Style::new());
+        assert!(style.is_italic && style.is_strikethrough);
+        assert!(!style.is_bold);
+        assert!(Attributes::is_attribute("reverse"));
+        assert!(!Attributes::is_attribute("red"));
+    }
+
     #[test]
     fn test_brand_colors() {
         assert!(brands::color("geth").is_some());
@@ -2,7 +2,7 @@
 
 use anyhow::Result;
 use clap::Subcommand;
-use phos::program::loader;
+use phos::program::{loader, ProgramRegistry};
 
 /// Configuration subcommand actions.
 #[derive(Subcommand)]
@@ -23,8 +23,8 @@ pub enum ConfigAction {
     Init,
 }
 
-/// Handle configuration subcommands.
-pub fn handle_config_action(action: ConfigAction) -> Result<()> {
+/// Handle configuration subcommands; `extends` is validated against `registry`.
+pub fn handle_config_action(registry: &ProgramRegistry, action: ConfigAction) -> Result<()> {
     match action {
         ConfigAction::Path => {
             println!("Configuration paths:\n");
@@ -65,7 +65,7 @@ pub fn handle_config_action(action: ConfigAction) -> Result<()> {
                 if !path.exists() {
                     anyhow::bail!("File not found: {file_path}");
                 }
-                match loader::validate_program_file(&path) {
+                match loader::validate_program_file_with_registry(&path, registry) {
                     Ok(info) => {
                         println!("Valid: {info}");
                         Ok(())
@@ -93,7 +93,7 @@ pub fn handle_config_action(action: ConfigAction) -> Result<()> {
                         .file_name()
                         .and_then(|n| n.to_str())
                         .unwrap_or("unknown");
-                    match loader::validate_program_file(path) {
+                    match loader::validate_program_file_with_registry(path, registry) {
                         Ok(info) => println!("  [OK] {filename}: {info}"),
                         Err(result) => {
                             eprintln!("  [ERROR] {}", result.format());
@@ -143,6 +143,9 @@ pub fn handle_config_action(action: ConfigAction) -> Result<()> {
 # Force color output even when not a TTY
 # color: true
 
+# Bytes of each line to colorize; the rest of longer lines is passed through
+# max_line_length: 10000  # 0 = no limit
+
 # Statistics settings
 # stats: false
 # stats_export: human  # human, json, prometheus
@@ -174,10 +177,11 @@ name: MyApp
 description: Example custom application colorization
 category: custom
 
-# Patterns for auto-detection (matched against command line)
+# Patterns for auto-detection (matched against command line):
+# a word, re:REGEX, or argv:WORDS matched in order against the arguments
 detect:
   - myapp
-  - docker.*myapp
+  - "re:docker.*myapp"
 
 # Custom semantic colors for your domain
 semantic_colors:
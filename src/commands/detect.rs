@@ -0,0 +1,99 @@
+//! Program detection command.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+
+use anyhow::{Context, Result};
+use phos::program::rank::MIN_CONFIDENCE;
+use phos::{LineReader, ProgramRegistry, RawLine};
+use serde::Serialize;
+
+use crate::OutputFormat;
+
+/// Candidates shown in the table.
+const SHOWN: usize = 10;
+
+/// Serializable candidate for JSON output.
+#[derive(Serialize)]
+pub struct CandidateJson {
+    pub id: String,
+    pub name: String,
+    pub confidence: f64,
+    pub score: f64,
+    pub coverage: f64,
+}
+
+#[derive(Serialize)]
+pub struct DetectJson {
+    pub lines: usize,
+    pub detected: Option<String>,
+    pub candidates: Vec<CandidateJson>,
+}
+
+/// Rank programs against the first `lines` lines of `file` (stdin if None).
+pub fn detect_program(
+    registry: &ProgramRegistry,
+    file: Option<&str>,
+    lines: usize,
+    format: OutputFormat,
+) -> Result<()> {
+    let input: Box<dyn BufRead> = match file {
+        Some(path) => Box::new(BufReader::new(
+            File::open(path).with_context(|| format!("Failed to open {path}"))?,
+        )),
+        None => Box::new(io::stdin().lock()),
+    };
+    let sample = LineReader::new(input)
+        .take(lines)
+        .collect::<io::Result<Vec<RawLine>>>()
+        .context("Failed to read input")?;
+    let texts: Vec<_> = sample.iter().map(RawLine::to_str_lossy).collect();
+    let line_refs: Vec<&str> = texts.iter().map(AsRef::as_ref).collect();
+
+    let candidates = registry.rank_from_lines(&line_refs);
+    let detected = candidates
+        .first()
+        .filter(|candidate| candidate.confidence >= MIN_CONFIDENCE)
+        .map(|candidate| candidate.program.info().id.to_string());
+
+    match format {
+        OutputFormat::Json => {
+            let output = DetectJson {
+                lines: sample.len(),
+                detected,
+                candidates: candidates
+                    .iter()
+                    .map(|candidate| CandidateJson {
+                        id: candidate.program.info().id.to_string(),
+                        name: candidate.program.info().name.to_string(),
+                        confidence: candidate.confidence,
+                        score: candidate.score,
+                        coverage: candidate.coverage,
+                    })
+                    .collect(),
+            };
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        }
+        OutputFormat::Table => {
+            match &detected {
+                Some(id) => println!("Detected: {id} ({} lines sampled)\n", sample.len()),
+                None => println!("No program detected ({} lines sampled)\n", sample.len()),
+            }
+            println!(
+                "  {:24} {:>10} {:>6} {:>8}",
+                "PROGRAM", "CONFIDENCE", "SCORE", "COVERAGE"
+            );
+            for candidate in candidates.iter().take(SHOWN) {
+                println!(
+                    "  {:24} {:>9.0}% {:>6.2} {:>7.0}%",
+                    candidate.program.info().id,
+                    candidate.confidence * 100.0,
+                    candidate.score,
+                    candidate.coverage * 100.0
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -5,6 +5,7 @@
 pub mod colors;
 pub mod completions;
 pub mod config;
+pub mod detect;
 pub mod info;
 pub mod interactive;
 pub mod list;
@@ -17,6 +18,7 @@ pub mod themes;
 pub use colors::show_colors;
 pub use completions::generate_completions;
 pub use config::{handle_config_action, ConfigAction};
+pub use detect::detect_program;
 pub use info::show_info;
 pub use interactive::{execution_mode, ExecutionMode};
 pub use list::list_programs;
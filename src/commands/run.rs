@@ -1,9 +1,12 @@
 //! Command execution with colorization.
 
 use anyhow::{Context, Result};
-use phos::{AlertManager, Colorizer, StatsCollector};
+use phos::filter::FilteredOutput;
+use phos::{AlertManager, Colorizer, LineReader, RawLine, Record, RecordAssembler, StatsCollector};
+use std::borrow::Cow;
 use std::io::{BufRead, BufReader, Write};
 use std::process::{Command, Stdio};
+use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
 
 #[cfg(unix)]
@@ -170,20 +173,13 @@ pub fn run_command(
         let alert_tx = alert_tx.clone();
         move || {
             let out = std::io::stdout();
-            let mut out = out.lock();
-            for line in stdout_reader.lines().map_while(Result::ok) {
-                let (colored, had_match) = colorizer.colorize_with_match_info(&line);
-                if let Some(ref stats) = stats_arc {
-                    if let Ok(mut s) = stats.lock() {
-                        s.process_line(&line, had_match);
-                    }
-                }
-                // Send line for alert processing
-                if let Some(ref tx) = alert_tx {
-                    let _ = tx.send(line);
-                }
-                let _ = writeln!(out, "{colored}");
-            }
+            colorize_stream(
+                &mut colorizer,
+                stdout_reader,
+                &mut out.lock(),
+                stats_arc.as_deref(),
+                alert_tx.as_ref(),
+            );
         }
     });
 
@@ -193,20 +189,13 @@ pub fn run_command(
         let alert_tx = alert_tx;
         move || {
             let err = std::io::stderr();
-            let mut err = err.lock();
-            for line in stderr_reader.lines().map_while(Result::ok) {
-                let (colored, had_match) = colorizer.colorize_with_match_info(&line);
-                if let Some(ref stats) = stats_arc {
-                    if let Ok(mut s) = stats.lock() {
-                        s.process_line(&line, had_match);
-                    }
-                }
-                // Send line for alert processing
-                if let Some(ref tx) = alert_tx {
-                    let _ = tx.send(line);
-                }
-                let _ = writeln!(err, "{colored}");
-            }
+            colorize_stream(
+                &mut colorizer,
+                stderr_reader,
+                &mut err.lock(),
+                stats_arc.as_deref(),
+                alert_tx.as_ref(),
+            );
         }
     });
 
@@ -252,6 +241,89 @@ pub fn run_command(
     Ok(())
 }
 
+/// Colorize one output stream of a piped command, line by line or record by record.
+///
+/// Stats and alerts see each line (or multi-line record) once, whether or not
+/// the colorizer's filters show it; skipped lines come out empty.
+fn colorize_stream<R: BufRead, W: Write>(
+    colorizer: &mut Colorizer,
+    reader: R,
+    out: &mut W,
+    stats: Option<&Mutex<StatsCollector>>,
+    alert_tx: Option<&Sender<String>>,
+) {
+    let track = |text: &str, had_match: bool| {
+        if let Some(Ok(mut s)) = stats.map(Mutex::lock) {
+            s.process_line(text, had_match);
+        }
+        // Send text for alert processing
+        if let Some(tx) = alert_tx {
+            let _ = tx.send(text.to_string());
+        }
+    };
+
+    let mut reader = LineReader::new(reader);
+    let mut out = colorizer.filtered_output(out);
+    let mut line = RawLine::default();
+    let mut colored = String::new();
+    let mut records = colorizer.multiline().cloned().map(RecordAssembler::new);
+    // Invalid UTF-8 is passed through; only I/O errors end the stream
+    while let Ok(true) = reader.read_line(&mut line) {
+        let Some(assembler) = records.as_mut() else {
+            let text = line.to_str_lossy();
+            colored.clear();
+            let had_match = colorizer
+                .colorize_append(&text, &mut colored)
+                .unwrap_or(true);
+            track(&text, had_match);
+            let _ = out.write_line(&line, &colored);
+            continue;
+        };
+
+        // Don't hold a record back while the command is quiet
+        let complete = assembler.push(std::mem::take(&mut line));
+        let pending = if reader.has_buffered() {
+            None
+        } else {
+            assembler.flush()
+        };
+        for record in complete.iter().chain(&pending) {
+            write_record(colorizer, record, &mut out, &track);
+        }
+    }
+    if let Some(record) = records.as_mut().and_then(RecordAssembler::flush) {
+        write_record(colorizer, &record, &mut out, &track);
+    }
+    let _ = out.finish();
+}
+
+/// Colorize a multi-line record and write its lines.
+fn write_record<W: Write>(
+    colorizer: &mut Colorizer,
+    record: &Record,
+    out: &mut FilteredOutput<W>,
+    track: &impl Fn(&str, bool),
+) {
+    let texts: Vec<Cow<str>> = record.lines().iter().map(RawLine::to_str_lossy).collect();
+    let results = colorizer.colorize_record(&texts, record.is_continued());
+
+    // A continued record was tracked with the record it extends
+    if !record.is_continued() {
+        let skipped = results.iter().all(Option::is_none);
+        let had_match = skipped || results.iter().flatten().any(|(_, matched)| *matched);
+        track(&record.text(), had_match);
+    }
+
+    let lines = record.lines().iter().zip(&results);
+    let _ = out.write_record(
+        record,
+        lines.map(|(line, result)| {
+            let colored = result.as_ref().map_or("", |(colored, _)| colored.as_str());
+            (line, colored)
+        }),
+    );
+}
+
 // ============================================================================
 // PTY-based Execution (Unix only)
 // ============================================================================
@@ -1,5 +1,6 @@
 //! Configuration file loading and parsing.
 
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
@@ -7,8 +8,10 @@ use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use thiserror::Error;
 
-use crate::colors::SemanticColor;
-use crate::rule::Rule;
+use crate::colors::{Attributes, Color, ColorSpec};
+use crate::level::LogLevel;
+use crate::rule::{CountMode, Rule};
+use crate::timestamp::TimeFormat;
 
 /// Configuration loading errors.
 #[derive(Debug, Error)]
@@ -132,7 +135,8 @@ pub struct RuleConfig {
     /// Regex pattern
     pub regex: String,
 
-    /// Colors to apply
+    /// Colors to apply. With capture groups and several colors, the first
+    /// color covers the whole match and each following one its capture group.
     #[serde(default)]
     pub colors: Vec<String>,
 
@@ -140,6 +144,34 @@ pub struct RuleConfig {
     #[serde(default)]
     pub bold: bool,
 
+    /// Background color (semantic, domain, hex, or ANSI name)
+    #[serde(default)]
+    pub background: Option<String>,
+
+    /// Whether to apply italic
+    #[serde(default)]
+    pub italic: bool,
+
+    /// Whether to apply underline
+    #[serde(default)]
+    pub underline: bool,
+
+    /// Whether to apply dim (faint)
+    #[serde(default)]
+    pub dim: bool,
+
+    /// Whether to swap foreground and background
+    #[serde(default)]
+    pub reverse: bool,
+
+    /// Whether to apply strikethrough
+    #[serde(default)]
+    pub strikethrough: bool,
+
+    /// How matches are applied: once, more (default), stop, block, unblock
+    #[serde(default)]
+    pub count: CountMode,
+
     /// Skip the entire line if this rule matches
     #[serde(default)]
     pub skip: bool,
@@ -147,6 +179,122 @@ pub struct RuleConfig {
     /// Replacement pattern (uses ${1}, ${2} for backreferences)
     #[serde(default)]
     pub replace: Option<String>,
+
+    /// Precedence when matches overlap (higher wins, default 0)
+    #[serde(default)]
+    pub priority: i32,
+
+    /// Match only the value of this field in JSON and logfmt lines
+    #[serde(default)]
+    pub field: Option<String>,
+
+    /// Apply only on lines matching this pattern
+    #[serde(default)]
+    pub when: Option<String>,
+
+    /// Do not apply on lines matching this pattern
+    #[serde(default)]
+    pub unless: Option<String>,
+
+    /// Apply only while this named state is active
+    #[serde(default)]
+    pub state: Option<String>,
+
+    /// Named state entered when the rule matches
+    #[serde(default)]
+    pub enter: Option<String>,
+
+    /// Named state left when the rule matches
+    #[serde(default)]
+    pub leave: Option<String>,
+}
+
+impl RuleConfig {
+    /// Build a rule, resolving domain color names through `domain_colors`.
+    ///
+    /// Attribute names listed in `colors` (e.g. `bold`, `italic`) are applied
+    /// as attributes and do not shift positional capture-group colors.
+    pub fn to_rule(&self, domain_colors: &HashMap<String, Color>) -> Result<Rule, regex::Error> {
+        let resolve = |name: &str| match ColorSpec::from_name(name) {
+            ColorSpec::Semantic(s) => Color::Semantic(s),
+            ColorSpec::Domain(name) => domain_colors
+                .get(&name)
+                .cloned()
+                .unwrap_or(Color::Named(name)),
+            ColorSpec::Named(name) => Color::Named(name),
+            ColorSpec::Hex(hex) => Color::Hex(hex),
+            ColorSpec::Hashed => Color::Hashed,
+        };
+
+        let (attribute_names, color_names): (Vec<_>, Vec<_>) = self
+            .colors
+            .iter()
+            .partition(|c| Attributes::is_attribute(c));
+
+        let mut attributes = Attributes {
+            bold: self.bold,
+            dim: self.dim,
+            italic: self.italic,
+            underline: self.underline,
+            reverse: self.reverse,
+            strikethrough: self.strikethrough,
+        };
+        for name in attribute_names {
+            attributes.insert(name);
+        }
+
+        let builder = color_names
+            .into_iter()
+            .fold(Rule::new(&self.regex)?, |b, name| b.color(resolve(name)))
+            .attributes(attributes);
+
+        let builder = match &self.background {
+            Some(bg) => builder.background(resolve(bg)),
+            None => builder,
+        };
+
+        let builder = match self.skip {
+            true => builder.skip(),
+            false => builder,
+        };
+
+        let builder = match &self.replace {
+            Some(r) => builder.replace(r),
+            None => builder,
+        };
+
+        let builder = match &self.field {
+            Some(name) => builder.field(name),
+            None => builder,
+        };
+
+        let builder = match &self.when {
+            Some(pattern) => builder.when(pattern)?,
+            None => builder,
+        };
+
+        let builder = match &self.unless {
+            Some(pattern) => builder.unless(pattern)?,
+            None => builder,
+        };
+
+        let builder = match &self.state {
+            Some(name) => builder.state(name),
+            None => builder,
+        };
+
+        let builder = match &self.enter {
+            Some(name) => builder.enter(name),
+            None => builder,
+        };
+
+        let builder = match &self.leave {
+            Some(name) => builder.leave(name),
+            None => builder,
+        };
+
+        Ok(builder.count(self.count).priority(self.priority).build())
+    }
 }
 
 impl Config {
@@ -157,40 +305,12 @@ impl Config {
 
     /// Convert configuration to rules.
     pub fn to_rules(&self) -> Result<Vec<Rule>, ConfigError> {
-        self.rules.iter().map(rule_from_config).collect()
-    }
-}
-
-/// Convert a single rule config to a Rule.
-fn rule_from_config(config: &RuleConfig) -> Result<Rule, ConfigError> {
-    let mut builder = config.colors.iter().filter(|c| *c != "bold").fold(
-        Rule::new(&config.regex)?,
-        |b, color| match parse_semantic_color(color) {
-            Some(s) => b.semantic(s),
-            None => b.named(color),
-        },
-    );
-
-    // Apply skip if set
-    if config.skip {
-        builder = builder.skip();
-    }
-
-    // Apply replace if set
-    if let Some(ref replacement) = config.replace {
-        builder = builder.replace(replacement);
+        let no_domain_colors = HashMap::new();
+        self.rules
+            .iter()
+            .map(|rule| Ok(rule.to_rule(&no_domain_colors)?))
+            .collect()
     }
-
-    let should_bold = config.bold || config.colors.iter().any(|c| c == "bold");
-    let builder = if should_bold { builder.bold() } else { builder };
-
-    Ok(builder.build())
-}
-
-/// Parse a semantic color name.
-/// Non-semantic colors (domain colors, hex, ANSI) return None and are handled elsewhere.
-fn parse_semantic_color(name: &str) -> Option<SemanticColor> {
-    SemanticColor::from_name(name)
 }
 
 // ---------------------------------------------------------------------------
@@ -223,6 +343,18 @@ pub struct GlobalConfig {
     #[serde(default)]
     pub color: bool,
 
+    /// Bytes of each line to colorize; longer lines pass the rest through (0 = no limit)
+    #[serde(default)]
+    pub max_line_length: Option<usize>,
+
+    /// Hide lines below this level (error, warn, info, debug, trace)
+    #[serde(default)]
+    pub level: Option<LogLevel>,
+
+    /// Rewrite timestamps as local, utc, iso or relative
+    #[serde(default)]
+    pub time_format: Option<TimeFormat>,
+
     /// Default alerting configuration
     #[serde(default)]
     pub alerts: AlertsConfig,
@@ -320,6 +452,7 @@ impl GlobalConfig {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::colors::SemanticColor;
 
     #[test]
     fn test_parse_yaml() {
@@ -339,9 +472,135 @@ rules:
 
     #[test]
     fn test_semantic_color_parsing() {
-        assert_eq!(parse_semantic_color("error"), Some(SemanticColor::Error));
-        assert_eq!(parse_semantic_color("WARN"), Some(SemanticColor::Warn));
-        assert_eq!(parse_semantic_color("unknown"), None);
+        assert_eq!(
+            ColorSpec::from_name("error"),
+            ColorSpec::Semantic(SemanticColor::Error)
+        );
+        assert_eq!(
+            ColorSpec::from_name("WARN"),
+            ColorSpec::Semantic(SemanticColor::Warn)
+        );
+        assert!(!matches!(
+            ColorSpec::from_name("unknown"),
+            ColorSpec::Semantic(_)
+        ));
+    }
+
+    #[test]
+    fn test_rule_background_and_attributes() {
+        let yaml = r##"
+name: test
+rules:
+  - regex: '\bFATAL\b'
+    colors: [error, italic]
+    background: "#330000"
+    underline: true
+"##;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let rules = config.to_rules().unwrap();
+        assert_eq!(rules[0].colors, vec![Color::Semantic(SemanticColor::Error)]);
+        assert_eq!(rules[0].background, Some(Color::hex("#330000")));
+        assert!(rules[0].attributes.italic && rules[0].attributes.underline);
+        assert!(!rules[0].attributes.bold);
+    }
+
+    #[test]
+    fn test_rule_count_modes() {
+        let yaml = r"
+name: test
+rules:
+  - regex: '^Terraform will perform'
+    colors: [info]
+    count: block
+  - regex: '^Plan:'
+    count: unblock
+  - regex: 'x'
+";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let rules = config.to_rules().unwrap();
+        let modes: Vec<CountMode> = rules.iter().map(|r| r.count_mode).collect();
+        assert_eq!(
+            modes,
+            [CountMode::Block, CountMode::Unblock, CountMode::More]
+        );
+
+        let yaml = "name: test\nrules:\n  - regex: x\n    count: blok\n";
+        let err = serde_yaml::from_str::<Config>(yaml).unwrap_err();
+        assert!(err.to_string().contains("unknown variant `blok`"), "{err}");
+    }
+
+    #[test]
+    fn test_rule_hex_color() {
+        let yaml = r##"
+name: test
+rules:
+  - regex: 'x'
+    colors: ["#FF8800"]
+"##;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let rules = config.to_rules().unwrap();
+        assert_eq!(rules[0].colors, vec![Color::hex("#FF8800")]);
+    }
+
+    #[test]
+    fn test_rule_priority() {
+        let yaml = r"
+name: test
+rules:
+  - regex: 'a'
+    colors: [red]
+  - regex: 'b'
+    colors: [blue]
+    priority: 5
+";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let rules = config.to_rules().unwrap();
+        assert_eq!(rules[0].priority, 0);
+        assert_eq!(rules[1].priority, 5);
+    }
+
+    #[test]
+    fn test_rule_field() {
+        let yaml = r"
+name: test
+rules:
+  - regex: '^\d+$'
+    field: slot
+    colors: [number]
+";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let rules = config.to_rules().unwrap();
+        assert_eq!(rules[0].field.as_deref(), Some("slot"));
+    }
+
+    #[test]
+    fn test_rule_conditions_and_states() {
+        let yaml = r"
+name: test
+rules:
+  - regex: '^Plan:'
+    enter: plan
+  - regex: '\d+'
+    when: '\bERROR\b'
+    unless: retrying
+    state: plan
+    colors: [failure]
+";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let rules = config.to_rules().unwrap();
+        assert_eq!(rules[0].enter.as_deref(), Some("plan"));
+        assert_eq!(rules[1].state.as_deref(), Some("plan"));
+        assert!(rules[1].applies_to("ERROR 3 failed"));
+        assert!(!rules[1].applies_to("ERROR 3 failed, retrying"));
+
+        let yaml = r"
+name: test
+rules:
+  - regex: 'x'
+    when: '('
+";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.to_rules().is_err());
     }
 
     // -------------------------------------------------------------------------
@@ -399,6 +658,30 @@ pty:
         assert_eq!(config.pty.interactive_commands, vec!["fzf", "bat"]);
     }
 
+    #[test]
+    fn test_global_config_max_line_length() {
+        let config: GlobalConfig = serde_yaml::from_str("max_line_length: 65536").unwrap();
+        assert_eq!(config.max_line_length, Some(65536));
+        let config: GlobalConfig = serde_yaml::from_str("theme: nord").unwrap();
+        assert_eq!(config.max_line_length, None);
+    }
+
+    #[test]
+    fn test_global_config_level() {
+        let config: GlobalConfig = serde_yaml::from_str("level: warning").unwrap();
+        assert_eq!(config.level, Some(LogLevel::Warn));
+        let config: GlobalConfig = serde_yaml::from_str("theme: nord").unwrap();
+        assert_eq!(config.level, None);
+        assert!(serde_yaml::from_str::<GlobalConfig>("level: loud").is_err());
+    }
+
+    #[test]
+    fn test_global_config_time_format() {
+        let config: GlobalConfig = serde_yaml::from_str("time_format: relative").unwrap();
+        assert_eq!(config.time_format, Some(TimeFormat::Relative));
+        assert!(serde_yaml::from_str::<GlobalConfig>("time_format: unix").is_err());
+    }
+
     #[test]
     fn test_global_config_without_pty_uses_defaults() {
         let yaml = r"
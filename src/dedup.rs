@@ -0,0 +1,158 @@
+//! Collapsing runs of repeated lines.
+//!
+//! A client in a reconnect loop can print the same line thousands of times a
+//! minute. With `--dedup`, the first line of a run is shown and the rest are
+//! dropped, then an `(x1234 repeated)` line is written when the run ends.
+//! Lines count as repeats when identical, or in [`DedupMode::Similar`] when
+//! identical after masking numbers and hex hashes (so timestamps, counters
+//! and IDs may differ). Dropped lines are still counted in statistics.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use phos::dedup::{Dedup, DedupMode, Run};
+//!
+//! let mut dedup = Dedup::new(DedupMode::Similar);
+//! assert_eq!(dedup.push("12:00:01 Peer 0x1f dropped"), Run::New(0));
+//! assert_eq!(dedup.push("12:00:02 Peer 0x2e dropped"), Run::Repeat);
+//! assert_eq!(dedup.push("12:00:03 Peer 0x3d dropped"), Run::Repeat);
+//! assert_eq!(dedup.push("12:00:04 Synced"), Run::New(2));
+//! assert_eq!(dedup.summary(2), "(x2 repeated)");
+//! ```
+
+use std::borrow::Cow;
+use std::sync::LazyLock;
+
+use clap::ValueEnum;
+use nu_ansi_term::Style;
+use regex::Regex;
+
+/// Numbers, hashes and IDs: whole `0x` or hex-digit tokens containing a
+/// decimal digit, else runs of digits (so `Failed2` keeps its letters).
+static NUMBER_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b0[xX][0-9A-Fa-f]+\b|\b[0-9A-Fa-f]*\d[0-9A-Fa-f]*\b|\d+")
+        .expect("number regex is valid")
+});
+
+/// Which lines count as repeats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DedupMode {
+    /// Identical lines
+    Exact,
+    /// Lines identical after masking numbers and hashes
+    Similar,
+}
+
+/// Where a line falls in a run of repeats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Run {
+    /// The line repeats the previous one and is dropped
+    Repeat,
+    /// The line starts a new run, ending one with this many repeats
+    New(usize),
+}
+
+/// Tracks the current run of repeated lines.
+#[derive(Debug, Clone)]
+pub struct Dedup {
+    mode: DedupMode,
+    /// Style of the repeat count (None = plain text)
+    style: Option<Style>,
+    /// Comparison key of the line starting the current run
+    key: Option<String>,
+    /// Lines dropped in the current run
+    repeats: usize,
+}
+
+impl Dedup {
+    /// Collapse repeats as `mode` defines them.
+    #[must_use]
+    pub fn new(mode: DedupMode) -> Self {
+        Self {
+            mode,
+            style: None,
+            key: None,
+            repeats: 0,
+        }
+    }
+
+    /// Paint repeat counts with `style`.
+    #[must_use]
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    /// Add a line with input text `text`.
+    pub fn push(&mut self, text: &str) -> Run {
+        let key = match self.mode {
+            DedupMode::Exact => Cow::Borrowed(text),
+            DedupMode::Similar => NUMBER_REGEX.replace_all(text, "#"),
+        };
+        if self.key.as_deref() == Some(key.as_ref()) {
+            self.repeats += 1;
+            return Run::Repeat;
+        }
+        self.key = Some(key.into_owned());
+        Run::New(std::mem::take(&mut self.repeats))
+    }
+
+    /// End the current run, returning its number of repeats.
+    pub fn finish(&mut self) -> usize {
+        self.key = None;
+        std::mem::take(&mut self.repeats)
+    }
+
+    /// The line written after a run with `repeats` repeats.
+    #[must_use]
+    pub fn summary(&self, repeats: usize) -> String {
+        let text = format!("(x{repeats} repeated)");
+        match self.style {
+            Some(style) => style.paint(text).to_string(),
+            None => text,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_runs() {
+        use Run::{New, Repeat};
+
+        let mut dedup = Dedup::new(DedupMode::Exact);
+        let runs: Vec<Run> = ["a", "a", "a", "b", "a", "a"]
+            .iter()
+            .map(|line| dedup.push(line))
+            .collect();
+        assert_eq!(runs, [New(0), Repeat, Repeat, New(2), New(0), Repeat]);
+        assert_eq!(dedup.finish(), 1);
+        assert_eq!(dedup.finish(), 0);
+
+        // Exact mode keeps lines that differ only in numbers
+        assert_eq!(dedup.push("slot 1"), New(0));
+        assert_eq!(dedup.push("slot 2"), New(0));
+    }
+
+    #[test]
+    fn test_similar_masks_numbers_and_hashes() {
+        let mut dedup = Dedup::new(DedupMode::Similar);
+        let line = "2024-12-05T00:12:36Z WARN Peer 0x3f9a dropped, 49 peers left";
+        assert_eq!(dedup.push(line), Run::New(0));
+        let line = "2024-12-05T00:12:37Z WARN Peer 0x7c21 dropped, 48 peers left";
+        assert_eq!(dedup.push(line), Run::Repeat);
+        assert_eq!(dedup.push("2024-12-05T00:12:38Z INFO Synced"), Run::New(1));
+    }
+
+    #[test]
+    fn test_similar_keeps_words() {
+        let mut dedup = Dedup::new(DedupMode::Similar);
+        assert_eq!(dedup.push("Failed2 to connect"), Run::New(0));
+        assert_eq!(dedup.push("Faile3d to connect"), Run::New(0));
+        assert_eq!(dedup.push("Faile4d to connect"), Run::Repeat);
+        assert_eq!(dedup.push("block a1b2c3 took 15ms"), Run::New(1));
+        assert_eq!(dedup.push("block ff09e8 took 9ms"), Run::Repeat);
+    }
+}
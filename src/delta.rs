@@ -0,0 +1,171 @@
+//! Time delta annotations.
+//!
+//! `--delta` prefixes each line with the time elapsed since the previous one,
+//! such as `+0.004s` or `+12.8s`, so slot gaps and hung build steps stand out.
+//! When both lines carry a timestamp the delta is taken from the log itself
+//! (see [`timestamp`](crate::timestamp)), otherwise from when the lines
+//! arrived. Gaps at or above the warn and error thresholds take those colors.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::time::{Duration, Instant};
+//! use phos::delta::DeltaTracker;
+//!
+//! let mut tracker = DeltaTracker::new(Duration::from_secs(10), Duration::from_secs(60));
+//! let now = Instant::now();
+//! tracker.measure_at("2024-12-05T00:12:24Z Slot 1", now);
+//! let delta = tracker.measure_at("2024-12-05T00:12:36.800Z Slot 2", now);
+//! assert_eq!(phos::delta::format_delta(delta), "+12.8s");
+//! ```
+
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use nu_ansi_term::Style;
+
+use crate::timestamp;
+
+/// Default gap colored as a warning.
+pub const DEFAULT_WARN: Duration = Duration::from_secs(10);
+
+/// Default gap colored as an error.
+pub const DEFAULT_ERROR: Duration = Duration::from_secs(60);
+
+/// Width the delta column is padded to, so lines stay aligned.
+const WIDTH: usize = 7;
+
+/// Measures the time between consecutive lines.
+#[derive(Debug, Clone)]
+pub struct DeltaTracker {
+    warn: Duration,
+    error: Duration,
+    /// Styles for normal, warning and error gaps (None = plain text)
+    styles: Option<[Style; 3]>,
+    /// Timestamp of the last line that had one
+    last_time: Option<DateTime<Utc>>,
+    /// When the last line arrived
+    last_arrival: Option<Instant>,
+}
+
+impl DeltaTracker {
+    /// Flag gaps of `warn` or more as warnings, and of `error` or more as errors.
+    #[must_use]
+    pub fn new(warn: Duration, error: Duration) -> Self {
+        Self {
+            warn,
+            error,
+            styles: None,
+            last_time: None,
+            last_arrival: None,
+        }
+    }
+
+    /// Paint deltas with these styles for normal, warning and error gaps.
+    #[must_use]
+    pub fn with_styles(mut self, styles: [Style; 3]) -> Self {
+        self.styles = Some(styles);
+        self
+    }
+
+    /// Time since the previous line, measured as `line` arrives now.
+    pub fn measure(&mut self, line: &str) -> TimeDelta {
+        self.measure_at(line, Instant::now())
+    }
+
+    /// Time since the previous line, for `line` arriving at `arrival`.
+    ///
+    /// The first line has a delta of zero.
+    pub fn measure_at(&mut self, line: &str, arrival: Instant) -> TimeDelta {
+        let time = timestamp::find(line).map(|found| found.time);
+        let delta = match (time, self.last_time) {
+            (Some(time), Some(last)) => time - last,
+            _ => self
+                .last_arrival
+                .and_then(|last| TimeDelta::from_std(arrival.duration_since(last)).ok())
+                .unwrap_or_default(),
+        };
+        self.last_time = time.or(self.last_time);
+        self.last_arrival = Some(arrival);
+        delta
+    }
+
+    /// Prefix the colorized `colored` with the delta of the line `line`.
+    pub fn annotate(&mut self, line: &str, colored: &str) -> String {
+        let delta = self.measure(line);
+        let text = format!("{:>WIDTH$}", format_delta(delta));
+        let millis = u128::from(delta.num_milliseconds().unsigned_abs());
+        let level = if millis >= self.error.as_millis() {
+            2
+        } else {
+            usize::from(millis >= self.warn.as_millis())
+        };
+        match self.styles {
+            Some(styles) => format!("{} {colored}", styles[level].paint(text)),
+            None => format!("{text} {colored}"),
+        }
+    }
+
+    /// Indent a line continuing a record past the delta column.
+    #[must_use]
+    pub fn indent(&self, colored: &str) -> String {
+        format!("{:WIDTH$} {colored}", "")
+    }
+}
+
+/// Format a delta: `+0.004s` under 10 seconds, `+12.8s` under a minute, then `+3m04s`.
+#[must_use]
+pub fn format_delta(delta: TimeDelta) -> String {
+    let sign = if delta < TimeDelta::zero() { '-' } else { '+' };
+    let millis = delta.num_milliseconds().unsigned_abs();
+    match millis {
+        0..10_000 => format!("{sign}{}.{:03}s", millis / 1000, millis % 1000),
+        10_000..60_000 => format!("{sign}{}.{}s", millis / 1000, millis % 1000 / 100),
+        _ => format!("{sign}{}", timestamp::format_duration(delta)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_delta() {
+        assert_eq!(format_delta(TimeDelta::milliseconds(4)), "+0.004s");
+        assert_eq!(format_delta(TimeDelta::milliseconds(12_840)), "+12.8s");
+        assert_eq!(format_delta(TimeDelta::seconds(184)), "+3m04s");
+        assert_eq!(format_delta(TimeDelta::milliseconds(-500)), "-0.500s");
+    }
+
+    #[test]
+    fn test_timestamps_then_arrival() {
+        let mut tracker = DeltaTracker::new(DEFAULT_WARN, DEFAULT_ERROR);
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        assert_eq!(
+            tracker.measure_at("[12-05|00:12:24.000] a", at(0)),
+            TimeDelta::zero()
+        );
+        // Timestamps win over arrival times
+        let delta = tracker.measure_at("[12-05|00:12:36.000] b", at(5));
+        assert_eq!(delta, TimeDelta::seconds(12));
+        // Lines without one fall back to arrival times
+        let delta = tracker.measure_at("    at frame", at(250));
+        assert_eq!(delta, TimeDelta::milliseconds(245));
+        let delta = tracker.measure_at("[12-05|00:12:37.000] c", at(260));
+        assert_eq!(delta, TimeDelta::seconds(1));
+    }
+
+    #[test]
+    fn test_annotate_thresholds() {
+        let styles = [Style::new(), Style::new().bold(), Style::new().italic()];
+        let mut tracker = DeltaTracker::new(DEFAULT_WARN, DEFAULT_ERROR).with_styles(styles);
+        tracker.annotate("2024-12-05T00:00:00Z a", "a");
+        let line = tracker.annotate("2024-12-05T00:00:12Z b", "b");
+        assert_eq!(line, format!("{} b", styles[1].paint(" +12.0s")));
+        let line = tracker.annotate("2024-12-05T00:05:00Z c", "c");
+        assert_eq!(line, format!("{} c", styles[2].paint(" +4m48s")));
+        assert_eq!(tracker.indent("d"), "        d");
+    }
+}
@@ -0,0 +1,430 @@
+//! Grep-like output filtering with context lines.
+//!
+//! A [`LineFilter`] selects lines (or multi-line records) whose input text
+//! matches a pattern, optionally inverted, plus a number of context lines
+//! before and after each match. [`ContextBuffer`] turns a stream of lines into
+//! the lines to show, with a separator between non-contiguous hunks like
+//! `grep -C`. Lines are still colorized and counted in statistics when they
+//! are filtered out; only their output is dropped.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use phos::filter::{ContextBuffer, Filtered, LineFilter};
+//!
+//! let filter = LineFilter::new(r"\bERROR\b").unwrap().with_context(1, 0);
+//! let mut context = ContextBuffer::new(filter);
+//!
+//! let mut shown = Vec::new();
+//! for line in ["a", "b", "ERROR one", "c", "d", "ERROR two"] {
+//!     shown.extend(context.push(line, line).map(|f| match f {
+//!         Filtered::Separator => "--",
+//!         Filtered::Item(line) => line,
+//!     }));
+//! }
+//! assert_eq!(shown, ["b", "ERROR one", "--", "d", "ERROR two"]);
+//! ```
+
+use std::borrow::Cow;
+use std::collections::vec_deque::{Drain, VecDeque};
+use std::io::{self, Write};
+
+use regex::Regex;
+
+use crate::colors::strip_ansi;
+use crate::dedup::{Dedup, Run};
+use crate::delta::DeltaTracker;
+use crate::level::LevelFilter;
+use crate::line_reader::{LineEnding, RawLine};
+use crate::record::Record;
+
+/// Separator written between non-contiguous hunks.
+pub const SEPARATOR: &str = "--";
+
+/// Which lines to show.
+#[derive(Debug, Clone)]
+pub struct LineFilter {
+    pattern: Regex,
+    invert: bool,
+    before: usize,
+    after: usize,
+}
+
+impl LineFilter {
+    /// Show lines matching `pattern`.
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            pattern: Regex::new(pattern)?,
+            invert: false,
+            before: 0,
+            after: 0,
+        })
+    }
+
+    /// Show lines that do not match instead.
+    #[must_use]
+    pub fn with_invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    /// Show `before` lines before and `after` lines after each selected line.
+    #[must_use]
+    pub fn with_context(mut self, before: usize, after: usize) -> Self {
+        self.before = before;
+        self.after = after;
+        self
+    }
+
+    /// Whether `text` is selected (escape sequences are ignored).
+    #[must_use]
+    pub fn is_selected(&self, text: &str) -> bool {
+        self.pattern.is_match(&strip_ansi(text)) != self.invert
+    }
+}
+
+/// One piece of filtered output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filtered<T> {
+    /// Marks a gap between two hunks
+    Separator,
+    /// A line (or record) to show
+    Item(T),
+}
+
+/// Tracks context around selected lines in a stream.
+///
+/// Holds up to `before` unselected lines until it is known whether a match
+/// follows them.
+#[derive(Debug)]
+pub struct ContextBuffer<T> {
+    filter: LineFilter,
+    /// Unselected lines that may become before-context
+    before: VecDeque<T>,
+    /// Output ready to be taken by the caller
+    ready: VecDeque<Filtered<T>>,
+    /// After-context lines still to show
+    after_left: usize,
+    /// Whether anything was shown yet
+    shown: bool,
+    /// Whether a line was dropped since the last one shown
+    gap: bool,
+}
+
+impl<T> ContextBuffer<T> {
+    /// Create a buffer applying `filter`.
+    #[must_use]
+    pub fn new(filter: LineFilter) -> Self {
+        Self {
+            before: VecDeque::with_capacity(filter.before),
+            filter,
+            ready: VecDeque::new(),
+            after_left: 0,
+            shown: false,
+            gap: false,
+        }
+    }
+
+    /// Add a line with input text `text`, returning what to show now.
+    pub fn push(&mut self, text: &str, item: T) -> Drain<'_, Filtered<T>> {
+        if self.filter.is_selected(text) {
+            // Separators only make sense when context lines are shown, as in grep
+            let context = self.filter.before > 0 || self.filter.after > 0;
+            if self.shown && self.gap && context {
+                self.ready.push_back(Filtered::Separator);
+            }
+            self.ready.extend(self.before.drain(..).map(Filtered::Item));
+            self.ready.push_back(Filtered::Item(item));
+            self.after_left = self.filter.after;
+            self.shown = true;
+            self.gap = false;
+        } else if self.after_left > 0 {
+            self.ready.push_back(Filtered::Item(item));
+            self.after_left -= 1;
+        } else if self.filter.before > 0 {
+            if self.before.len() == self.filter.before {
+                self.before.pop_front();
+                self.gap = true;
+            }
+            self.before.push_back(item);
+        } else {
+            self.gap = true;
+        }
+        self.ready.drain(..)
+    }
+}
+
+/// A line or record held for context: its input text and colorized lines.
+type Unit = (String, Vec<(RawLine, String)>);
+
+/// A writer for colorized lines that keeps only the selected hunks when filtering.
+///
+/// Lines below a [minimum level](Self::with_level) are dropped before the
+/// line filter sees them, and [repeats](Self::with_dedup) of the lines left
+/// are collapsed. [Time deltas](Self::with_delta) are measured on every line,
+/// shown or not.
+#[derive(Debug)]
+pub struct FilteredOutput<W> {
+    sink: Sink<W>,
+    delta: Option<DeltaTracker>,
+    level: Option<LevelFilter>,
+    context: Option<ContextBuffer<Unit>>,
+}
+
+impl<W: Write> FilteredOutput<W> {
+    /// Wrap `out`, applying `filter` (None = write everything).
+    pub fn new(out: W, filter: Option<LineFilter>) -> Self {
+        Self {
+            sink: Sink { out, dedup: None },
+            delta: None,
+            level: None,
+            context: filter.map(ContextBuffer::new),
+        }
+    }
+
+    /// Also drop lines below a minimum level (None = any level).
+    #[must_use]
+    pub fn with_level(mut self, level: Option<LevelFilter>) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Prefix lines with the time since the previous one (None = no prefix).
+    #[must_use]
+    pub fn with_delta(mut self, delta: Option<DeltaTracker>) -> Self {
+        self.delta = delta;
+        self
+    }
+
+    /// Collapse runs of repeated lines (None = write every line).
+    #[must_use]
+    pub fn with_dedup(mut self, dedup: Option<Dedup>) -> Self {
+        self.sink.dedup = dedup;
+        self
+    }
+
+    /// Write a colorized line, selected by its input text.
+    ///
+    /// The line's original ending is preserved, and carriage-return redraws are
+    /// flushed immediately so progress output stays live.
+    pub fn write_line(&mut self, line: &RawLine, colored: &str) -> io::Result<()> {
+        let text = line.to_str_lossy();
+        let annotated = self
+            .delta
+            .as_mut()
+            .map(|delta| delta.annotate(&text, colored));
+        let colored = annotated.as_deref().unwrap_or(colored);
+        if let Some(level) = self.level.as_mut() {
+            if !level.is_shown(&text) {
+                return Ok(());
+            }
+        }
+        if self.context.is_none() {
+            return self.sink.write(&text, [(line, colored)]);
+        }
+        self.write_unit(&text, [(line, colored)])
+    }
+
+    /// Write the colorized lines of a record, selected as one unit by its text.
+    ///
+    /// A time delta is shown on its first line only.
+    pub fn write_record<'a>(
+        &mut self,
+        record: &Record,
+        lines: impl IntoIterator<Item = (&'a RawLine, &'a str)>,
+    ) -> io::Result<()> {
+        let lines: Vec<(&RawLine, Cow<str>)> = lines
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (line, colored))| match self.delta.as_mut() {
+                Some(delta) if idx == 0 && !record.is_continued() => {
+                    let annotated = delta.annotate(&line.to_str_lossy(), colored);
+                    (line, Cow::Owned(annotated))
+                }
+                Some(delta) => (line, Cow::Owned(delta.indent(colored))),
+                None => (line, Cow::Borrowed(colored)),
+            })
+            .collect();
+        let lines = lines
+            .iter()
+            .map(|(line, colored)| (*line, colored.as_ref()));
+        if let Some(level) = self.level.as_mut() {
+            if !level.is_record_shown(record) {
+                return Ok(());
+            }
+        }
+        if self.context.is_none() {
+            return self.sink.write(&record.text(), lines);
+        }
+        self.write_unit(&record.text(), lines)
+    }
+
+    /// Write the count of a pending run of repeats, then flush the writer.
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.sink.end_run()?;
+        self.sink.out.flush()
+    }
+
+    fn write_unit<'a>(
+        &mut self,
+        text: &str,
+        lines: impl IntoIterator<Item = (&'a RawLine, &'a str)>,
+    ) -> io::Result<()> {
+        let Some(context) = self.context.as_mut() else {
+            return Ok(());
+        };
+        let unit: Vec<_> = lines
+            .into_iter()
+            .map(|(line, colored)| (line.clone(), colored.to_string()))
+            .collect();
+        if unit.is_empty() {
+            return Ok(());
+        }
+        for filtered in context.push(text, (text.to_string(), unit)) {
+            match filtered {
+                Filtered::Separator => {
+                    self.sink.end_run()?;
+                    writeln!(self.sink.out, "{SEPARATOR}")?;
+                }
+                Filtered::Item((text, unit)) => {
+                    let lines = unit.iter().map(|(line, colored)| (line, colored.as_str()));
+                    self.sink.write(&text, lines)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The end of the output: collapses repeated units, then writes them.
+#[derive(Debug)]
+struct Sink<W> {
+    out: W,
+    dedup: Option<Dedup>,
+}
+
+impl<W: Write> Sink<W> {
+    /// Write the lines of a unit with input text `text`, unless it is a repeat.
+    fn write<'a>(
+        &mut self,
+        text: &str,
+        lines: impl IntoIterator<Item = (&'a RawLine, &'a str)>,
+    ) -> io::Result<()> {
+        if let Some(dedup) = self.dedup.as_mut() {
+            match dedup.push(text) {
+                Run::Repeat => return Ok(()),
+                Run::New(0) => {}
+                Run::New(repeats) => writeln!(self.out, "{}", dedup.summary(repeats))?,
+            }
+        }
+        lines
+            .into_iter()
+            .try_for_each(|(line, colored)| write_raw(&mut self.out, line, colored))
+    }
+
+    /// End the current run of repeats, writing its count if it had any.
+    fn end_run(&mut self) -> io::Result<()> {
+        let Some(dedup) = self.dedup.as_mut() else {
+            return Ok(());
+        };
+        match dedup.finish() {
+            0 => Ok(()),
+            repeats => writeln!(self.out, "{}", dedup.summary(repeats)),
+        }
+    }
+}
+
+/// Write one colorized line with its original ending.
+fn write_raw<W: Write>(out: &mut W, line: &RawLine, colored: &str) -> io::Result<()> {
+    line.write_with(out, colored)?;
+    if line.ending() == LineEnding::Cr {
+        out.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dedup::DedupMode;
+
+    fn shown(filter: LineFilter, lines: &[&'static str]) -> Vec<&'static str> {
+        let mut context = ContextBuffer::new(filter);
+        let mut shown = Vec::new();
+        for &line in lines {
+            shown.extend(context.push(line, line).map(|f| match f {
+                Filtered::Separator => SEPARATOR,
+                Filtered::Item(line) => line,
+            }));
+        }
+        shown
+    }
+
+    #[test]
+    fn test_grep_and_invert() {
+        let lines = ["INFO a", "ERROR b", "INFO c"];
+        let filter = LineFilter::new("ERROR").unwrap();
+        assert_eq!(shown(filter.clone(), &lines), ["ERROR b"]);
+        assert_eq!(
+            shown(filter.with_invert(true), &lines),
+            ["INFO a", "INFO c"]
+        );
+    }
+
+    #[test]
+    fn test_context_and_separators() {
+        let lines = ["1", "2", "x3", "4", "5", "6", "x7", "8", "x9", "10"];
+        let filter = LineFilter::new("x").unwrap().with_context(1, 1);
+        assert_eq!(
+            shown(filter, &lines),
+            ["2", "x3", "4", "--", "6", "x7", "8", "x9", "10"]
+        );
+
+        // Overlapping context is shown once, without a separator
+        let filter = LineFilter::new("x").unwrap().with_context(2, 2);
+        assert_eq!(
+            shown(filter, &lines[..7]),
+            ["1", "2", "x3", "4", "5", "6", "x7"]
+        );
+
+        // No separators without context
+        let filter = LineFilter::new("x").unwrap();
+        assert_eq!(shown(filter, &lines), ["x3", "x7", "x9"]);
+    }
+
+    #[test]
+    fn test_filtered_output() {
+        let line = |text: &str| RawLine::new(text, LineEnding::Lf);
+        let filter = LineFilter::new("x").unwrap().with_context(0, 1);
+        let mut out = FilteredOutput::new(Vec::new(), Some(filter));
+        for text in ["x1", "2", "3", "x4"] {
+            out.write_line(&line(text), &text.to_uppercase()).unwrap();
+        }
+        assert_eq!(String::from_utf8(out.sink.out).unwrap(), "X1\n2\n--\nX4\n");
+
+        let mut out = FilteredOutput::new(Vec::new(), None);
+        out.write_line(&line("a"), "A").unwrap();
+        assert_eq!(out.sink.out, b"A\n");
+    }
+
+    #[test]
+    fn test_dedup_output() {
+        let line = |text: &str| RawLine::new(text, LineEnding::Lf);
+        let dedup = Dedup::new(DedupMode::Similar);
+        let mut out = FilteredOutput::new(Vec::new(), None).with_dedup(Some(dedup));
+        for text in ["drop 1", "drop 2", "drop 3", "ok", "drop 4", "drop 5"] {
+            out.write_line(&line(text), text).unwrap();
+        }
+        out.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(out.sink.out).unwrap(),
+            "drop 1\n(x2 repeated)\nok\ndrop 4\n(x1 repeated)\n"
+        );
+    }
+
+    #[test]
+    fn test_matches_text_without_escapes() {
+        let filter = LineFilter::new("ERROR: x").unwrap();
+        assert!(filter.is_selected("\x1b[31mERROR\x1b[0m: x"));
+    }
+}
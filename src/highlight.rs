@@ -0,0 +1,132 @@
+//! Ad-hoc highlight patterns layered on top of a program.
+//!
+//! `--highlight REGEX[:color]` marks a validator index, tx hash or request id
+//! in a live stream without writing a program. Each pattern becomes a
+//! high-priority [`Rule`] shown in reverse video, so it stands out on any
+//! theme. Patterns without a color get the next one from a fixed palette;
+//! `:hashed` gives each matched text its own stable color.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use phos::highlight;
+//!
+//! let rules = highlight::highlight_rules(&["0xdeadbeef", r"validator=\d+:error"]).unwrap();
+//! assert_eq!(rules.len(), 2);
+//! assert!(rules[1].is_match("validator=42"));
+//! ```
+
+use std::sync::Arc;
+
+use crate::colors::{Color, ColorSpec};
+use crate::rule::Rule;
+
+/// Priority of highlight rules, above any program rule.
+pub const HIGHLIGHT_PRIORITY: i32 = i32::MAX;
+
+/// Colors assigned in turn to patterns without an explicit color.
+const PALETTE: &[&str] = &[
+    "bright_yellow",
+    "bright_cyan",
+    "bright_magenta",
+    "bright_green",
+    "bright_red",
+    "bright_blue",
+];
+
+/// Split a `REGEX[:color]` spec into its pattern and optional color.
+///
+/// The suffix after the last `:` is a color only if it names one (semantic,
+/// ANSI or hex), so patterns such as `\d{2}:\d{2}` are kept whole.
+#[must_use]
+pub fn parse_spec(spec: &str) -> (&str, Option<Color>) {
+    let Some((pattern, suffix)) = spec.rsplit_once(':') else {
+        return (spec, None);
+    };
+    let color = match ColorSpec::from_name(suffix) {
+        ColorSpec::Semantic(semantic) => Color::Semantic(semantic),
+        ColorSpec::Named(name) => Color::Named(name),
+        ColorSpec::Hex(hex) => Color::Hex(hex),
+        ColorSpec::Hashed => Color::Hashed,
+        ColorSpec::Domain(_) => return (spec, None),
+    };
+    if pattern.is_empty() {
+        return (spec, None);
+    }
+    (pattern, Some(color))
+}
+
+/// Build the highlight rule for one spec; `index` picks its palette color.
+pub fn highlight_rule(spec: &str, index: usize) -> Result<Rule, regex::Error> {
+    let (pattern, color) = parse_spec(spec);
+    let color = color.unwrap_or_else(|| Color::named(PALETTE[index % PALETTE.len()]));
+    Ok(Rule::new(pattern)?
+        .color(color)
+        .bold()
+        .reverse()
+        .priority(HIGHLIGHT_PRIORITY)
+        .build())
+}
+
+/// Build highlight rules for all specs, each with a distinct palette color.
+///
+/// Add them to a program's rules with [`prepend`].
+pub fn highlight_rules<S: AsRef<str>>(specs: &[S]) -> Result<Vec<Rule>, regex::Error> {
+    specs
+        .iter()
+        .enumerate()
+        .map(|(idx, spec)| highlight_rule(spec.as_ref(), idx))
+        .collect()
+}
+
+/// Put `highlights` ahead of a program's `rules`, so they win over its overlapping matches.
+#[must_use]
+pub fn prepend(rules: Arc<[Rule]>, highlights: &[Rule]) -> Arc<[Rule]> {
+    if highlights.is_empty() {
+        return rules;
+    }
+    highlights.iter().chain(rules.iter()).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::SemanticColor;
+
+    #[test]
+    fn test_parse_spec() {
+        assert_eq!(parse_spec("0xabc"), ("0xabc", None));
+        assert_eq!(
+            parse_spec("slot=\\d+:error"),
+            ("slot=\\d+", Some(Color::Semantic(SemanticColor::Error)))
+        );
+        assert_eq!(
+            parse_spec("id:#ff8800"),
+            ("id", Some(Color::hex("#ff8800")))
+        );
+        // A suffix that is not a color belongs to the pattern
+        assert_eq!(parse_spec(r"\d{2}:\d{2}"), (r"\d{2}:\d{2}", None));
+        assert_eq!(parse_spec(":red"), (":red", None));
+    }
+
+    #[test]
+    fn test_distinct_styles() {
+        let rules = highlight_rules(&["a", "b", "c:red"]).unwrap();
+        assert_ne!(rules[0].colors, rules[1].colors);
+        assert_eq!(rules[2].colors, vec![Color::named("red")]);
+        assert!(rules
+            .iter()
+            .all(|rule| rule.attributes.reverse && rule.priority == HIGHLIGHT_PRIORITY));
+        assert!(highlight_rules(&["("]).is_err());
+    }
+
+    #[test]
+    fn test_prepend() {
+        let rules: Arc<[Rule]> = Arc::from([Rule::new("program").unwrap().build()]);
+        let highlights = highlight_rules(&["mark"]).unwrap();
+        let prepended = prepend(Arc::clone(&rules), &highlights);
+        let patterns: Vec<&str> = prepended.iter().map(|r| r.regex.as_str()).collect();
+        assert_eq!(patterns, ["mark", "program"]);
+        assert!(Arc::ptr_eq(&prepend(Arc::clone(&rules), &[]), &rules));
+    }
+}
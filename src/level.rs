@@ -0,0 +1,255 @@
+//! Minimum log level filtering.
+//!
+//! `--level warn` hides lines below a threshold. A [`LevelFilter`] classifies
+//! each line by the level field of JSON and logfmt lines, then by the
+//! program's own level vocabulary (its rules colored with a level color, such
+//! as Lighthouse `CRIT`/`ERRO`, Nimbus `FTL`/`WRN` or syslog priorities), then
+//! by the generic patterns used for statistics. Lines continuing a previous
+//! line, such as stack frames, inherit its level; other lines without one are
+//! shown.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use phos::level::{LevelFilter, LogLevel};
+//!
+//! let mut filter = LevelFilter::new(LogLevel::Warn);
+//! assert!(!filter.is_shown("DEBUG peer connected"));
+//! assert!(filter.is_shown("ERROR block import failed"));
+//! assert!(filter.is_shown("    at Importer.run(Importer.java:42)"));
+//! assert!(filter.is_shown(r#"{"level":"warn","msg":"slow"}"#));
+//! ```
+
+use clap::ValueEnum;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::colors::{strip_ansi, Color, SemanticColor};
+use crate::record::{MultilineRules, Record};
+use crate::rule::Rule;
+use crate::stats::STATS_PATTERNS;
+use crate::structured::{self, FieldNames};
+
+/// Log severity, from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    #[value(alias = "warning")]
+    #[serde(alias = "warning")]
+    Warn,
+    #[value(alias = "err")]
+    #[serde(alias = "err")]
+    Error,
+}
+
+impl LogLevel {
+    /// The level a semantic color stands for, if it is a level color.
+    #[must_use]
+    pub fn from_semantic(color: SemanticColor) -> Option<Self> {
+        match color {
+            SemanticColor::Error => Some(Self::Error),
+            SemanticColor::Warn => Some(Self::Warn),
+            SemanticColor::Info => Some(Self::Info),
+            SemanticColor::Debug => Some(Self::Debug),
+            SemanticColor::Trace => Some(Self::Trace),
+            _ => None,
+        }
+    }
+}
+
+/// Hides lines below a minimum level.
+#[derive(Debug, Clone)]
+pub struct LevelFilter {
+    min: LogLevel,
+    /// Level patterns from the program's rules
+    vocabulary: Vec<(Regex, LogLevel)>,
+    /// Field names holding the level of structured lines
+    names: FieldNames,
+    /// Rules deciding which lines continue the previous one
+    continuation: MultilineRules,
+    /// Level of the last line or record seen
+    current: Option<LogLevel>,
+}
+
+impl LevelFilter {
+    /// Show lines at `min` or above.
+    #[must_use]
+    pub fn new(min: LogLevel) -> Self {
+        Self {
+            min,
+            vocabulary: Vec::new(),
+            names: FieldNames::default(),
+            continuation: MultilineRules::default(),
+            current: None,
+        }
+    }
+
+    /// Recognize levels by the rules colored with a level color.
+    ///
+    /// Field rules are ignored, since their patterns only match field values.
+    #[must_use]
+    pub fn with_rules(mut self, rules: &[Rule]) -> Self {
+        self.vocabulary = rules
+            .iter()
+            .filter(|rule| rule.field.is_none())
+            .filter_map(|rule| match rule.colors.first() {
+                Some(Color::Semantic(color)) => {
+                    Some((rule.regex.clone(), LogLevel::from_semantic(*color)?))
+                }
+                _ => None,
+            })
+            .collect();
+        self
+    }
+
+    /// Read the level of structured lines from these fields.
+    #[must_use]
+    pub fn with_field_names(mut self, names: FieldNames) -> Self {
+        self.names = names;
+        self
+    }
+
+    /// The level of a line, if one is recognized.
+    ///
+    /// Among the program's level patterns, the leftmost match wins, since the
+    /// level usually precedes a message that may mention other levels.
+    #[must_use]
+    pub fn classify(&self, line: &str) -> Option<LogLevel> {
+        let line = strip_ansi(line);
+        self.structured_level(&line)
+            .or_else(|| {
+                self.vocabulary
+                    .iter()
+                    .filter_map(|(regex, level)| Some((regex.find(&line)?.start(), *level)))
+                    .min_by_key(|&(start, _)| start)
+                    .map(|(_, level)| level)
+            })
+            .or_else(|| generic_level(&line))
+    }
+
+    /// Whether to show a line, tracking its level for the lines continuing it.
+    pub fn is_shown(&mut self, line: &str) -> bool {
+        let inherited = self
+            .current
+            .filter(|_| self.continuation.is_continuation(line));
+        self.current = inherited.or_else(|| self.classify(line));
+        self.current.is_none_or(|level| level >= self.min)
+    }
+
+    /// Whether to show a record, classified by its first line.
+    ///
+    /// A continued record has the level of the record it extends.
+    pub fn is_record_shown(&mut self, record: &Record) -> bool {
+        if !record.is_continued() {
+            self.current = record
+                .lines()
+                .first()
+                .and_then(|head| self.classify(&head.to_str_lossy()));
+        }
+        self.current.is_none_or(|level| level >= self.min)
+    }
+
+    /// The level field of a JSON or logfmt line.
+    fn structured_level(&self, line: &str) -> Option<LogLevel> {
+        let fields = structured::parse(line)?;
+        let field = self
+            .names
+            .level
+            .iter()
+            .find_map(|name| fields.iter().find(|f| f.key == name.as_str()))?;
+        structured::level_color(&field.value).and_then(LogLevel::from_semantic)
+    }
+}
+
+/// The level found by the statistics patterns, most severe first.
+fn generic_level(line: &str) -> Option<LogLevel> {
+    let patterns = &*STATS_PATTERNS;
+    [
+        (&patterns.error, LogLevel::Error),
+        (&patterns.warn, LogLevel::Warn),
+        (&patterns.info, LogLevel::Info),
+        (&patterns.debug, LogLevel::Debug),
+        (&patterns.trace, LogLevel::Trace),
+    ]
+    .into_iter()
+    .find(|(pattern, _)| pattern.is_match(line))
+    .map(|(_, level)| level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::line_reader::{LineEnding, RawLine};
+    use crate::programs;
+    use crate::record::RecordAssembler;
+
+    #[test]
+    fn test_program_vocabulary() {
+        let registry = programs::default_registry();
+        let rules = registry.get("lighthouse").unwrap().rules();
+        let filter = LevelFilter::new(LogLevel::Warn).with_rules(&rules);
+        assert_eq!(
+            filter.classify("Dec 05 00:12:36.557 CRIT Database corrupt"),
+            Some(LogLevel::Error)
+        );
+        assert_eq!(
+            filter.classify("Dec 05 00:12:36.557 INFO Retrying after error"),
+            Some(LogLevel::Info)
+        );
+
+        let rules = registry.get("nimbus").unwrap().rules();
+        let filter = LevelFilter::new(LogLevel::Warn).with_rules(&rules);
+        assert_eq!(
+            filter.classify("WRN 2024-01-15 Low peers"),
+            Some(LogLevel::Warn)
+        );
+        assert_eq!(
+            filter.classify("FTL 2024-01-15 Crashed"),
+            Some(LogLevel::Error)
+        );
+    }
+
+    #[test]
+    fn test_structured_and_generic_levels() {
+        let filter = LevelFilter::new(LogLevel::Info);
+        assert_eq!(
+            filter.classify(r#"{"level":50,"msg":"x"}"#),
+            Some(LogLevel::Error)
+        );
+        assert_eq!(filter.classify("level=dbug msg=x"), Some(LogLevel::Debug));
+        assert_eq!(
+            filter.classify("\x1b[33mWARNING\x1b[0m disk"),
+            Some(LogLevel::Warn)
+        );
+        assert_eq!(filter.classify("no level here"), None);
+        assert!(LogLevel::Error > LogLevel::Warn && LogLevel::Debug > LogLevel::Trace);
+    }
+
+    #[test]
+    fn test_continuations_inherit_level() {
+        let mut filter = LevelFilter::new(LogLevel::Info);
+        let shown: Vec<bool> = [
+            "DEBUG dumping state",
+            "  slot=1",
+            "ERROR failed",
+            "\tat Foo.bar(Foo.java:1)",
+            "plain line",
+        ]
+        .iter()
+        .map(|line| filter.is_shown(line))
+        .collect();
+        assert_eq!(shown, [false, false, true, true, true]);
+
+        let mut assembler = RecordAssembler::new(MultilineRules::default());
+        let mut records = Vec::new();
+        for line in ["DEBUG trace", "  detail", "WARN slow"] {
+            records.extend(assembler.push(RawLine::new(line, LineEnding::Lf)));
+        }
+        records.extend(assembler.flush());
+        let shown: Vec<bool> = records.iter().map(|r| filter.is_record_shown(r)).collect();
+        assert_eq!(shown, [false, true]);
+    }
+}
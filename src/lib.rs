@@ -92,30 +92,49 @@
 
 pub mod aggregator;
 pub mod alert;
+pub mod ansi;
 pub mod category;
+pub mod color_depth;
 pub mod colorizer;
 pub mod colors;
 pub mod config;
+pub mod dedup;
+pub mod delta;
+pub mod filter;
+pub mod highlight;
+pub mod level;
+pub mod line_reader;
 pub mod program;
 pub mod programs;
 #[cfg(unix)]
 pub mod pty;
+pub mod record;
 pub mod rule;
 pub mod shell;
 pub mod stats;
+pub mod structured;
 pub mod theme;
+pub mod timestamp;
 
 pub use alert::{AlertCondition, AlertManager, AlertManagerBuilder, AlertSeverity};
+pub use ansi::AnsiMode;
 pub use category::{Category, ParseCategoryError};
+pub use color_depth::ColorDepth;
 pub use colorizer::Colorizer;
 pub use colors::{contains_ansi, parse_hex_rgb, strip_ansi, Color, ColorSpec, SemanticColor};
 pub use config::{
     load_config_file, AlertsConfig, Config, FileFormat, GlobalConfig, PtyConfig, RuleConfig,
 };
+pub use filter::LineFilter;
+pub use level::{LevelFilter, LogLevel};
+pub use line_reader::{LineEnding, LineReader, RawLine};
 pub use program::{Program, ProgramInfo, ProgramRegistry};
+pub use record::{MultilineRules, Record, RecordAssembler};
 pub use rule::Rule;
 pub use stats::{Stats, StatsCollector, StatsExportFormat, StatsJson};
+pub use structured::FieldNames;
 pub use theme::{Theme, ThemeConfig, ThemeLoadError};
+pub use timestamp::TimeFormat;
 
 /// Prelude for convenient imports.
 pub mod prelude {
@@ -0,0 +1,294 @@
+//! Byte-oriented line reading for log streams.
+//!
+//! [`BufRead::lines`] fails on the first invalid UTF-8 byte and drops line
+//! terminators. [`LineReader`] instead splits raw bytes into [`RawLine`]s that
+//! remember their exact ending, so output can be written back byte-for-byte:
+//!
+//! - Invalid UTF-8 is kept as-is; callers colorize a lossy copy, and lines
+//!   that pass through unchanged are written back as their original bytes.
+//! - `\r\n` endings are preserved, even when split across reads.
+//! - A bare `\r` (progress redraws from docker pull, cargo, curl) ends a line
+//!   of its own, so each redraw is colorized separately.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use phos::line_reader::{LineEnding, LineReader};
+//!
+//! let input: &[u8] = b"ok\r\n10%\r50%\rdone\n\xff junk";
+//! let lines: Vec<_> = LineReader::new(input).collect::<Result<_, _>>().unwrap();
+//!
+//! assert_eq!(lines.len(), 5);
+//! assert_eq!(lines[0].as_str(), Some("ok"));
+//! assert_eq!(lines[0].ending(), LineEnding::CrLf);
+//! assert_eq!(lines[1].ending(), LineEnding::Cr);
+//! assert_eq!(lines[4].as_str(), None); // invalid UTF-8
+//! assert_eq!(lines[4].ending(), LineEnding::None);
+//! ```
+
+use std::borrow::Cow;
+use std::io::{self, BufRead, Write};
+
+/// How a line was terminated in the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    /// `\n`
+    #[default]
+    Lf,
+    /// `\r\n`
+    CrLf,
+    /// Bare `\r` (carriage-return redraw)
+    Cr,
+    /// Last line of the input without a terminator
+    None,
+}
+
+impl LineEnding {
+    /// The terminator bytes to write back.
+    #[must_use]
+    pub fn as_bytes(self) -> &'static [u8] {
+        match self {
+            Self::Lf => b"\n",
+            Self::CrLf => b"\r\n",
+            Self::Cr => b"\r",
+            Self::None => b"",
+        }
+    }
+}
+
+/// A line of raw input bytes with its original terminator (not included in the bytes).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawLine {
+    bytes: Vec<u8>,
+    ending: LineEnding,
+}
+
+impl RawLine {
+    /// Create a line from raw bytes and an ending.
+    #[must_use]
+    pub fn new(bytes: impl Into<Vec<u8>>, ending: LineEnding) -> Self {
+        Self {
+            bytes: bytes.into(),
+            ending,
+        }
+    }
+
+    /// The raw line content, without the terminator.
+    #[must_use]
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The line content as text, or None if it is not valid UTF-8.
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.bytes).ok()
+    }
+
+    /// The line content as text, replacing invalid sequences with U+FFFD.
+    #[must_use]
+    pub fn to_str_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.bytes)
+    }
+
+    /// How the line was terminated.
+    #[must_use]
+    pub fn ending(&self) -> LineEnding {
+        self.ending
+    }
+
+    /// Write `text` (the processed line) followed by the original terminator.
+    ///
+    /// A line that is not valid UTF-8 but passed through unchanged (`text` is
+    /// its lossy copy) is written back as its original bytes.
+    pub fn write_with<W: Write + ?Sized>(&self, out: &mut W, text: &str) -> io::Result<()> {
+        if self.as_str().is_none() && text == self.to_str_lossy() {
+            out.write_all(&self.bytes)?;
+        } else {
+            out.write_all(text.as_bytes())?;
+        }
+        out.write_all(self.ending.as_bytes())
+    }
+}
+
+/// Splits a byte stream into [`RawLine`]s on `\n`, `\r\n` and bare `\r`.
+///
+/// Never fails on invalid UTF-8; only I/O errors are returned.
+pub struct LineReader<R> {
+    reader: R,
+    buffered: bool,
+}
+
+impl<R: BufRead> LineReader<R> {
+    /// Wrap a buffered reader.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffered: false,
+        }
+    }
+
+    /// Whether input after the last line read is already buffered.
+    ///
+    /// When false, the next read may block waiting for input, so streaming
+    /// callers can emit anything they are holding back first.
+    #[must_use]
+    pub fn has_buffered(&self) -> bool {
+        self.buffered
+    }
+
+    /// Read the next line into `line`, reusing its buffer.
+    /// Returns false at end of input.
+    pub fn read_line(&mut self, line: &mut RawLine) -> io::Result<bool> {
+        line.bytes.clear();
+
+        loop {
+            let available = match self.reader.fill_buf() {
+                Ok(buf) => buf,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+
+            if available.is_empty() {
+                self.buffered = false;
+                line.ending = LineEnding::None;
+                return Ok(!line.bytes.is_empty());
+            }
+
+            let Some(pos) = available.iter().position(|&b| b == b'\n' || b == b'\r') else {
+                let len = available.len();
+                line.bytes.extend_from_slice(available);
+                self.reader.consume(len);
+                continue;
+            };
+
+            line.bytes.extend_from_slice(&available[..pos]);
+            let (ending, consumed) = match (available[pos], available.get(pos + 1)) {
+                (b'\n', _) => (LineEnding::Lf, pos + 1),
+                (_, Some(b'\n')) => (LineEnding::CrLf, pos + 2),
+                (_, Some(_)) => (LineEnding::Cr, pos + 1),
+                (_, None) => {
+                    // A `\r` ending the buffer may be the first half of a `\r\n`
+                    self.reader.consume(pos + 1);
+                    line.ending = self.ending_after_cr()?;
+                    return Ok(true);
+                }
+            };
+            self.buffered = available.len() > consumed;
+            self.reader.consume(consumed);
+            line.ending = ending;
+            return Ok(true);
+        }
+    }
+}
+
+impl<R: BufRead> LineReader<R> {
+    /// Read past a `\r` that ended the buffer, taking the `\n` of a `\r\n` if it follows.
+    ///
+    /// This waits for the next read; a redraw written as `\r<text>` is not
+    /// affected, since its `\r` arrives together with the text after it.
+    fn ending_after_cr(&mut self) -> io::Result<LineEnding> {
+        let available = loop {
+            match self.reader.fill_buf() {
+                Ok(buf) => break buf,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        };
+        let (ending, consumed) = match available.first() {
+            Some(b'\n') => (LineEnding::CrLf, 1),
+            _ => (LineEnding::Cr, 0),
+        };
+        self.buffered = available.len() > consumed;
+        self.reader.consume(consumed);
+        Ok(ending)
+    }
+}
+
+impl<R: BufRead> Iterator for LineReader<R> {
+    type Item = io::Result<RawLine>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = RawLine::default();
+        match self.read_line(&mut line) {
+            Ok(true) => Some(Ok(line)),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(input: &[u8]) -> Vec<RawLine> {
+        LineReader::new(input).collect::<io::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn test_line_endings() {
+        let lines = read_all(b"a\nb\r\nc\rd");
+        let endings: Vec<_> = lines.iter().map(RawLine::ending).collect();
+        assert_eq!(
+            endings,
+            [
+                LineEnding::Lf,
+                LineEnding::CrLf,
+                LineEnding::Cr,
+                LineEnding::None
+            ]
+        );
+        assert_eq!(lines[3].as_str(), Some("d"));
+    }
+
+    #[test]
+    fn test_empty_lines_and_eof() {
+        assert!(read_all(b"").is_empty());
+        let lines = read_all(b"\n\n");
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|l| l.bytes().is_empty()));
+    }
+
+    #[test]
+    fn test_invalid_utf8_round_trip() {
+        let input = b"ok \xff\xfe bytes\r\nnext\n";
+        let mut out = Vec::new();
+        for line in read_all(input) {
+            let text = line.to_str_lossy().into_owned();
+            line.write_with(&mut out, &text).unwrap();
+        }
+        assert_eq!(out, input);
+    }
+
+    #[test]
+    fn test_processed_invalid_utf8_written_as_text() {
+        let line = RawLine::new(b"a\xffb".to_vec(), LineEnding::Lf);
+        let mut out = Vec::new();
+        line.write_with(&mut out, "+0.000s a\u{FFFD}b").unwrap();
+        assert_eq!(out, "+0.000s a\u{FFFD}b\n".as_bytes());
+    }
+
+    #[test]
+    fn test_split_crlf() {
+        // A one-byte buffer forces `\r` and `\n` into separate reads
+        let input: &[u8] = b"line\r\nnext\r";
+        let reader = io::BufReader::with_capacity(1, input);
+        let lines: Vec<_> = LineReader::new(reader).collect::<io::Result<_>>().unwrap();
+        let endings: Vec<_> = lines.iter().map(RawLine::ending).collect();
+        assert_eq!(endings, [LineEnding::CrLf, LineEnding::Cr]);
+        assert_eq!(lines[0].as_str(), Some("line"));
+        assert_eq!(lines[1].as_str(), Some("next"));
+    }
+
+    #[test]
+    fn test_has_buffered() {
+        let input: &[u8] = b"one\ntwo\n";
+        let mut reader = LineReader::new(input);
+        let mut line = RawLine::default();
+        reader.read_line(&mut line).unwrap();
+        assert!(reader.has_buffered());
+        reader.read_line(&mut line).unwrap();
+        assert!(!reader.has_buffered());
+    }
+}
@@ -13,15 +13,24 @@
 
 mod commands;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
 use is_terminal::IsTerminal;
 use phos::alert::AlertManagerBuilder;
+use phos::dedup::DedupMode;
+use phos::delta::{self, DeltaTracker};
+use phos::filter::LineFilter;
+use phos::program::rank::DEFAULT_DETECT_LINES;
+use phos::program::ComposedProgram;
 use phos::programs;
-use phos::{Colorizer, Config, GlobalConfig, StatsCollector, StatsExportFormat, Theme};
+use phos::{
+    AnsiMode, ColorDepth, Colorizer, Config, GlobalConfig, LogLevel, MultilineRules,
+    StatsCollector, StatsExportFormat, Theme, TimeFormat,
+};
 use std::io;
 use std::sync::Arc;
+use std::time::Duration;
 
 /// Version string with git hash
 fn version_string() -> &'static str {
@@ -53,7 +62,7 @@ fn version_string() -> &'static str {
                   CI/CD:      github-actions, jenkins"
 )]
 pub struct Cli {
-    /// Program to use for colorization (e.g., docker, kubectl, cargo)
+    /// Program to use for colorization (e.g., docker, kubectl, cargo; docker,postgres layers both)
     #[arg(short, long, value_name = "PROGRAM")]
     program: Option<String>,
 
@@ -73,6 +82,89 @@ pub struct Cli {
     #[arg(long)]
     color: bool,
 
+    /// Colors the terminal supports (default: detected from COLORTERM, TERM and terminfo)
+    #[arg(long, value_enum, value_name = "DEPTH")]
+    color_depth: Option<ColorDepth>,
+
+    /// Handling of colors already in the input (default: program setting, else merge)
+    #[arg(long, value_enum, value_name = "MODE")]
+    ansi: Option<AnsiMode>,
+
+    /// Group stack traces and indented lines into multi-line records (default: program setting)
+    #[arg(long, conflicts_with = "no_multiline")]
+    multiline: bool,
+
+    /// Process input line by line even if the program groups multi-line records
+    #[arg(long)]
+    no_multiline: bool,
+
+    /// Render JSON and logfmt lines as `time LEVEL message key=value` (default: program setting)
+    #[arg(long)]
+    structured: bool,
+
+    /// Field holding the log level of structured lines (implies --structured)
+    #[arg(long, value_name = "FIELD")]
+    level_field: Option<String>,
+
+    /// Field holding the message of structured lines (implies --structured)
+    #[arg(long, value_name = "FIELD")]
+    message_field: Option<String>,
+
+    /// Highlight matches of REGEX on top of the program's rules (repeatable)
+    #[arg(short = 'H', long = "highlight", value_name = "REGEX[:COLOR]")]
+    highlights: Vec<String>,
+
+    /// Hide lines below LEVEL, using the program's level names (still counted in stats)
+    #[arg(long, value_enum, value_name = "LEVEL")]
+    level: Option<LogLevel>,
+
+    /// Rewrite timestamps in one format (parses ISO, syslog, geth, log4j, epoch ms)
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    time_format: Option<TimeFormat>,
+
+    /// Prefix each line with the time since the previous one (log timestamps, else arrival)
+    #[arg(long)]
+    delta: bool,
+
+    /// Color deltas of SECS or more as warnings [default: 10]
+    #[arg(long, value_name = "SECS", requires = "delta")]
+    delta_warn: Option<f64>,
+
+    /// Color deltas of SECS or more as errors [default: 60]
+    #[arg(long, value_name = "SECS", requires = "delta")]
+    delta_error: Option<f64>,
+
+    /// Collapse runs of repeated lines into one plus an `(xN repeated)` count
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "similar"
+    )]
+    dedup: Option<DedupMode>,
+
+    /// Only show lines matching REGEX (still counted in stats when hidden)
+    #[arg(long, value_name = "REGEX")]
+    grep: Option<String>,
+
+    /// Show lines that do not match --grep instead
+    #[arg(short = 'v', long, requires = "grep")]
+    invert: bool,
+
+    /// Show N lines after each --grep match
+    #[arg(short = 'A', long, value_name = "N", requires = "grep")]
+    after_context: Option<usize>,
+
+    /// Show N lines before each --grep match
+    #[arg(short = 'B', long, value_name = "N", requires = "grep")]
+    before_context: Option<usize>,
+
+    /// Show N lines before and after each --grep match
+    #[arg(short = 'C', long, value_name = "N", requires = "grep")]
+    context: Option<usize>,
+
     /// Show log statistics after processing
     #[arg(long)]
     stats: bool,
@@ -89,6 +181,16 @@ pub struct Cli {
     #[arg(long, value_name = "SECONDS", default_value = "0")]
     stats_interval: u64,
 
+    /// Read input from a file instead of stdin (repeatable)
+    #[arg(long = "file", value_name = "PATH")]
+    files: Vec<String>,
+
+    /// Colorize files or stdin on N threads, keeping line order (0 = all cores).
+    /// Input is read in batches of 2048 lines per thread, so piped output lags
+    /// behind its input; not for following live logs
+    #[arg(short = 'j', long, value_name = "N")]
+    jobs: Option<usize>,
+
     /// Webhook URL for alerts (auto-detects Discord/Telegram)
     #[arg(long, value_name = "URL")]
     alert: Option<String>,
@@ -176,6 +278,20 @@ enum Commands {
         format: OutputFormat,
     },
 
+    /// Rank programs by how well they fit a log sample
+    #[command(name = "detect")]
+    Detect {
+        /// Log file to sample (reads stdin if not specified)
+        file: Option<String>,
+        /// Number of lines to sample (phos itself detects programs from the
+        /// command it runs, not from input lines, so it has no such option)
+        #[arg(long, short = 'n', value_name = "N", default_value_t = DEFAULT_DETECT_LINES)]
+        detect_lines: usize,
+        /// Output format
+        #[arg(long, short = 'f', value_enum, default_value = "table")]
+        format: OutputFormat,
+    },
+
     /// Show brand colors for Ethereum clients
     #[command(name = "colors")]
     Colors,
@@ -286,10 +402,15 @@ fn main() -> Result<()> {
             }
             Commands::Themes | Commands::ListThemes => commands::list_themes(),
             Commands::Info { name, format } => commands::show_info(&registry, &name, format),
+            Commands::Detect {
+                file,
+                detect_lines,
+                format,
+            } => commands::detect_program(&registry, file.as_deref(), detect_lines, format),
             Commands::Colors => commands::show_colors(),
             Commands::Completions { shell } => commands::generate_completions(shell),
             Commands::ShellInit { shell } => commands::generate_shell_init(&registry, &shell),
-            Commands::Config { action } => commands::handle_config_action(action),
+            Commands::Config { action } => commands::handle_config_action(&registry, action),
             Commands::Man { output } => commands::generate_man_page(output),
             Commands::Preview {
                 theme,
@@ -309,38 +430,119 @@ fn main() -> Result<()> {
         .unwrap_or(&cli.theme);
     let theme = Theme::get(theme_name).unwrap_or_else(Theme::default_dark);
 
-    // Get rules - check program first, then config, then auto-detect
-    let rules = if let Some(program_name) = cli.program.as_ref().or(cli.client.as_ref()) {
-        // Look up program in registry
-        if let Some(program) = registry.get(program_name) {
-            program.rules()
-        } else {
-            anyhow::bail!(
-                "Unknown program: {program_name}. Run 'phos list' to see available programs."
-            );
-        }
-    } else if let Some(ref config_path) = cli.config {
-        // Load from config file
-        Arc::from(Config::load(config_path)?.to_rules()?)
-    } else if !cli.args.is_empty() {
+    // Find the program - check program first, then config, then auto-detect
+    let program = if let Some(program_names) = cli.program.as_ref().or(cli.client.as_ref()) {
+        // Look up each program in registry, layering them in order
+        let programs = program_names
+            .split(',')
+            .map(str::trim)
+            .map(|name| {
+                anyhow::ensure!(
+                    !name.is_empty(),
+                    "Empty program name in '{program_names}'. Separate program names with single commas."
+                );
+                registry.get(name).with_context(|| {
+                    format!("Unknown program: {name}. Run 'phos list' to see available programs.")
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Some(ComposedProgram::compose(programs))
+    } else if cli.config.is_none() && !cli.args.is_empty() {
         // Try to auto-detect from command
-        let cmd_str = cli.args.join(" ");
-        if let Some(program) = registry.detect(&cmd_str) {
-            program.rules()
-        } else {
-            // No program detected, use empty rules
-            Arc::from([])
-        }
+        registry.detect(&cli.args.join(" "))
     } else {
-        Arc::from([])
+        None
     };
 
+    let rules = match (&program, &cli.config) {
+        (Some(program), _) => program.rules(),
+        // Load from config file
+        (None, Some(config_path)) => Arc::from(Config::load(config_path)?.to_rules()?),
+        // No program detected, use empty rules
+        (None, None) => Arc::from([]),
+    };
+
+    // Ad-hoc highlights go ahead of the program's rules
+    let highlights =
+        phos::highlight::highlight_rules(&cli.highlights).context("Invalid --highlight pattern")?;
+    let rules = phos::highlight::prepend(rules, &highlights);
+    let program_ansi = program.as_ref().and_then(|p| p.ansi_mode());
+    let program_multiline = program.as_ref().and_then(|p| p.multiline());
+    let program_structured = program.as_ref().and_then(|p| p.structured());
+
     // Enable colors if: --color flag set OR global config color OR stdout is a TTY
     let color_enabled = cli.color || global_config.color || io::stdout().is_terminal();
 
+    // Colors already in the input: CLI > program setting > merge
+    let ansi_mode = cli.ansi.or(program_ansi).unwrap_or_default();
+
+    // Palette size: CLI > terminal detection
+    let color_depth = cli.color_depth.unwrap_or_else(ColorDepth::detect);
+
+    // Multi-line records: CLI flags > program setting
+    let multiline = match (cli.multiline, cli.no_multiline) {
+        (_, true) => None,
+        (true, false) => program_multiline.or_else(|| Some(MultilineRules::default())),
+        (false, false) => program_multiline,
+    };
+
+    // Structured rendering: CLI flags > program setting; field flags imply --structured
+    let structured = (cli.structured || cli.level_field.is_some() || cli.message_field.is_some())
+        .then(|| program_structured.clone().unwrap_or_default())
+        .or(program_structured)
+        .map(|names| match &cli.level_field {
+            Some(field) => names.with_level_field(field),
+            None => names,
+        })
+        .map(|names| match &cli.message_field {
+            Some(field) => names.with_message_field(field),
+            None => names,
+        });
+
+    // Output filter: -A/-B override -C, as in grep
+    let filter = cli
+        .grep
+        .as_deref()
+        .map(LineFilter::new)
+        .transpose()
+        .context("Invalid --grep pattern")?
+        .map(|filter| {
+            let before = cli.before_context.or(cli.context).unwrap_or(0);
+            let after = cli.after_context.or(cli.context).unwrap_or(0);
+            filter.with_invert(cli.invert).with_context(before, after)
+        });
+
+    // Time deltas, with thresholds in seconds
+    let delta = if cli.delta {
+        let threshold = |secs: Option<f64>, default: Duration, flag: &str| {
+            secs.map_or(Ok(default), Duration::try_from_secs_f64)
+                .with_context(|| format!("Invalid {flag}"))
+        };
+        Some(DeltaTracker::new(
+            threshold(cli.delta_warn, delta::DEFAULT_WARN, "--delta-warn")?,
+            threshold(cli.delta_error, delta::DEFAULT_ERROR, "--delta-error")?,
+        ))
+    } else {
+        None
+    };
+
     let mut colorizer = Colorizer::new(rules)
+        .with_color_depth(color_depth)
         .with_theme(theme)
-        .with_color_enabled(color_enabled);
+        .with_ansi_mode(ansi_mode)
+        .with_color_enabled(color_enabled)
+        .with_multiline(multiline)
+        .with_structured(structured)
+        .with_filter(filter)
+        .with_min_level(cli.level.or(global_config.level))
+        .with_time_format(cli.time_format.or(global_config.time_format))
+        .with_delta(delta)
+        .with_dedup(cli.dedup)
+        .with_max_line_length(
+            global_config
+                .max_line_length
+                .unwrap_or(phos::colorizer::DEFAULT_MAX_LINE_LENGTH),
+        );
 
     // Merge stats settings: CLI > global config > default
     // --stats-export and --stats-interval > 0 imply --stats
@@ -447,6 +649,41 @@ fn main() -> Result<()> {
             stats.as_mut(),
             alert_manager.as_mut(),
         )?;
+    } else if !cli.files.is_empty() {
+        // Read files in order; block state is reset between files
+        let mut stdout = io::stdout().lock();
+        for path in &cli.files {
+            let file =
+                std::fs::File::open(path).with_context(|| format!("Failed to open {path}"))?;
+            let reader = io::BufReader::new(file);
+            match cli.jobs {
+                Some(threads) => colorizer.process_parallel(
+                    reader,
+                    &mut stdout,
+                    stats.as_mut(),
+                    alert_manager.as_mut(),
+                    threads,
+                    stats_interval,
+                )?,
+                None => colorizer.process_reader(
+                    reader,
+                    &mut stdout,
+                    stats.as_mut(),
+                    alert_manager.as_mut(),
+                    stats_interval,
+                )?,
+            }
+        }
+    } else if let (true, Some(threads)) = (is_pipe, cli.jobs) {
+        // Buffered stdin on a worker pool
+        colorizer.process_parallel(
+            io::stdin().lock(),
+            io::stdout().lock(),
+            stats.as_mut(),
+            alert_manager.as_mut(),
+            threads,
+            stats_interval,
+        )?;
     } else if is_pipe {
         // Read from stdin
         match (&mut stats, &mut alert_manager, stats_interval > 0) {
@@ -471,6 +708,7 @@ fn main() -> Result<()> {
         eprintln!("Usage: phos -p <program> -- <command>");
         eprintln!("       phos -c <client> -- <command>  (for Ethereum clients)");
         eprintln!("       echo 'log line' | phos -p <program>");
+        eprintln!("       phos -p <program> --file <path>");
         eprintln!();
         eprintln!("Run 'phos list' to see available programs.");
         std::process::exit(1);
@@ -10,12 +10,16 @@ use std::sync::Arc;
 use serde::Deserialize;
 use thiserror::Error;
 
+use crate::ansi::AnsiMode;
 use crate::category::{Category, ParseCategoryError};
-use crate::colors::{Color, ColorSpec};
+use crate::colors::Color;
 use crate::config::RuleConfig;
+use crate::record::MultilineRules;
 use crate::rule::Rule;
+use crate::structured::FieldNames;
 
-use super::{Program, ProgramInfo};
+use super::detect::DetectPattern;
+use super::{ComposedProgram, Program, ProgramInfo, ProgramRegistry};
 
 /// Configuration loading errors.
 #[derive(Debug, Error)]
@@ -38,6 +42,18 @@ pub enum ConfigError {
     #[error("Invalid category: {0}")]
     InvalidCategory(#[from] ParseCategoryError),
 
+    #[error("Unknown program in extends: {0}")]
+    UnknownProgram(String),
+
+    #[error("Program in extends failed to load: {0}")]
+    InvalidBase(String),
+
+    #[error("Programs extend each other in a cycle: {0}")]
+    ExtendsCycle(String),
+
+    #[error("No inherited rule to disable has the pattern: {0}")]
+    UnknownRule(String),
+
     #[error("{file}: {message}")]
     FileError {
         file: String,
@@ -158,7 +174,7 @@ pub struct ProgramConfig {
     #[serde(default = "default_category")]
     pub category: String,
 
-    /// Patterns for auto-detection
+    /// Patterns for auto-detection (words, `re:REGEX` or `argv:WORDS`)
     #[serde(default)]
     pub detect: Vec<String>,
 
@@ -166,15 +182,130 @@ pub struct ProgramConfig {
     #[serde(default)]
     pub semantic_colors: HashMap<String, String>,
 
+    /// Handling of colors already in the program's output (merge, strip, raw)
+    #[serde(default)]
+    pub ansi: Option<AnsiMode>,
+
+    /// Multi-line record grouping (stack traces, wrapped messages)
+    #[serde(default)]
+    pub multiline: Option<MultilineConfig>,
+
+    /// Compact rendering of JSON and logfmt lines
+    #[serde(default)]
+    pub structured: Option<StructuredConfig>,
+
     /// Colorization rules
     #[serde(default)]
     pub rules: Vec<RuleConfig>,
+
+    /// Registered programs whose rules and settings this one builds on
+    #[serde(default)]
+    pub extends: Vec<String>,
+
+    /// Patterns of inherited rules to leave out
+    #[serde(default)]
+    pub disable_rules: Vec<String>,
+
+    /// Rules placed ahead of the inherited ones
+    #[serde(default)]
+    pub prepend: Vec<RuleConfig>,
+
+    /// Rules placed after the inherited ones and `rules`
+    #[serde(default)]
+    pub append: Vec<RuleConfig>,
 }
 
 fn default_category() -> String {
     "custom".to_string()
 }
 
+/// Multi-line record settings of a user-defined program.
+///
+/// An empty section (`multiline: {}`) groups indented lines and common
+/// stack trace formats into the record before them.
+#[derive(Debug, Default, Deserialize)]
+pub struct MultilineConfig {
+    /// Pattern for the first line of a record; all other lines continue it
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// Extra patterns for lines that continue a record
+    #[serde(default)]
+    pub continuation: Vec<String>,
+
+    /// Whether indented lines continue a record (default true)
+    #[serde(default)]
+    pub indent: Option<bool>,
+
+    /// Whether the built-in stack trace patterns apply (default true)
+    #[serde(default)]
+    pub stack_traces: Option<bool>,
+
+    /// Maximum lines per record
+    #[serde(default)]
+    pub max_lines: Option<usize>,
+}
+
+/// Structured line settings of a user-defined program.
+///
+/// Names given here are preferred over the defaults (`time`, `level`, `msg`, ...).
+#[derive(Debug, Default, Deserialize)]
+pub struct StructuredConfig {
+    /// Field holding the timestamp
+    #[serde(default)]
+    pub timestamp: Option<String>,
+
+    /// Field holding the log level
+    #[serde(default)]
+    pub level: Option<String>,
+
+    /// Field holding the message
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+impl StructuredConfig {
+    /// Build the field names to render with.
+    #[must_use]
+    pub fn to_field_names(&self) -> FieldNames {
+        let mut names = FieldNames::default();
+        if let Some(name) = &self.timestamp {
+            names = names.with_timestamp_field(name);
+        }
+        if let Some(name) = &self.level {
+            names = names.with_level_field(name);
+        }
+        if let Some(name) = &self.message {
+            names = names.with_message_field(name);
+        }
+        names
+    }
+}
+
+impl MultilineConfig {
+    /// Compile into record grouping rules.
+    pub fn to_rules(&self) -> Result<MultilineRules, regex::Error> {
+        let mut rules = if self.stack_traces.unwrap_or(true) {
+            MultilineRules::default()
+        } else {
+            MultilineRules::indentation()
+        };
+        if let Some(start) = &self.start {
+            rules = rules.with_start(start)?;
+        }
+        for pattern in &self.continuation {
+            rules = rules.with_continuation(pattern)?;
+        }
+        if let Some(indent) = self.indent {
+            rules = rules.with_indent(indent);
+        }
+        if let Some(max_lines) = self.max_lines {
+            rules = rules.with_max_lines(max_lines);
+        }
+        Ok(rules)
+    }
+}
+
 impl ProgramConfig {
     /// Load configuration from a file path.
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
@@ -203,63 +334,92 @@ impl ProgramConfig {
     }
 
     /// Convert to a Program implementation.
+    ///
+    /// Programs named in `extends` are not available; use
+    /// [`to_program_in`](Self::to_program_in) to build on registered programs.
     pub fn to_program(self) -> Result<Arc<dyn Program>, ConfigError> {
+        self.to_program_in(&ProgramRegistry::new())
+    }
+
+    /// Convert to a Program implementation, looking up `extends` in `registry`.
+    ///
+    /// Rules run in the order `prepend`, inherited rules (minus `disable_rules`),
+    /// `rules`, then `append`. Domain colors, ANSI handling, multi-line and
+    /// structured settings are inherited unless set here.
+    pub fn to_program_in(
+        self,
+        registry: &ProgramRegistry,
+    ) -> Result<Arc<dyn Program>, ConfigError> {
         let category: Category = self.category.parse()?;
         let info = ProgramInfo::new(&self.program_id(), &self.name, &self.description, category);
 
-        // Parse domain colors
-        let domain_colors: HashMap<String, Color> = self
-            .semantic_colors
+        let base = if self.extends.is_empty() {
+            None
+        } else {
+            let programs = self
+                .extends
+                .iter()
+                .map(|name| {
+                    registry
+                        .get(name)
+                        .ok_or_else(|| ConfigError::UnknownProgram(name.clone()))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Some(ComposedProgram::compose(programs))
+        };
+
+        // Parse domain colors (inherited ones can be overridden)
+        let mut domain_colors = base
+            .as_ref()
+            .map(|base| base.domain_colors())
+            .unwrap_or_default();
+        domain_colors.extend(self.semantic_colors.iter().map(|(name, value)| {
+            let color = if value.starts_with('#') {
+                Color::hex(value)
+            } else if value.eq_ignore_ascii_case("hashed") {
+                Color::Hashed
+            } else {
+                Color::named(value)
+            };
+            (name.clone(), color)
+        }));
+
+        let inherited = base.as_ref().map(|base| base.rules()).unwrap_or_default();
+        if let Some(pattern) = self
+            .disable_rules
+            .iter()
+            .find(|&pattern| !inherited.iter().any(|rule| rule.regex.as_str() == pattern))
+        {
+            return Err(ConfigError::UnknownRule(pattern.clone()));
+        }
+        let inherited = inherited
             .iter()
-            .map(|(name, value)| {
-                let color = if value.starts_with('#') {
-                    Color::hex(value)
-                } else {
-                    Color::named(value)
-                };
-                (name.clone(), color)
-            })
+            .filter(|rule| !self.disable_rules.iter().any(|p| p == rule.regex.as_str()))
+            .cloned();
+
+        let to_rules = |configs: &[RuleConfig]| {
+            configs
+                .iter()
+                .map(|rule_config| rule_config.to_rule(&domain_colors))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let rules: Vec<Rule> = to_rules(&self.prepend)?
+            .into_iter()
+            .chain(inherited)
+            .chain(to_rules(&self.rules)?)
+            .chain(to_rules(&self.append)?)
             .collect();
 
-        // Parse rules using functional fold pattern
-        let rules: Vec<Rule> = self
-            .rules
-            .iter()
-            .map(|rule_config| {
-                // Build colors using fold
-                let builder = rule_config.colors.iter().fold(
-                    Rule::new(&rule_config.regex)?,
-                    |builder, color_name| match ColorSpec::from_name(color_name) {
-                        ColorSpec::Semantic(s) => builder.semantic(s),
-                        ColorSpec::Domain(ref name) => match domain_colors.get(name) {
-                            Some(c) => builder.color(c.clone()),
-                            None => builder.named(name),
-                        },
-                        ColorSpec::Named(ref name) => builder.named(name),
-                        ColorSpec::Hex(ref hex) => builder.hex(hex),
-                    },
-                );
-
-                // Apply modifiers conditionally using match for ownership
-                let builder =
-                    match rule_config.bold || rule_config.colors.contains(&"bold".to_string()) {
-                        true => builder.bold(),
-                        false => builder,
-                    };
-
-                let builder = match rule_config.skip {
-                    true => builder.skip(),
-                    false => builder,
-                };
-
-                let builder = match &rule_config.replace {
-                    Some(r) => builder.replace(r),
-                    None => builder,
-                };
-
-                Ok(builder.build())
-            })
-            .collect::<Result<Vec<_>, ConfigError>>()?;
+        let multiline = self
+            .multiline
+            .as_ref()
+            .map(MultilineConfig::to_rules)
+            .transpose()?
+            .or_else(|| base.as_ref().and_then(|base| base.multiline()));
+
+        for pattern in &self.detect {
+            DetectPattern::parse(pattern)?;
+        }
 
         // Leak detect patterns once at construction time (programs are typically loaded once)
         let detect_patterns: Vec<&'static str> = self
@@ -273,6 +433,15 @@ impl ProgramConfig {
             rules: rules.into(),
             domain_colors,
             detect_patterns,
+            ansi_mode: self
+                .ansi
+                .or_else(|| base.as_ref().and_then(|base| base.ansi_mode())),
+            multiline,
+            structured: self
+                .structured
+                .as_ref()
+                .map(StructuredConfig::to_field_names)
+                .or_else(|| base.as_ref().and_then(|base| base.structured())),
         }))
     }
 }
@@ -283,6 +452,9 @@ struct ConfigProgram {
     rules: Arc<[Rule]>,
     domain_colors: HashMap<String, Color>,
     detect_patterns: Vec<&'static str>,
+    ansi_mode: Option<AnsiMode>,
+    multiline: Option<MultilineRules>,
+    structured: Option<FieldNames>,
 }
 
 impl Program for ConfigProgram {
@@ -301,6 +473,18 @@ impl Program for ConfigProgram {
     fn detect_patterns(&self) -> &[&str] {
         &self.detect_patterns
     }
+
+    fn ansi_mode(&self) -> Option<AnsiMode> {
+        self.ansi_mode
+    }
+
+    fn multiline(&self) -> Option<MultilineRules> {
+        self.multiline.clone()
+    }
+
+    fn structured(&self) -> Option<FieldNames> {
+        self.structured.clone()
+    }
 }
 
 #[cfg(test)]
@@ -343,4 +527,98 @@ category: custom
         let config: ProgramConfig = serde_yaml::from_str(yaml).unwrap();
         assert_eq!(config.program_id(), "custom.my_app");
     }
+
+    #[test]
+    fn test_ansi_mode() {
+        let config: ProgramConfig = serde_yaml::from_str("name: pytest\nansi: strip\n").unwrap();
+        let program = config.to_program().unwrap();
+        assert_eq!(program.ansi_mode(), Some(AnsiMode::Strip));
+    }
+
+    #[test]
+    fn test_multiline_config() {
+        let yaml = r"
+name: myapp
+multiline:
+  start: '^\d{4}-\d{2}-\d{2}'
+";
+        let config: ProgramConfig = serde_yaml::from_str(yaml).unwrap();
+        let rules = config.to_program().unwrap().multiline().unwrap();
+        assert!(rules.is_continuation("  at Foo.bar"));
+        assert!(rules.is_continuation("wrapped text"));
+        assert!(!rules.is_continuation("2024-01-15 next record"));
+
+        let config: ProgramConfig = serde_yaml::from_str("name: plain\n").unwrap();
+        assert!(config.to_program().unwrap().multiline().is_none());
+    }
+
+    #[test]
+    fn test_structured_config() {
+        let yaml = "name: otel\nstructured:\n  level: severity_text\n  message: body\n";
+        let config: ProgramConfig = serde_yaml::from_str(yaml).unwrap();
+        let names = config.to_program().unwrap().structured().unwrap();
+        assert_eq!(names.level[0], "severity_text");
+        assert_eq!(names.message[0], "body");
+        assert_eq!(names.timestamp, FieldNames::default().timestamp);
+    }
+
+    #[test]
+    fn test_extends() {
+        let base = crate::program::SimpleProgram::new(
+            "devops.base",
+            "Base",
+            "Base program",
+            Category::DevOps,
+            ["inherited", r"\d+"]
+                .iter()
+                .map(|p| Rule::new(p).unwrap().build())
+                .collect(),
+        )
+        .with_multiline(MultilineRules::default());
+        let mut registry = ProgramRegistry::new();
+        registry.register(Arc::new(base));
+
+        let yaml = r"
+name: service
+extends: [devops.base]
+disable_rules: ['\d+']
+prepend:
+  - regex: first
+rules:
+  - regex: own
+append:
+  - regex: last
+";
+        let config: ProgramConfig = serde_yaml::from_str(yaml).unwrap();
+        let program = config.to_program_in(&registry).unwrap();
+        let rules = program.rules();
+        let patterns: Vec<&str> = rules.iter().map(|r| r.regex.as_str()).collect();
+        assert_eq!(patterns, ["first", "inherited", "own", "last"]);
+        assert!(program.multiline().is_some());
+
+        let config: ProgramConfig = serde_yaml::from_str(yaml).unwrap();
+        let err = config.to_program().err().unwrap();
+        assert!(matches!(err, ConfigError::UnknownProgram(name) if name == "devops.base"));
+
+        let yaml = "name: typo\nextends: [base]\ndisable_rules: ['\\d']\n";
+        let config: ProgramConfig = serde_yaml::from_str(yaml).unwrap();
+        let err = config.to_program_in(&registry).err().unwrap();
+        assert!(matches!(err, ConfigError::UnknownRule(pattern) if pattern == r"\d"));
+    }
+
+    #[test]
+    fn test_group_colors_skip_bold_modifier() {
+        let yaml = r#"
+name: kv
+rules:
+  - regex: '(\w+)=(\S+)'
+    colors: [bold, default, key, value]
+"#;
+        let config: ProgramConfig = serde_yaml::from_str(yaml).unwrap();
+        let program = config.to_program().unwrap();
+        let rules = program.rules();
+        assert!(rules[0].bold);
+        assert_eq!(rules[0].colors.len(), 3);
+        assert!(rules[0].has_group_colors());
+    }
 }
@@ -0,0 +1,138 @@
+//! Command-line detection patterns.
+//!
+//! Programs list patterns that pick them for the command being wrapped:
+//!
+//! - `word`: the word anywhere in the command
+//! - `re:REGEX`: a regex searched for in the command
+//! - `argv:docker logs *`: words matched in order against the arguments, the
+//!   first against the program name; `*` matches any word, and other
+//!   arguments (flags and their values) may come in between
+//!
+//! Matching ignores case. When several programs match, the one with the most
+//! specific pattern wins (see [`DetectPattern::specificity`]).
+//!
+//! # Examples
+//!
+//! ```rust
+//! use phos::program::detect::DetectPattern;
+//!
+//! let pattern = DetectPattern::parse("argv:docker logs myapp").unwrap();
+//! assert!(pattern.is_match("docker logs -f --tail 100 myapp"));
+//! assert!(!pattern.is_match("docker ps myapp"));
+//! assert!(!pattern.is_match("sudo docker logs myapp"));
+//!
+//! let pattern = DetectPattern::parse(r"re:docker.*myapp-\d+").unwrap();
+//! assert!(pattern.is_match("docker logs myapp-2"));
+//! ```
+
+use regex::Regex;
+
+/// Prefix of regex patterns.
+const REGEX_PREFIX: &str = "re:";
+
+/// Prefix of argument patterns.
+const ARGV_PREFIX: &str = "argv:";
+
+/// Specificity each argument pattern word adds for its position constraint.
+const ARGV_WORD_BONUS: usize = 2;
+
+/// A compiled detection pattern.
+#[derive(Debug, Clone)]
+pub enum DetectPattern {
+    /// A word anywhere in the command
+    Word { word: String, regex: Regex },
+    /// A regex searched for in the command
+    Regex(Regex),
+    /// Words matched in order against the arguments (`*` = any word)
+    Argv(Vec<String>),
+}
+
+impl DetectPattern {
+    /// Compile a pattern, recognizing the `re:` and `argv:` prefixes.
+    pub fn parse(pattern: &str) -> Result<Self, regex::Error> {
+        if let Some(source) = pattern.strip_prefix(REGEX_PREFIX) {
+            return Ok(Self::Regex(Regex::new(&format!("(?i){source}"))?));
+        }
+        if let Some(words) = pattern.strip_prefix(ARGV_PREFIX) {
+            return Ok(Self::Argv(
+                words.split_whitespace().map(str::to_lowercase).collect(),
+            ));
+        }
+        Ok(Self::Word {
+            word: pattern.to_string(),
+            regex: Regex::new(&format!(r"(?i)\b{}\b", regex::escape(pattern)))?,
+        })
+    }
+
+    /// Whether the pattern matches the command line `cmd`.
+    #[must_use]
+    pub fn is_match(&self, cmd: &str) -> bool {
+        match self {
+            Self::Word { regex, .. } | Self::Regex(regex) => regex.is_match(cmd),
+            Self::Argv(words) => {
+                let mut args = cmd.split_whitespace().map(str::to_lowercase);
+                let (Some((first, rest)), Some(program)) = (words.split_first(), args.next())
+                else {
+                    return false;
+                };
+                let name = program.rsplit('/').next().unwrap_or(&program);
+                let matches = |word: &str, arg: &str| word == "*" || word == arg;
+                matches(first, name) && rest.iter().all(|word| args.any(|arg| matches(word, &arg)))
+            }
+        }
+    }
+
+    /// How specific the pattern is; the highest-scoring match wins.
+    ///
+    /// Literal characters count one each (for regexes, their letters and
+    /// digits), and each argument pattern word adds a bonus for its position.
+    #[must_use]
+    pub fn specificity(&self) -> usize {
+        match self {
+            Self::Word { word, .. } => word.len(),
+            Self::Regex(regex) => {
+                let source = regex.as_str().trim_start_matches("(?i)");
+                source.chars().filter(char::is_ascii_alphanumeric).count()
+            }
+            Self::Argv(words) => words
+                .iter()
+                .map(|word| ARGV_WORD_BONUS + if word == "*" { 0 } else { word.len() })
+                .sum(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_pattern() {
+        let pattern = DetectPattern::parse("git").unwrap();
+        assert!(pattern.is_match("GIT log"));
+        assert!(!pattern.is_match("digit"));
+        assert_eq!(pattern.specificity(), 3);
+    }
+
+    #[test]
+    fn test_argv_pattern() {
+        let pattern = DetectPattern::parse("argv:cargo").unwrap();
+        assert!(pattern.is_match("/usr/bin/cargo build"));
+        assert!(!pattern.is_match("rustup run stable cargo build"));
+
+        let pattern = DetectPattern::parse("argv:docker logs *").unwrap();
+        assert!(pattern.is_match("docker logs -f app"));
+        assert!(!pattern.is_match("docker logs"));
+        assert_eq!(pattern.specificity(), 16);
+    }
+
+    #[test]
+    fn test_regex_pattern() {
+        let pattern = DetectPattern::parse(r"re:^kubectl\s+logs").unwrap();
+        assert!(pattern.is_match("kubectl logs pod"));
+        assert!(!pattern.is_match("echo kubectl logs"));
+        let word = DetectPattern::parse("kubectl").unwrap();
+        assert!(pattern.specificity() > word.specificity());
+        assert!(DetectPattern::parse("re:(").is_err());
+    }
+}
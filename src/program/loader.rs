@@ -1,11 +1,13 @@
 //! Configuration directory discovery and loading.
 
+use std::collections::{HashMap, VecDeque};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use super::config::{ConfigError, ProgramConfig};
 use super::{Program, ProgramRegistry};
+use crate::programs;
 
 /// Result of loading a user program - includes file path for error reporting.
 #[derive(Debug)]
@@ -73,6 +75,8 @@ pub fn global_config_path() -> Option<PathBuf> {
 }
 
 /// Load all user-defined programs from the config directory.
+///
+/// Programs extending other user programs are built after them.
 /// Returns a list of load errors with file paths for detailed error reporting.
 pub fn load_user_programs(registry: &mut ProgramRegistry) -> Vec<LoadResult> {
     let programs_path = match programs_dir() {
@@ -80,8 +84,8 @@ pub fn load_user_programs(registry: &mut ProgramRegistry) -> Vec<LoadResult> {
         _ => return Vec::new(),
     };
 
-    let entries = match fs::read_dir(&programs_path) {
-        Ok(entries) => entries,
+    let paths = match program_files(&programs_path) {
+        Ok(paths) => paths,
         Err(e) => {
             return vec![LoadResult {
                 path: programs_path,
@@ -90,57 +94,141 @@ pub fn load_user_programs(registry: &mut ProgramRegistry) -> Vec<LoadResult> {
         }
     };
 
-    entries
-        .flatten()
-        .map(|entry| entry.path())
-        .filter(|path| {
-            path.extension()
-                .and_then(|e| e.to_str())
-                .is_some_and(|ext| matches!(ext.to_lowercase().as_str(), "yaml" | "yml" | "json"))
-        })
-        .filter_map(|path| match load_program_from_file(&path) {
-            Ok(program) => {
-                registry.register(program);
+    let mut errors = Vec::new();
+    let configs = paths
+        .into_iter()
+        .filter_map(|path| match ProgramConfig::load(&path) {
+            Ok(config) => Some((path, config)),
+            Err(error) => {
+                errors.push(LoadResult { path, error });
                 None
             }
-            Err(error) => Some(LoadResult { path, error }),
         })
-        .collect()
+        .collect();
+    errors.extend(register_configs(configs, registry));
+    errors
+}
+
+/// Build and register `configs`, each after the user programs it extends.
+fn register_configs(
+    configs: Vec<(PathBuf, ProgramConfig)>,
+    registry: &mut ProgramRegistry,
+) -> Vec<LoadResult> {
+    let ids: Vec<String> = configs.iter().map(|(_, c)| c.program_id()).collect();
+    let index: HashMap<&str, usize> = ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| (id.as_str(), i))
+        .collect();
+
+    // User programs each config extends; extending its own id builds on the built-in
+    let bases: Vec<Vec<usize>> = configs
+        .iter()
+        .enumerate()
+        .map(|(i, (_, config))| {
+            config
+                .extends
+                .iter()
+                .filter_map(|name| index.get(name.as_str()).copied())
+                .filter(|&base| base != i)
+                .collect()
+        })
+        .collect();
+    let mut dependents = vec![Vec::new(); configs.len()];
+    for (i, list) in bases.iter().enumerate() {
+        for &base in list {
+            dependents[base].push(i);
+        }
+    }
+
+    let mut waiting: Vec<usize> = bases.iter().map(Vec::len).collect();
+    let mut ready: VecDeque<usize> = (0..configs.len()).filter(|&i| waiting[i] == 0).collect();
+    let mut failed = vec![false; configs.len()];
+    let mut configs: Vec<_> = configs.into_iter().map(Some).collect();
+    let mut errors = Vec::new();
+
+    while let Some(i) = ready.pop_front() {
+        let Some((path, config)) = configs[i].take() else {
+            continue;
+        };
+        let result = match bases[i].iter().find(|&&base| failed[base]) {
+            Some(&base) => Err(ConfigError::InvalidBase(ids[base].clone())),
+            None => config.to_program_in(registry),
+        };
+        match result {
+            Ok(program) => registry.register(program),
+            Err(error) => {
+                failed[i] = true;
+                errors.push(LoadResult { path, error });
+            }
+        }
+        for &dependent in &dependents[i] {
+            waiting[dependent] -= 1;
+            if waiting[dependent] == 0 {
+                ready.push_back(dependent);
+            }
+        }
+    }
+
+    // Whatever is left extends itself through other user programs
+    let mut cycle: Vec<&str> = configs
+        .iter()
+        .zip(&ids)
+        .filter(|(config, _)| config.is_some())
+        .map(|(_, id)| id.as_str())
+        .collect();
+    cycle.sort_unstable();
+    let cycle = cycle.join(", ");
+    errors.extend(configs.into_iter().flatten().map(|(path, _)| LoadResult {
+        path,
+        error: ConfigError::ExtendsCycle(cycle.clone()),
+    }));
+    errors
 }
 
 /// Load a single program from a configuration file.
+///
+/// Programs named in `extends` are looked up among the built-in programs.
 pub fn load_program_from_file(path: &Path) -> Result<Arc<dyn Program>, ConfigError> {
+    load_program_from_file_with_registry(path, &programs::default_registry())
+}
+
+/// Load a single program from a configuration file, building on programs in `registry`.
+pub fn load_program_from_file_with_registry(
+    path: &Path,
+    registry: &ProgramRegistry,
+) -> Result<Arc<dyn Program>, ConfigError> {
     let config = ProgramConfig::load(path)?;
-    config.to_program()
+    config.to_program_in(registry)
 }
 
 /// Validate a program configuration file without loading it into the registry.
+///
+/// Programs named in `extends` are looked up among the built-in programs.
 /// Returns Ok with the program info if valid, Err with detailed error if not.
 pub fn validate_program_file(path: &Path) -> Result<String, LoadResult> {
-    match ProgramConfig::load(path) {
-        Ok(config) => {
-            // Try to convert to program to catch regex errors etc.
-            match config.to_program() {
-                Ok(program) => {
-                    let info = program.info();
-                    Ok(format!(
-                        "{} ({}) - {} rules",
-                        info.name,
-                        info.id,
-                        program.rules().len()
-                    ))
-                }
-                Err(error) => Err(LoadResult {
-                    path: path.to_path_buf(),
-                    error,
-                }),
-            }
-        }
-        Err(error) => Err(LoadResult {
+    validate_program_file_with_registry(path, &programs::default_registry())
+}
+
+/// Validate a program configuration file, looking up `extends` in `registry`.
+pub fn validate_program_file_with_registry(
+    path: &Path,
+    registry: &ProgramRegistry,
+) -> Result<String, LoadResult> {
+    load_program_from_file_with_registry(path, registry)
+        .map(|program| {
+            let info = program.info();
+            format!(
+                "{} ({}) - {} rules",
+                info.name,
+                info.id,
+                program.rules().len()
+            )
+        })
+        .map_err(|error| LoadResult {
             path: path.to_path_buf(),
             error,
-        }),
-    }
+        })
 }
 
 /// List all config files in the programs directory.
@@ -151,20 +239,20 @@ pub fn list_program_files() -> Vec<PathBuf> {
         _ => return Vec::new(),
     };
 
-    match fs::read_dir(&programs_path) {
-        Ok(entries) => entries
-            .flatten()
-            .map(|entry| entry.path())
-            .filter(|path| {
-                path.extension()
-                    .and_then(|e| e.to_str())
-                    .is_some_and(|ext| {
-                        matches!(ext.to_lowercase().as_str(), "yaml" | "yml" | "json")
-                    })
-            })
-            .collect(),
-        Err(_) => Vec::new(),
-    }
+    program_files(&programs_path).unwrap_or_default()
+}
+
+/// Config files in `dir`, by extension.
+fn program_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    Ok(fs::read_dir(dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|ext| matches!(ext.to_lowercase().as_str(), "yaml" | "yml" | "json"))
+        })
+        .collect())
 }
 
 /// Ensure the config directory structure exists.
@@ -190,4 +278,64 @@ mod tests {
         let dir = programs_dir().expect("programs_dir should return Some on supported platforms");
         assert!(dir.to_string_lossy().contains("programs"));
     }
+
+    fn configs(files: &[(&str, &str)]) -> Vec<(PathBuf, ProgramConfig)> {
+        files
+            .iter()
+            .map(|(file, yaml)| (PathBuf::from(file), serde_yaml::from_str(yaml).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_register_configs_in_dependency_order() {
+        let mut registry = ProgramRegistry::new();
+        let errors = register_configs(
+            configs(&[
+                (
+                    "child.yaml",
+                    "name: child\nextends: [custom.base]\nrules:\n  - regex: own\n",
+                ),
+                ("base.yaml", "name: base\nrules:\n  - regex: inherited\n"),
+            ]),
+            &mut registry,
+        );
+        assert!(errors.is_empty());
+        let child = registry.get("custom.child").unwrap();
+        let rules = child.rules();
+        let patterns: Vec<&str> = rules.iter().map(|r| r.regex.as_str()).collect();
+        assert_eq!(patterns, ["inherited", "own"]);
+    }
+
+    #[test]
+    fn test_register_configs_reports_bad_bases() {
+        let mut registry = ProgramRegistry::new();
+        let mut errors = register_configs(
+            configs(&[
+                ("a.yaml", "name: a\nextends: [custom.b]\n"),
+                ("b.yaml", "name: b\nextends: [custom.a]\n"),
+                ("missing.yaml", "name: missing\nextends: [nope]\n"),
+                ("broken.yaml", "name: broken\nrules:\n  - regex: '('\n"),
+                ("child.yaml", "name: child\nextends: [custom.broken]\n"),
+            ]),
+            &mut registry,
+        );
+        errors.sort_by(|a, b| a.path.cmp(&b.path));
+        let messages: Vec<String> = errors.iter().map(LoadResult::format).collect();
+        assert_eq!(messages.len(), 5);
+        assert_eq!(
+            messages[0],
+            "a.yaml: Programs extend each other in a cycle: custom.a, custom.b"
+        );
+        assert!(messages[1].starts_with("b.yaml: Programs extend each other in a cycle"));
+        assert!(messages[2].starts_with("broken.yaml: invalid regex"));
+        assert_eq!(
+            messages[3],
+            "child.yaml: Program in extends failed to load: custom.broken"
+        );
+        assert_eq!(
+            messages[4],
+            "missing.yaml: Unknown program in extends: nope"
+        );
+        assert!(registry.list().is_empty());
+    }
 }
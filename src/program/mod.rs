@@ -7,6 +7,7 @@
 //!
 //! - [`Program`]: Trait for defining colorization programs
 //! - [`SimpleProgram`]: Convenience implementation for most use cases
+//! - [`ComposedProgram`]: Several programs layered into one
 //! - [`ProgramRegistry`]: Registry for managing and discovering programs
 //! - [`ProgramInfo`]: Metadata about a program (name, category, etc.)
 //!
@@ -66,16 +67,22 @@
 
 use std::borrow::Cow;
 use std::collections::HashMap;
-use std::sync::Arc;
-
-use regex::Regex;
+use std::sync::{Arc, OnceLock};
 
+use crate::ansi::AnsiMode;
 use crate::category::Category;
 use crate::colors::Color;
+use crate::record::MultilineRules;
 use crate::rule::Rule;
+use crate::structured::FieldNames;
+
+use detect::DetectPattern;
+pub use rank::{Candidate, Ranker};
 
 pub mod config;
+pub mod detect;
 pub mod loader;
+pub mod rank;
 
 /// Information about a program.
 ///
@@ -140,6 +147,9 @@ impl ProgramInfo {
 ///
 /// - [`domain_colors`](Self::domain_colors): Domain-specific colors
 /// - [`detect_patterns`](Self::detect_patterns): Patterns for auto-detection
+/// - [`ansi_mode`](Self::ansi_mode): Handling of colors already in the program's output
+/// - [`multiline`](Self::multiline): Grouping of stack traces into multi-line records
+/// - [`structured`](Self::structured): Field names for rendering JSON and logfmt lines
 pub trait Program: Send + Sync {
     /// Get program information.
     fn info(&self) -> &ProgramInfo;
@@ -155,11 +165,30 @@ pub trait Program: Send + Sync {
     }
 
     /// Get patterns for auto-detecting this program from command lines.
-    /// Each pattern is matched against the command being colorized.
+    /// Each pattern is matched against the command being colorized; see
+    /// [`detect`] for the `re:` and `argv:` forms.
     /// Returns a borrowed slice to avoid allocations on each call.
     fn detect_patterns(&self) -> &[&str] {
         &[]
     }
+
+    /// How escape sequences already in this program's output should be handled.
+    /// None uses the colorizer default ([`AnsiMode::Merge`]).
+    fn ansi_mode(&self) -> Option<AnsiMode> {
+        None
+    }
+
+    /// How lines are grouped into multi-line records (stack traces, wrapped messages).
+    /// None processes the output line by line.
+    fn multiline(&self) -> Option<MultilineRules> {
+        None
+    }
+
+    /// Field names used to render JSON and logfmt lines compactly.
+    /// None colorizes structured lines as plain text.
+    fn structured(&self) -> Option<FieldNames> {
+        None
+    }
 }
 
 /// A simple program implementation that can be constructed from data.
@@ -192,6 +221,8 @@ pub struct SimpleProgram {
     rules: Arc<[Rule]>,
     detect_patterns: Vec<&'static str>,
     domain_colors: HashMap<String, Color>,
+    ansi_mode: Option<AnsiMode>,
+    multiline: Option<MultilineRules>,
 }
 
 impl SimpleProgram {
@@ -211,6 +242,8 @@ impl SimpleProgram {
             rules: rules.into(),
             detect_patterns: Vec::new(),
             domain_colors: HashMap::new(),
+            ansi_mode: None,
+            multiline: None,
         }
     }
 
@@ -227,6 +260,20 @@ impl SimpleProgram {
         self.domain_colors = colors;
         self
     }
+
+    /// Builder: set how escape sequences already in the output are handled.
+    #[must_use]
+    pub fn with_ansi_mode(mut self, mode: AnsiMode) -> Self {
+        self.ansi_mode = Some(mode);
+        self
+    }
+
+    /// Builder: group lines into multi-line records.
+    #[must_use]
+    pub fn with_multiline(mut self, rules: MultilineRules) -> Self {
+        self.multiline = Some(rules);
+        self
+    }
 }
 
 impl Program for SimpleProgram {
@@ -245,6 +292,110 @@ impl Program for SimpleProgram {
     fn domain_colors(&self) -> HashMap<String, Color> {
         self.domain_colors.clone()
     }
+
+    fn ansi_mode(&self) -> Option<AnsiMode> {
+        self.ansi_mode
+    }
+
+    fn multiline(&self) -> Option<MultilineRules> {
+        self.multiline.clone()
+    }
+}
+
+/// Several programs layered into one.
+///
+/// Rules come from each program in turn, so earlier programs win ties between
+/// overlapping matches. Domain colors are merged (earlier programs win), and
+/// the ANSI, multi-line and structured settings come from the first program
+/// that has one.
+///
+/// # Examples
+///
+/// ```rust
+/// use phos::programs;
+/// use phos::program::ComposedProgram;
+///
+/// let registry = programs::default_registry();
+/// let docker = registry.get("docker").unwrap();
+/// let postgres = registry.get("postgres").unwrap();
+/// let rules = docker.rules().len() + postgres.rules().len();
+///
+/// let program = ComposedProgram::compose(vec![docker, postgres]);
+/// assert_eq!(program.info().id, "devops.docker,data.postgres");
+/// assert_eq!(program.rules().len(), rules);
+/// ```
+pub struct ComposedProgram {
+    info: ProgramInfo,
+    programs: Vec<Arc<dyn Program>>,
+    rules: Arc<[Rule]>,
+}
+
+impl ComposedProgram {
+    /// Layer `programs` in order; a single program is returned as it is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `programs` is empty.
+    #[must_use]
+    pub fn compose(mut programs: Vec<Arc<dyn Program>>) -> Arc<dyn Program> {
+        if programs.len() == 1 {
+            return programs.remove(0);
+        }
+        let join = |field: fn(&ProgramInfo) -> &str, separator: &str| {
+            programs
+                .iter()
+                .map(|program| field(program.info()))
+                .collect::<Vec<_>>()
+                .join(separator)
+        };
+        let info = ProgramInfo::new(
+            &join(|info| info.id.as_ref(), ","),
+            &join(|info| info.name.as_ref(), " + "),
+            &join(|info| info.description.as_ref(), "; "),
+            programs[0].info().category,
+        );
+        let rules = programs
+            .iter()
+            .flat_map(|program| program.rules().to_vec())
+            .collect();
+        Arc::new(Self {
+            info,
+            programs,
+            rules,
+        })
+    }
+}
+
+impl Program for ComposedProgram {
+    fn info(&self) -> &ProgramInfo {
+        &self.info
+    }
+
+    fn rules(&self) -> Arc<[Rule]> {
+        Arc::clone(&self.rules)
+    }
+
+    fn domain_colors(&self) -> HashMap<String, Color> {
+        let mut colors = HashMap::new();
+        for program in self.programs.iter().rev() {
+            colors.extend(program.domain_colors());
+        }
+        colors
+    }
+
+    fn ansi_mode(&self) -> Option<AnsiMode> {
+        self.programs.iter().find_map(|program| program.ansi_mode())
+    }
+
+    fn multiline(&self) -> Option<MultilineRules> {
+        self.programs.iter().find_map(|program| program.multiline())
+    }
+
+    fn structured(&self) -> Option<FieldNames> {
+        self.programs
+            .iter()
+            .find_map(|program| program.structured())
+    }
 }
 
 /// Registry for managing programs.
@@ -270,9 +421,11 @@ impl Program for SimpleProgram {
 /// ```
 pub struct ProgramRegistry {
     programs: HashMap<String, Arc<dyn Program>>,
-    /// Cached compiled regexes for detection patterns.
-    /// Maps pattern string to compiled Regex.
-    detection_cache: HashMap<String, Regex>,
+    /// Cached compiled detection patterns.
+    /// Maps pattern string to compiled pattern.
+    detection_cache: HashMap<String, DetectPattern>,
+    /// Rule weights for content detection, computed on first use.
+    ranker: OnceLock<Ranker>,
 }
 
 impl Default for ProgramRegistry {
@@ -288,23 +441,25 @@ impl ProgramRegistry {
         Self {
             programs: HashMap::new(),
             detection_cache: HashMap::new(),
+            ranker: OnceLock::new(),
         }
     }
 
     /// Register a program and cache its detection patterns.
     pub fn register(&mut self, program: Arc<dyn Program>) {
-        // Cache detection regexes for this program
+        // Cache detection patterns for this program
         for pattern in program.detect_patterns() {
             if !self.detection_cache.contains_key(*pattern) {
-                let regex_pattern = format!(r"(?i)\b{}\b", regex::escape(pattern));
-                if let Ok(re) = Regex::new(&regex_pattern) {
-                    self.detection_cache.insert((*pattern).to_string(), re);
+                if let Ok(compiled) = DetectPattern::parse(pattern) {
+                    self.detection_cache
+                        .insert((*pattern).to_string(), compiled);
                 }
             }
         }
 
         let id = program.info().id.to_string();
         self.programs.insert(id, program);
+        self.ranker = OnceLock::new();
     }
 
     /// Get a program by ID.
@@ -324,26 +479,26 @@ impl ProgramRegistry {
 
     /// Detect a program from a command string.
     ///
-    /// Uses word-boundary matching to avoid false positives where a pattern
-    /// might match a substring (e.g., "git" matching "digit").
-    /// Returns the most specific match (longest pattern wins).
+    /// Plain patterns use word-boundary matching to avoid false positives where
+    /// a pattern might match a substring (e.g., "git" matching "digit").
+    /// Returns the program with the most specific matching pattern
+    /// (see [`DetectPattern::specificity`]).
     #[must_use]
     pub fn detect(&self, cmd: &str) -> Option<Arc<dyn Program>> {
-        let cmd_lower = cmd.to_lowercase();
-
-        // Find matching programs with pattern length for specificity ranking
         self.programs
             .values()
             .filter_map(|program| {
-                // Find first matching pattern for this program
-                program.detect_patterns().iter().find_map(|pattern| {
-                    self.detection_cache
-                        .get(*pattern)
-                        .filter(|re| re.is_match(&cmd_lower))
-                        .map(|_| (pattern.len(), Arc::clone(program)))
-                })
+                // Score each program by its most specific matching pattern
+                program
+                    .detect_patterns()
+                    .iter()
+                    .filter_map(|pattern| self.detection_cache.get(*pattern))
+                    .filter(|pattern| pattern.is_match(cmd))
+                    .map(DetectPattern::specificity)
+                    .max()
+                    .map(|score| (score, Arc::clone(program)))
             })
-            .max_by_key(|(len, _)| *len)
+            .max_by_key(|(score, _)| *score)
             .map(|(_, program)| program)
     }
 
@@ -391,31 +546,31 @@ impl ProgramRegistry {
 
     /// Detect a program from log content.
     ///
-    /// Tries each program's rules against the sample lines and returns
-    /// the program with the most rule matches. This is useful for auto-detecting
-    /// the program when reading from stdin.
+    /// Returns the best candidate of [`rank_from_lines`](Self::rank_from_lines)
+    /// if its confidence reaches [`rank::MIN_CONFIDENCE`]. This is useful for
+    /// auto-detecting the program when reading from stdin.
     #[must_use]
     pub fn detect_from_lines(&self, lines: &[&str]) -> Option<Arc<dyn Program>> {
-        if lines.is_empty() {
-            return None;
-        }
-
-        self.programs
-            .values()
-            .filter(|p| !p.rules().is_empty())
-            .filter_map(|program| {
-                let rules = program.rules();
-                let match_count = lines
-                    .iter()
-                    .filter(|line| rules.iter().any(|rule| rule.is_match(line)))
-                    .count();
+        self.rank_from_lines(lines)
+            .into_iter()
+            .next()
+            .filter(|candidate| candidate.confidence >= rank::MIN_CONFIDENCE)
+            .map(|candidate| candidate.program)
+    }
 
-                // Only consider if we have meaningful matches (at least 20% of lines)
-                (match_count > 0 && match_count * 5 >= lines.len())
-                    .then(|| (match_count, Arc::clone(program)))
+    /// Rank programs by how well their rules fit sample log lines, best first.
+    ///
+    /// Distinctive rules count for more than ones many programs share; see
+    /// [`rank`] for the scoring. Rule weights are computed on first use and
+    /// kept until another program is registered.
+    #[must_use]
+    pub fn rank_from_lines(&self, lines: &[&str]) -> Vec<Candidate> {
+        self.ranker
+            .get_or_init(|| {
+                let programs: Vec<Arc<dyn Program>> = self.programs.values().cloned().collect();
+                Ranker::new(&programs)
             })
-            .max_by_key(|(count, _)| *count)
-            .map(|(_, program)| program)
+            .rank(lines)
     }
 }
 
@@ -485,6 +640,47 @@ mod tests {
         assert!(registry.detect("other command").is_none());
     }
 
+    #[test]
+    fn test_composed_program() {
+        let rule = |pattern: &str| Rule::new(pattern).unwrap().build();
+        let colors = |hex: &str| HashMap::from([("peer".to_string(), Color::hex(hex))]);
+        let first = SimpleProgram::new("dev.a", "A", "First", Category::Dev, vec![rule("a")])
+            .with_domain_colors(colors("#111111"));
+        let second = SimpleProgram::new("dev.b", "B", "Second", Category::Dev, vec![rule("b")])
+            .with_domain_colors(colors("#222222"))
+            .with_ansi_mode(AnsiMode::Strip);
+
+        let program = ComposedProgram::compose(vec![Arc::new(first), Arc::new(second)]);
+        assert_eq!(program.info().id, "dev.a,dev.b");
+        assert_eq!(program.info().name, "A + B");
+        let rules = program.rules();
+        let patterns: Vec<&str> = rules.iter().map(|r| r.regex.as_str()).collect();
+        assert_eq!(patterns, ["a", "b"]);
+        assert_eq!(program.domain_colors()["peer"], Color::hex("#111111"));
+        assert_eq!(program.ansi_mode(), Some(AnsiMode::Strip));
+    }
+
+    #[test]
+    fn test_registry_detect_specificity() {
+        let mut registry = ProgramRegistry::new();
+        registry.register(Arc::new(
+            TestProgram::new("devops.docker", "Docker", Category::DevOps)
+                .with_detect(vec!["docker"]),
+        ));
+        registry.register(Arc::new(
+            TestProgram::new("custom.myapp", "MyApp", Category::Dev)
+                .with_detect(vec!["argv:docker logs myapp", r"re:^myapp-\w+"]),
+        ));
+
+        let id = |cmd: &str| registry.detect(cmd).map(|p| p.info().id.to_string());
+        assert_eq!(id("docker logs -f myapp").as_deref(), Some("custom.myapp"));
+        assert_eq!(id("docker ps myapp").as_deref(), Some("devops.docker"));
+        assert_eq!(
+            id("myapp-server --port 80").as_deref(),
+            Some("custom.myapp")
+        );
+    }
+
     #[test]
     fn test_registry_list_by_category() {
         let mut registry = ProgramRegistry::new();
@@ -501,4 +697,19 @@ mod tests {
         assert_eq!(registry.list_by_category(Category::Dev).len(), 2);
         assert_eq!(registry.list_by_category(Category::System).len(), 1);
     }
+
+    #[test]
+    fn test_rank_from_lines_after_register() {
+        let program = |id: &'static str, pattern: &str| -> Arc<dyn Program> {
+            let rules = vec![Rule::new(pattern).unwrap().build()];
+            Arc::new(SimpleProgram::new(id, id, "Test", Category::Dev, rules))
+        };
+        let mut registry = ProgramRegistry::new();
+        registry.register(program("dev.a", "alpha"));
+        assert_eq!(registry.rank_from_lines(&["alpha beta"]).len(), 1);
+
+        // Registering invalidates the cached weights
+        registry.register(program("dev.b", "beta"));
+        assert_eq!(registry.rank_from_lines(&["alpha beta"]).len(), 2);
+    }
 }
@@ -0,0 +1,250 @@
+//! Ranking programs against sample log lines.
+//!
+//! Each line earns a program the weight of its most distinctive matching
+//! rule. Rules that also match any of a few generic log lines (plain levels,
+//! ISO timestamps, numbers, paths, addresses) weigh nothing, and the others
+//! split their weight among the programs sharing the pattern. So a client's
+//! own level tokens (`ERRO`, `WRN`) and timestamp format count, while the
+//! `ERROR` and `\d+` dozens of programs color alike do not. A candidate's
+//! confidence is its score discounted by how close the runner-up comes.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use phos::programs;
+//!
+//! let registry = programs::default_registry();
+//! let lines = [
+//!     "INFO [12-05|00:12:36.000] Imported new potential chain segment number=21,345,678",
+//!     "WARN [12-05|00:12:37.000] Snapshot extension registration failed peer=1a2b3c4d",
+//! ];
+//! let candidates = registry.rank_from_lines(&lines);
+//! assert_eq!(candidates[0].program.info().id, "ethereum.geth");
+//! assert!(candidates[0].confidence > candidates[1].confidence);
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::Program;
+use crate::rule::Rule;
+
+/// Default number of lines sampled for detection.
+pub const DEFAULT_DETECT_LINES: usize = 10;
+
+/// Confidence below which no program is detected.
+pub const MIN_CONFIDENCE: f64 = 0.3;
+
+/// Lines any log could contain; rules matching them say nothing about the program.
+const GENERIC_LINES: &[&str] = &[
+    "2024-12-05T00:12:36.123Z INFO Server started on 0.0.0.0:8080 in 12ms",
+    "2024-12-05 00:12:36,123 WARNING [main] Retrying request id=42 after 1.5s",
+    "Dec  5 00:12:36 host app[1234]: ERROR open /var/lib/app/data.db: permission denied",
+    "[DEBUG] GET /api/v1/users 200 OK (3 items, 4.2 KB, 97%)",
+    "level=warn msg=\"connecting\" addr=192.168.1.10:30303 uuid=550e8400-e29b-41d4-a716-446655440000",
+    r#"{"level":"error","time":1733357556,"msg":"request failed","hash":"0xdeadbeef"}"#,
+    "Error: request timed out; [WARN] retrying; [INFO] TRACE: true false null v1.2.3",
+    "2024-12-05 00:12:36.123 INFO | user | service: api | status: running",
+];
+
+/// A program ranked against sample lines.
+#[derive(Clone)]
+pub struct Candidate {
+    pub program: Arc<dyn Program>,
+    /// Fraction of lines matched by any rule
+    pub coverage: f64,
+    /// Mean weight of each line's most distinctive matching rule (0 to 1)
+    pub score: f64,
+    /// Score discounted by the runner-up's (0 to 1)
+    pub confidence: f64,
+}
+
+/// Rank `programs` by how well their rules fit `lines`, best first.
+///
+/// Programs matching no line are left out. Use a [`Ranker`] to rank the same
+/// programs against several samples.
+#[must_use]
+pub fn rank(programs: &[Arc<dyn Program>], lines: &[&str]) -> Vec<Candidate> {
+    if lines.is_empty() {
+        return Vec::new();
+    }
+    Ranker::new(programs).rank(lines)
+}
+
+/// Programs with their rule weights, computed once for repeated ranking.
+pub struct Ranker {
+    programs: Vec<Weighed>,
+}
+
+/// A program's rules with their weights.
+struct Weighed {
+    program: Arc<dyn Program>,
+    rules: Arc<[Rule]>,
+    weights: Vec<f64>,
+}
+
+impl Ranker {
+    /// Weigh the rules of `programs`.
+    #[must_use]
+    pub fn new(programs: &[Arc<dyn Program>]) -> Self {
+        let rule_sets: Vec<Arc<[Rule]>> = programs.iter().map(|p| p.rules()).collect();
+
+        // Number of programs sharing each pattern
+        let mut shared: HashMap<&str, usize> = HashMap::new();
+        for rules in &rule_sets {
+            let mut patterns: Vec<&str> = rules.iter().map(|rule| rule.regex.as_str()).collect();
+            patterns.sort_unstable();
+            patterns.dedup();
+            for pattern in patterns {
+                *shared.entry(pattern).or_default() += 1;
+            }
+        }
+
+        let weights: Vec<Vec<f64>> = rule_sets
+            .iter()
+            .map(|rules| {
+                rules
+                    .iter()
+                    .map(|rule| weight(rule, shared[rule.regex.as_str()]))
+                    .collect()
+            })
+            .collect();
+        let programs = programs
+            .iter()
+            .cloned()
+            .zip(rule_sets)
+            .zip(weights)
+            .map(|((program, rules), weights)| Weighed {
+                program,
+                rules,
+                weights,
+            })
+            .collect();
+        Self { programs }
+    }
+
+    /// Rank the programs by how well their rules fit `lines`, best first.
+    ///
+    /// Programs matching no line are left out.
+    #[must_use]
+    pub fn rank(&self, lines: &[&str]) -> Vec<Candidate> {
+        if lines.is_empty() {
+            return Vec::new();
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        let total = lines.len() as f64;
+        let mut candidates: Vec<Candidate> = self
+            .programs
+            .iter()
+            .filter_map(
+                |Weighed {
+                     program,
+                     rules,
+                     weights,
+                 }| {
+                    let (matched, sum) = lines
+                        .iter()
+                        .filter_map(|line| {
+                            rules
+                                .iter()
+                                .zip(weights)
+                                .filter(|(rule, _)| rule.is_match(line))
+                                .map(|(_, weight)| *weight)
+                                .reduce(f64::max)
+                        })
+                        .fold((0usize, 0.0), |(matched, sum), weight| {
+                            (matched + 1, sum + weight)
+                        });
+                    #[allow(clippy::cast_precision_loss)]
+                    (matched > 0).then(|| Candidate {
+                        program: Arc::clone(program),
+                        coverage: matched as f64 / total,
+                        score: sum / total,
+                        confidence: 0.0,
+                    })
+                },
+            )
+            .collect();
+
+        candidates.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.program.info().id.cmp(&b.program.info().id))
+        });
+        let (first, second) = match candidates.as_slice() {
+            [first, second, ..] => (first.score, second.score),
+            [first] => (first.score, 0.0),
+            [] => return candidates,
+        };
+        for candidate in candidates.iter_mut().filter(|c| c.score > 0.0) {
+            // The top candidate is measured against the runner-up, the rest against the top
+            let rival = if candidate.score < first {
+                first
+            } else {
+                second
+            };
+            candidate.confidence = candidate.score * candidate.score / (candidate.score + rival);
+        }
+        candidates
+    }
+}
+
+/// Weight of a rule whose pattern `shared` programs use.
+#[allow(clippy::cast_precision_loss)]
+fn weight(rule: &Rule, shared: usize) -> f64 {
+    if GENERIC_LINES.iter().any(|line| rule.is_match(line)) {
+        0.0
+    } else {
+        1.0 / shared as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::program::SimpleProgram;
+    use crate::Category;
+
+    fn program(id: &'static str, rules: &[&str]) -> Arc<dyn Program> {
+        let rules = rules
+            .iter()
+            .map(|pattern| Rule::new(pattern).unwrap().build())
+            .collect();
+        Arc::new(SimpleProgram::new(id, id, "Test", Category::Dev, rules))
+    }
+
+    #[test]
+    fn test_distinctive_rules_win() {
+        // All match every line, but `\d+` is generic and `WRN` is shared
+        let programs = [
+            program("dev.a", &[r"\bWRN\b", r"\d+"]),
+            program("dev.b", &[r"\bWRN\b", r"\bslot=\d+"]),
+            program("dev.c", &[r"\d+"]),
+        ];
+        let lines = ["WRN slot=12 late", "INF slot=13 ok", "WRN 14 peers"];
+        let candidates = rank(&programs, &lines);
+        let ids: Vec<&str> = candidates
+            .iter()
+            .map(|c| c.program.info().id.as_ref())
+            .collect();
+        assert_eq!(ids, ["dev.b", "dev.a", "dev.c"]);
+        assert!((candidates[0].score - 2.5 / 3.0).abs() < 1e-9);
+        assert!((candidates[2].coverage - 1.0).abs() < f64::EPSILON);
+        assert!(candidates[2].score.abs() < f64::EPSILON);
+        assert!(candidates[0].confidence >= MIN_CONFIDENCE);
+        assert!(candidates[1].confidence < MIN_CONFIDENCE);
+    }
+
+    #[test]
+    fn test_generic_rules_weigh_less() {
+        let programs = [program("dev.a", &[r"\d+"]), program("dev.b", &[r"slot"])];
+        let candidates = rank(&programs, &["slot 12"]);
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].program.info().id, "dev.b");
+        assert!(candidates[1].score.abs() < f64::EPSILON);
+        assert!(candidates[1].confidence.abs() < f64::EPSILON);
+        assert!(rank(&programs, &["nothing here"]).is_empty());
+        assert!(rank(&programs, &[]).is_empty());
+    }
+}
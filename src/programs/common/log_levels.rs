@@ -181,6 +181,8 @@ pub fn database_log_level_rules() -> Vec<Rule> {
 
 /// JSON structured log levels ("level": "error", etc.).
 /// Common in Caddy, Traefik, and modern JSON-logging applications.
+/// These color the raw JSON; [`Colorizer::with_structured`](crate::Colorizer::with_structured)
+/// renders such lines in a readable layout instead.
 #[must_use]
 pub fn json_log_level_rules() -> Vec<Rule> {
     vec![
@@ -8,6 +8,7 @@ use super::common;
 use crate::category::Category;
 use crate::colors::SemanticColor;
 use crate::program::{Program, SimpleProgram};
+use crate::record::MultilineRules;
 use crate::rule::Rule;
 
 // =============================================================================
@@ -511,6 +512,7 @@ pub fn mvn_program() -> Arc<dyn Program> {
             Category::Dev,
             mvn_rules(),
         )
-        .with_detect_patterns(vec!["mvn", "maven", "mvnw"]),
+        .with_detect_patterns(vec!["mvn", "maven", "mvnw"])
+        .with_multiline(MultilineRules::default()),
     )
 }
@@ -8,6 +8,7 @@ use super::common;
 use crate::category::Category;
 use crate::colors::SemanticColor;
 use crate::program::{Program, SimpleProgram};
+use crate::record::MultilineRules;
 use crate::rule::Rule;
 
 // =============================================================================
@@ -96,7 +97,8 @@ pub fn go_program() -> Arc<dyn Program> {
             " go fmt",
             " go vet",
             " go test",
-        ]),
+        ])
+        .with_multiline(MultilineRules::default()),
     )
 }
 
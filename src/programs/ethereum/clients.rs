@@ -491,7 +491,7 @@ pub fn charon_rules() -> Result<Vec<Rule>, regex::Error> {
         Rule::new(r"partial[_\s]?sig")?
             .hex(patterns::ATTESTATION_COLOR)
             .build(),
-        Rule::new(r"peer=\w+")?.hex(patterns::PEER_ID_COLOR).build(),
+        Rule::new(r"peer=\w+")?.hashed().build(),
         Rule::new(r"node=\d+")?
             .semantic(SemanticColor::Number)
             .build(),
@@ -36,6 +36,7 @@ macro_rules! define_client {
 /// # Variants
 /// - `semantic($color)` - Use a semantic color
 /// - `hex($color)` - Use a hex color string
+/// - `hashed` - Give each matched text its own stable color
 /// - Add `bold` at the end to make the rule bold
 ///
 /// # Examples
@@ -74,6 +75,11 @@ macro_rules! define_rule {
             Ok(Rule::new($pattern)?.named($color).bold().build())
         }
     };
+    ($fn_name:ident, $pattern:literal, hashed) => {
+        pub fn $fn_name() -> Result<Rule, regex::Error> {
+            Ok(Rule::new($pattern)?.hashed().build())
+        }
+    };
 }
 
 /// Define a log levels function that returns `Result<Vec<Rule>, regex::Error>`.
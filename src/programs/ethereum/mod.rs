@@ -16,6 +16,7 @@ use clients::{ClientMeta, Layer, ALL_CLIENTS};
 use crate::category::Category;
 use crate::colors::Color;
 use crate::program::{Program, ProgramInfo, ProgramRegistry};
+use crate::record::MultilineRules;
 use crate::rule::Rule;
 
 /// Ethereum domain-specific colors.
@@ -140,6 +141,11 @@ impl Program for EthereumProgram {
     fn detect_patterns(&self) -> &[&str] {
         &self.detect_patterns
     }
+
+    /// Clients print Java exceptions, Go panics and Rust backtraces over several lines.
+    fn multiline(&self) -> Option<MultilineRules> {
+        Some(MultilineRules::default())
+    }
 }
 
 /// Register all Ethereum clients as programs.
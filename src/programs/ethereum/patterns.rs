@@ -132,6 +132,11 @@ define_rule!(
 define_rule!(slot_rule, r"\bslot[=:\s]+(\d+)", hex(SLOT_COLOR));
 define_rule!(epoch_rule, r"\bepoch[=:\s]+(\d+)", hex(EPOCH_COLOR));
 define_rule!(peers_rule, r"\bpeers?[=:\s]+(\d+)", hex(PEER_ID_COLOR));
+define_rule!(
+    peer_id_rule,
+    r"\b16Uiu2HA[1-9A-HJ-NP-Za-km-z]{45}\b",
+    hashed
+);
 define_rule!(
     syncing_rule,
     r"\b(Syncing|Synced|syncing|synced)\b",
@@ -206,6 +211,7 @@ pub fn consensus_patterns() -> Result<Vec<Rule>, regex::Error> {
         epoch_rule()?,
         address_rule()?, // Before hash_rule for specific address matching
         hash_rule()?,
+        peer_id_rule()?, // Before peers_rule, which would take the leading digits
         peers_rule()?,
         syncing_rule()?,
         success_rule()?,
@@ -405,6 +411,14 @@ mod tests {
         assert!(rule_matches(&rule, "peers: 100"));
     }
 
+    #[test]
+    fn test_peer_id_rule_matches() {
+        let rule = peer_id_rule().unwrap();
+        let peer_id = "16Uiu2HAmSs3uUQzc4rLvCMwpUUQaU9EfZLUhVnBVWk7S6JBqr4bk";
+        assert!(rule_matches(&rule, &format!("peer: {peer_id}, client")));
+        assert!(!rule_matches(&rule, "peer: 16, client"));
+    }
+
     #[test]
     fn test_syncing_rule_matches() {
         let rule = syncing_rule().unwrap();
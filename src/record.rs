@@ -0,0 +1,301 @@
+//! Multi-line record assembly.
+//!
+//! Stack traces (Java exceptions from Besu/Teku, Go panics, Python tracebacks,
+//! Rust backtraces) and wrapped messages span several lines. A
+//! [`RecordAssembler`] groups lines into [`Record`]s using [`MultilineRules`],
+//! so the [`Colorizer`](crate::Colorizer) can style, filter, count and alert on
+//! each record as a unit.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use phos::line_reader::LineReader;
+//! use phos::record::{MultilineRules, RecordAssembler};
+//!
+//! let input: &[u8] = b"ERROR boom\njava.lang.IllegalStateException: bad\n\tat Foo.bar(Foo.java:1)\nINFO ok\n";
+//! let mut assembler = RecordAssembler::new(MultilineRules::default());
+//!
+//! let mut records = Vec::new();
+//! for line in LineReader::new(input) {
+//!     records.extend(assembler.push(line.unwrap()));
+//! }
+//! records.extend(assembler.flush());
+//!
+//! assert_eq!(records.len(), 2);
+//! assert_eq!(records[0].lines().len(), 3);
+//! ```
+
+use regex::{Regex, RegexSet};
+
+use crate::colors::strip_ansi;
+use crate::line_reader::RawLine;
+
+/// Default maximum number of lines in one record.
+pub const DEFAULT_MAX_RECORD_LINES: usize = 500;
+
+/// Continuation patterns for common stack trace formats.
+const STACK_TRACE_PATTERNS: &[&str] = &[
+    // Blank lines inside a trace (Go panics)
+    r"^\s*$",
+    // Java/Kotlin frames and causes
+    r"^\s*at\s",
+    r"^\s*Caused by:",
+    r"^\s*Suppressed:",
+    r"^\s*\.\.\. \d+ (more|common frames omitted)",
+    // Exception lines following a header (Java, Python)
+    r"^[\w$.]+(Error|Exception|Throwable)(: |$)",
+    // Go panics: goroutine headers and function frames
+    r"^\[signal ",
+    r"^goroutine \d+ \[",
+    r"^created by ",
+    r"^panic\(",
+    r"^[\w/.-]+\.(\(\*?\w+\)\.)?\w+\(.*\)$",
+    r"^exit status \d+$",
+    // Python tracebacks
+    r"^Traceback \(most recent call last\):",
+    r"^During handling of the above exception",
+    r"^The above exception was the direct cause",
+    // Rust backtraces
+    r"^stack backtrace:",
+    r"^note: run with `RUST_BACKTRACE",
+];
+
+/// Rules deciding which lines continue the current record.
+///
+/// With a start pattern, every line that does not match it continues the
+/// record. Otherwise a line continues the record if it is indented or matches
+/// one of the continuation patterns.
+#[derive(Debug, Clone)]
+pub struct MultilineRules {
+    /// Pattern for the first line of a record (overrides the other checks)
+    start: Option<Regex>,
+    /// Source patterns for lines that continue a record
+    patterns: Vec<String>,
+    /// Compiled continuation patterns
+    continuation: RegexSet,
+    /// Whether indented lines continue a record
+    indent: bool,
+    /// Maximum lines per record before it is emitted
+    max_lines: usize,
+}
+
+impl Default for MultilineRules {
+    /// Indentation plus the built-in stack trace patterns.
+    fn default() -> Self {
+        let patterns: Vec<String> = STACK_TRACE_PATTERNS
+            .iter()
+            .map(|p| (*p).to_string())
+            .collect();
+        let continuation =
+            RegexSet::new(&patterns).expect("built-in continuation patterns are valid");
+        Self {
+            start: None,
+            patterns,
+            continuation,
+            indent: true,
+            max_lines: DEFAULT_MAX_RECORD_LINES,
+        }
+    }
+}
+
+impl MultilineRules {
+    /// Rules that only continue records on indentation (no built-in patterns).
+    #[must_use]
+    pub fn indentation() -> Self {
+        Self {
+            patterns: Vec::new(),
+            continuation: RegexSet::empty(),
+            ..Self::default()
+        }
+    }
+
+    /// Set a pattern matching the first line of each record.
+    pub fn with_start(mut self, pattern: &str) -> Result<Self, regex::Error> {
+        self.start = Some(Regex::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Add a pattern for lines that continue a record.
+    pub fn with_continuation(mut self, pattern: &str) -> Result<Self, regex::Error> {
+        self.patterns.push(pattern.to_string());
+        self.continuation = RegexSet::new(&self.patterns)?;
+        Ok(self)
+    }
+
+    /// Set whether indented lines continue a record.
+    #[must_use]
+    pub fn with_indent(mut self, indent: bool) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Set the maximum number of lines per record.
+    #[must_use]
+    pub fn with_max_lines(mut self, max_lines: usize) -> Self {
+        self.max_lines = max_lines.max(1);
+        self
+    }
+
+    /// Whether `line` continues the current record rather than starting a new one.
+    #[must_use]
+    pub fn is_continuation(&self, line: &str) -> bool {
+        let line = strip_ansi(line);
+        match &self.start {
+            Some(start) => !start.is_match(&line),
+            None => {
+                (self.indent && line.starts_with([' ', '\t'])) || self.continuation.is_match(&line)
+            }
+        }
+    }
+}
+
+/// A group of lines forming one log record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    lines: Vec<RawLine>,
+    continued: bool,
+}
+
+impl Record {
+    /// The record's lines, head first.
+    #[must_use]
+    pub fn lines(&self) -> &[RawLine] {
+        &self.lines
+    }
+
+    /// Whether this record continues one that was already emitted.
+    ///
+    /// Happens when input pauses mid-record or a record exceeds the line limit;
+    /// the lines then belong to the previous record.
+    #[must_use]
+    pub fn is_continued(&self) -> bool {
+        self.continued
+    }
+
+    /// The record's text, lines joined with `\n`.
+    #[must_use]
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(RawLine::to_str_lossy)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Groups lines into records.
+///
+/// Lines are buffered until a line that starts a new record arrives. Callers
+/// reading live input should [`flush`](Self::flush) when no more input is
+/// immediately available, so the last record is not held back.
+#[derive(Debug)]
+pub struct RecordAssembler {
+    rules: MultilineRules,
+    lines: Vec<RawLine>,
+    continued: bool,
+    started: bool,
+}
+
+impl RecordAssembler {
+    /// Create an assembler with the given rules.
+    #[must_use]
+    pub fn new(rules: MultilineRules) -> Self {
+        Self {
+            rules,
+            lines: Vec::new(),
+            continued: false,
+            started: false,
+        }
+    }
+
+    /// Add a line, returning the pending record if this line starts a new one.
+    pub fn push(&mut self, line: RawLine) -> Option<Record> {
+        let continues = self.rules.is_continuation(&line.to_str_lossy());
+        let complete = if !continues || self.lines.len() >= self.rules.max_lines {
+            self.flush()
+        } else {
+            None
+        };
+
+        if self.lines.is_empty() {
+            // A continuation with nothing pending extends the last emitted record
+            self.continued = continues && self.started;
+        }
+        self.started = true;
+        self.lines.push(line);
+        complete
+    }
+
+    /// Take the pending record, if any.
+    pub fn flush(&mut self) -> Option<Record> {
+        (!self.lines.is_empty()).then(|| Record {
+            lines: std::mem::take(&mut self.lines),
+            continued: self.continued,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::line_reader::{LineEnding, LineReader};
+
+    fn assemble(rules: MultilineRules, input: &str) -> Vec<Record> {
+        let mut assembler = RecordAssembler::new(rules);
+        let mut records: Vec<Record> = LineReader::new(input.as_bytes())
+            .filter_map(|line| assembler.push(line.unwrap()))
+            .collect();
+        records.extend(assembler.flush());
+        records
+    }
+
+    #[test]
+    fn test_stack_traces_grouped() {
+        let input = "\
+INFO starting
+ERROR request failed
+java.lang.IllegalStateException: boom
+\tat com.example.Foo.bar(Foo.java:42)
+Caused by: java.io.IOException: closed
+\t... 3 more
+panic: runtime error: index out of range
+
+goroutine 1 [running]:
+main.main()
+\t/app/main.go:5 +0x1d
+INFO done
+";
+        let records = assemble(MultilineRules::default(), input);
+        let sizes: Vec<usize> = records.iter().map(|r| r.lines().len()).collect();
+        assert_eq!(sizes, [1, 5, 5, 1]);
+        assert!(records[1].text().ends_with("\t... 3 more"));
+    }
+
+    #[test]
+    fn test_start_pattern() {
+        let rules = MultilineRules::default()
+            .with_start(r"^\d{4}-\d{2}-\d{2}")
+            .unwrap();
+        let input = "2024-01-15 first\nwrapped message\n2024-01-15 second\n";
+        let records = assemble(rules, input);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].text(), "2024-01-15 first\nwrapped message");
+    }
+
+    #[test]
+    fn test_continuation_after_flush_and_limit() {
+        let line = |text: &str| RawLine::new(text, LineEnding::Lf);
+        let mut assembler = RecordAssembler::new(MultilineRules::indentation().with_max_lines(2));
+        assert!(assembler.push(line("head")).is_none());
+        let record = assembler.flush().unwrap();
+        assert!(!record.is_continued());
+
+        // Input resumed mid-record
+        assert!(assembler.push(line("  frame 1")).is_none());
+        assert!(assembler.push(line("  frame 2")).is_none());
+        let full = assembler.push(line("  frame 3")).unwrap();
+        assert!(full.is_continued());
+        assert_eq!(full.lines().len(), 2);
+        assert!(assembler.flush().unwrap().is_continued());
+    }
+}
@@ -15,6 +15,13 @@
 //!     .bold()
 //!     .build();
 //!
+//! // Attribute rule: fatal lines as a reversed red bar
+//! let fatal = Rule::new(r"\bFATAL\b.*")
+//!     .unwrap()
+//!     .semantic(SemanticColor::Error)
+//!     .reverse()
+//!     .build();
+//!
 //! // Skip rule: hide DEBUG lines
 //! let skip_debug = Rule::new(r"^\[DEBUG\]")
 //!     .unwrap()
@@ -27,24 +34,58 @@
 //!     .replace("${2}/${3}/${1}")
 //!     .semantic(SemanticColor::Timestamp)
 //!     .build();
+//!
+//! // Field rule: only the value of `slot` in JSON or logfmt lines
+//! let slot = Rule::new(r"^\d+$")
+//!     .unwrap()
+//!     .field("slot")
+//!     .semantic(SemanticColor::Number)
+//!     .build();
+//!
+//! // Conditional rule: numbers count as failures only on error lines
+//! let failures = Rule::new(r"\b\d+\b")
+//!     .unwrap()
+//!     .when(r"\bERROR\b")
+//!     .unwrap()
+//!     .semantic(SemanticColor::Failure)
+//!     .build();
+//!
+//! // Named states: resources are only highlighted inside the `Plan:` section
+//! let plan = Rule::new(r"^Plan:").unwrap().enter("plan").build();
+//! let resource = Rule::new(r"^\s+[+~-] \S+")
+//!     .unwrap()
+//!     .state("plan")
+//!     .semantic(SemanticColor::Identifier)
+//!     .build();
+//!
+//! // Group rule: key and value colored separately, `=` left alone
+//! let key_value = Rule::new(r#"(\w+)=("[^"]*"|\S+)"#)
+//!     .unwrap()
+//!     .named("default")
+//!     .semantic(SemanticColor::Key)
+//!     .semantic(SemanticColor::Value)
+//!     .build();
 //! ```
 
 use regex::Regex;
+use serde::Deserialize;
 
-use crate::colors::{Color, SemanticColor};
+use crate::colors::{Attributes, Color, SemanticColor};
 
 /// How a rule should be applied when multiple matches exist.
 ///
 /// Controls whether the rule matches once, multiple times, or affects
-/// subsequent processing.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// subsequent processing. In configuration files this is the `count` key,
+/// named as in grc (`once`, `more`, `stop`, `block`, `unblock`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum CountMode {
     /// Apply once per line (first match only)
     Once,
     /// Apply to all matches
     #[default]
     More,
-    /// Stop processing after this rule matches
+    /// Apply to all matches, then skip lower-precedence rules on the line
     Stop,
     /// Start block coloring until Unblock
     Block,
@@ -61,6 +102,14 @@ pub enum CountMode {
 ///
 /// Rules are typically created using the builder pattern via [`Rule::new`].
 ///
+/// # Capture Groups
+///
+/// A rule with a single color paints the whole match. When the pattern has
+/// capture groups and more than one color is given, colors are positional
+/// (like grc): the first color applies to the whole match and the Nth extra
+/// color to the Nth capture group. Use the named color `"default"` as the
+/// first color to leave uncaptured text unstyled.
+///
 /// # Examples
 ///
 /// ```rust
@@ -77,16 +126,34 @@ pub enum CountMode {
 pub struct Rule {
     /// Compiled regex pattern
     pub regex: Regex,
-    /// Colors to apply (first is foreground)
+    /// Colors to apply (first is the whole match, the rest map to capture groups)
     pub colors: Vec<Color>,
     /// How to apply the rule
     pub count_mode: CountMode,
     /// Whether to apply bold
     pub bold: bool,
+    /// Background color (resolved through the theme like foreground colors)
+    pub background: Option<Color>,
+    /// Additional text attributes (italic, underline, dim, reverse, strikethrough)
+    pub attributes: Attributes,
     /// Skip the entire line if this rule matches
     pub skip: bool,
     /// Replacement pattern (uses $1, $2 for backreferences)
     pub replace: Option<String>,
+    /// Precedence when matches overlap (higher wins; ties go to the earlier rule)
+    pub priority: i32,
+    /// Structured field whose value the pattern is matched against (None = whole line)
+    pub field: Option<String>,
+    /// Apply only on lines matching this pattern
+    pub when: Option<Regex>,
+    /// Do not apply on lines matching this pattern
+    pub unless: Option<Regex>,
+    /// Apply only while this named state is active
+    pub state: Option<String>,
+    /// Named state entered when the rule matches
+    pub enter: Option<String>,
+    /// Named state left when the rule matches
+    pub leave: Option<String>,
 }
 
 /// Builder for creating rules with a fluent API.
@@ -112,8 +179,17 @@ pub struct RuleBuilder {
     colors: Vec<Color>,
     count_mode: CountMode,
     bold: bool,
+    background: Option<Color>,
+    attributes: Attributes,
     skip: bool,
     replace: Option<String>,
+    priority: i32,
+    field: Option<String>,
+    when: Option<Regex>,
+    unless: Option<Regex>,
+    state: Option<String>,
+    enter: Option<String>,
+    leave: Option<String>,
 }
 
 impl Rule {
@@ -127,8 +203,17 @@ impl Rule {
             colors: Vec::new(),
             count_mode: CountMode::default(),
             bold: false,
+            background: None,
+            attributes: Attributes::default(),
             skip: false,
             replace: None,
+            priority: 0,
+            field: None,
+            when: None,
+            unless: None,
+            state: None,
+            enter: None,
+            leave: None,
         })
     }
 
@@ -144,6 +229,33 @@ impl Rule {
     pub fn find_iter<'a>(&'a self, text: &'a str) -> impl Iterator<Item = regex::Match<'a>> {
         self.regex.find_iter(text)
     }
+
+    /// Whether the rule's line conditions (`when`, `unless`) allow it on `line`.
+    ///
+    /// Named state conditions are tracked by the [`Colorizer`](crate::Colorizer).
+    #[inline]
+    #[must_use]
+    pub fn applies_to(&self, line: &str) -> bool {
+        let when = self.when.as_ref().is_none_or(|re| re.is_match(line));
+        let unless = self.unless.as_ref().is_some_and(|re| re.is_match(line));
+        when && !unless
+    }
+
+    /// Whether the rule changes or depends on named states.
+    #[inline]
+    #[must_use]
+    pub fn uses_states(&self) -> bool {
+        self.state.is_some() || self.enter.is_some() || self.leave.is_some()
+    }
+
+    /// Whether colors are applied per capture group rather than to the whole match.
+    ///
+    /// True when the pattern has capture groups and more than one color is set.
+    #[inline]
+    #[must_use]
+    pub fn has_group_colors(&self) -> bool {
+        self.colors.len() > 1 && self.regex.captures_len() > 1
+    }
 }
 
 impl RuleBuilder {
@@ -168,6 +280,13 @@ impl RuleBuilder {
         self
     }
 
+    /// Pick a stable color per matched text from the theme's hashed palette.
+    #[must_use]
+    pub fn hashed(mut self) -> Self {
+        self.colors.push(Color::Hashed);
+        self
+    }
+
     /// Add a color directly.
     #[must_use]
     pub fn color(mut self, color: Color) -> Self {
@@ -182,6 +301,77 @@ impl RuleBuilder {
         self
     }
 
+    /// Set a background color.
+    #[must_use]
+    pub fn background(mut self, color: Color) -> Self {
+        self.background = Some(color);
+        self
+    }
+
+    /// Set a semantic background color.
+    #[must_use]
+    pub fn bg_semantic(self, color: SemanticColor) -> Self {
+        self.background(Color::Semantic(color))
+    }
+
+    /// Set a named background color.
+    #[must_use]
+    pub fn bg_named(self, name: &str) -> Self {
+        self.background(Color::Named(name.to_string()))
+    }
+
+    /// Set a hex background color.
+    #[must_use]
+    pub fn bg_hex(self, hex: &str) -> Self {
+        self.background(Color::Hex(hex.to_string()))
+    }
+
+    /// Add text attributes. Bold is tracked by [`Rule::bold`].
+    #[must_use]
+    pub fn attributes(mut self, attributes: Attributes) -> Self {
+        self.bold |= attributes.bold;
+        self.attributes = self.attributes.union(Attributes {
+            bold: false,
+            ..attributes
+        });
+        self
+    }
+
+    /// Set dim (faint) styling.
+    #[must_use]
+    pub fn dim(mut self) -> Self {
+        self.attributes.dim = true;
+        self
+    }
+
+    /// Set italic styling.
+    #[must_use]
+    pub fn italic(mut self) -> Self {
+        self.attributes.italic = true;
+        self
+    }
+
+    /// Set underline styling.
+    #[must_use]
+    pub fn underline(mut self) -> Self {
+        self.attributes.underline = true;
+        self
+    }
+
+    /// Set reverse video (swap foreground and background).
+    #[must_use]
+    pub fn reverse(mut self) -> Self {
+        self.attributes.reverse = true;
+        self
+    }
+
+    /// Set strikethrough styling.
+    #[must_use]
+    pub fn strikethrough(mut self) -> Self {
+        self.attributes.strikethrough = true;
+        self
+    }
+
     /// Set the count mode.
     #[must_use]
     pub fn count(mut self, mode: CountMode) -> Self {
@@ -205,6 +395,64 @@ impl RuleBuilder {
         self
     }
 
+    /// Set the priority used when matches overlap.
+    ///
+    /// Where a match nests inside another, the higher-priority rule's style
+    /// shows; partially overlapping matches of lower priority are dropped.
+    #[must_use]
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Match only the value of a field in JSON and logfmt lines.
+    ///
+    /// The pattern runs against the value alone, so `^` and `$` anchor to its
+    /// ends and keys like `parent_slot` never match a `slot` rule. Lines that
+    /// are not structured or lack the field do not match.
+    #[must_use]
+    pub fn field(mut self, name: &str) -> Self {
+        self.field = Some(name.to_string());
+        self
+    }
+
+    /// Apply the rule only on lines matching `pattern`.
+    pub fn when(mut self, pattern: &str) -> Result<Self, regex::Error> {
+        self.when = Some(Regex::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Skip the rule on lines matching `pattern`.
+    pub fn unless(mut self, pattern: &str) -> Result<Self, regex::Error> {
+        self.unless = Some(Regex::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Apply the rule only while the named state is active.
+    #[must_use]
+    pub fn state(mut self, name: &str) -> Self {
+        self.state = Some(name.to_string());
+        self
+    }
+
+    /// Enter the named state when the rule matches.
+    ///
+    /// Like [`CountMode::Block`], the state takes effect when coloring the line
+    /// that enters it, and ends with the line matching a [`leave`](Self::leave)
+    /// rule. Any number of states can be active at once.
+    #[must_use]
+    pub fn enter(mut self, name: &str) -> Self {
+        self.enter = Some(name.to_string());
+        self
+    }
+
+    /// Leave the named state when the rule matches.
+    #[must_use]
+    pub fn leave(mut self, name: &str) -> Self {
+        self.leave = Some(name.to_string());
+        self
+    }
+
     /// Build the rule.
     #[must_use]
     pub fn build(self) -> Rule {
@@ -213,8 +461,17 @@ impl RuleBuilder {
             colors: self.colors,
             count_mode: self.count_mode,
             bold: self.bold,
+            background: self.background,
+            attributes: self.attributes,
             skip: self.skip,
             replace: self.replace,
+            priority: self.priority,
+            field: self.field,
+            when: self.when,
+            unless: self.unless,
+            state: self.state,
+            enter: self.enter,
+            leave: self.leave,
         }
     }
 }
@@ -236,6 +493,21 @@ mod tests {
         assert!(rule.bold);
     }
 
+    #[test]
+    fn test_background_and_attributes() {
+        let rule = Rule::new(r"FATAL")
+            .unwrap()
+            .semantic(SemanticColor::Error)
+            .bg_named("red")
+            .italic()
+            .underline()
+            .build();
+
+        assert_eq!(rule.background, Some(Color::named("red")));
+        assert!(rule.attributes.italic && rule.attributes.underline);
+        assert!(!rule.attributes.reverse);
+    }
+
     #[test]
     fn test_count_modes() {
         let rule = Rule::new(r"\d+").unwrap().count(CountMode::Once).build();
@@ -252,6 +524,57 @@ mod tests {
         assert!(!rule.is_match("INFO: some message"));
     }
 
+    #[test]
+    fn test_group_colors() {
+        let rule = Rule::new(r"(\w+)=(\S+)")
+            .unwrap()
+            .named("default")
+            .semantic(SemanticColor::Key)
+            .semantic(SemanticColor::Value)
+            .build();
+        assert!(rule.has_group_colors());
+
+        // A single color paints the whole match even with capture groups
+        let rule = Rule::new(r"(\w+)=(\S+)")
+            .unwrap()
+            .semantic(SemanticColor::Key)
+            .build();
+        assert!(!rule.has_group_colors());
+
+        // Several colors without groups keep whole-match coloring
+        let rule = Rule::new(r"\w+=\S+")
+            .unwrap()
+            .semantic(SemanticColor::Key)
+            .semantic(SemanticColor::Value)
+            .build();
+        assert!(!rule.has_group_colors());
+    }
+
+    #[test]
+    fn test_field_rule() {
+        let rule = Rule::new(r"^\d+$").unwrap().field("slot").build();
+        assert_eq!(rule.field.as_deref(), Some("slot"));
+        assert!(Rule::new(r"\d+").unwrap().build().field.is_none());
+    }
+
+    #[test]
+    fn test_line_conditions() {
+        let rule = Rule::new(r"\d+")
+            .unwrap()
+            .when(r"\bERROR\b")
+            .unwrap()
+            .unless("retrying")
+            .unwrap()
+            .build();
+        assert!(rule.applies_to("ERROR 3 peers failed"));
+        assert!(!rule.applies_to("INFO 3 peers connected"));
+        assert!(!rule.applies_to("ERROR 3 peers failed, retrying"));
+        assert!(!rule.uses_states());
+
+        let rule = Rule::new("x").unwrap().state("plan").build();
+        assert!(rule.uses_states() && rule.applies_to("x"));
+    }
+
     #[test]
     fn test_replace_rule() {
         let rule = Rule::new(r"(\d{2}):(\d{2}):(\d{2})")
@@ -3,6 +3,7 @@
 use std::collections::HashMap;
 use std::io::{self, Write};
 
+use chrono::{DateTime, Utc};
 use regex::Regex;
 
 use super::export::{
@@ -13,6 +14,7 @@ use super::helpers::{
     truncate_message, MetricType,
 };
 use super::patterns::StatsPatterns;
+use crate::timestamp;
 
 /// Type alias for log level pattern matching with associated incrementer.
 type LevelPattern<'a> = (&'a Regex, fn(&mut LogLevelCounts));
@@ -66,6 +68,10 @@ pub struct Stats {
     pub first_timestamp: Option<String>,
     /// Last timestamp seen (if any)
     pub last_timestamp: Option<String>,
+    /// Instant of the first timestamp seen
+    pub first_time: Option<DateTime<Utc>>,
+    /// Instant of the last timestamp seen
+    pub last_time: Option<DateTime<Utc>>,
     /// Top error messages (message -> count)
     pub top_errors: HashMap<String, usize>,
     /// Maximum errors to track
@@ -95,7 +101,7 @@ impl Stats {
         }
 
         self.detect_log_level(line, patterns);
-        self.extract_timestamp(line, patterns);
+        self.extract_timestamp(line);
         self.extract_peer_count(line, patterns);
         self.extract_slot(line, patterns);
     }
@@ -153,21 +159,24 @@ impl Stats {
     }
 
     /// Extract timestamp from line.
-    fn extract_timestamp(&mut self, line: &str, patterns: &StatsPatterns) {
-        let timestamp = patterns
-            .timestamp_iso
-            .find(line)
-            .or_else(|| patterns.timestamp_syslog.find(line))
-            .map(|m| m.as_str().to_string());
-
-        if let Some(ts) = timestamp {
+    fn extract_timestamp(&mut self, line: &str) {
+        if let Some(found) = timestamp::find(line) {
+            let ts = line[found.range].to_string();
             if self.first_timestamp.is_none() {
                 self.first_timestamp = Some(ts.clone());
+                self.first_time = Some(found.time);
             }
             self.last_timestamp = Some(ts);
+            self.last_time = Some(found.time);
         }
     }
 
+    /// Time between the first and last timestamps.
+    #[must_use]
+    pub fn time_span(&self) -> Option<chrono::TimeDelta> {
+        Some(self.last_time? - self.first_time?)
+    }
+
     /// Get top errors sorted by frequency (descending).
     fn sorted_errors(&self) -> Vec<(&String, &usize)> {
         let mut errors: Vec<_> = self.top_errors.iter().collect();
@@ -207,6 +216,9 @@ impl Stats {
             print_stat!("Time range:");
             print_stat!("  First: {first}");
             print_stat!("  Last:  {last}");
+            if let Some(span) = self.time_span() {
+                print_stat!("  Span:  {}", timestamp::format_duration(span));
+            }
             print_stat!();
         }
 
@@ -256,6 +268,7 @@ impl Stats {
             time_range: TimeRangeJson {
                 first: self.first_timestamp.clone(),
                 last: self.last_timestamp.clone(),
+                duration_secs: self.time_span().map(|span| span.num_seconds()),
             },
             log_levels: LogLevelsJson {
                 error: self.log_levels.error,
@@ -349,6 +362,17 @@ impl Stats {
             );
         }
 
+        if let Some(last) = self.last_time {
+            append_metric(
+                &mut output,
+                "phos_log_last_timestamp_seconds",
+                "Unix time of the last log timestamp seen",
+                MetricType::Gauge,
+                &base_labels,
+                last.timestamp(),
+            );
+        }
+
         output
     }
 
@@ -389,10 +413,12 @@ impl Stats {
 
         if self.first_timestamp.is_none() {
             self.first_timestamp.clone_from(&other.first_timestamp);
+            self.first_time = other.first_time;
         }
 
         if other.last_timestamp.is_some() {
             self.last_timestamp.clone_from(&other.last_timestamp);
+            self.last_time = other.last_time;
         }
 
         for (msg, count) in &other.top_errors {
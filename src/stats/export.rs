@@ -53,6 +53,8 @@ pub struct TimeRangeJson {
     pub first: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub last: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<i64>,
 }
 
 /// Log level counts for JSON export.
@@ -78,6 +78,7 @@ mod tests {
             stats.last_timestamp,
             Some("2024-01-15T10:30:50".to_string())
         );
+        assert_eq!(stats.time_span(), Some(chrono::TimeDelta::seconds(5)));
     }
 
     #[test]
@@ -21,8 +21,10 @@ pub struct StatsPatterns {
     /// Matches TRACE (case-insensitive)
     pub trace: Regex,
     /// ISO 8601 timestamps: 2024-01-15T10:30:45 or 2024-01-15 10:30:45
+    #[deprecated(note = "use `phos::timestamp::find`, which parses every supported format")]
     pub timestamp_iso: Regex,
     /// Syslog timestamps: Jan 15 10:30:45
+    #[deprecated(note = "use `phos::timestamp::find`, which parses every supported format")]
     pub timestamp_syslog: Regex,
     /// Extracts error message content after "error:", "failed:", etc.
     pub error_message: Regex,
@@ -34,6 +36,7 @@ pub struct StatsPatterns {
 
 /// Global instance of stats patterns, compiled once at first use.
 /// Uses `ERROR_LEVEL_PATTERN` from `common::log_levels` to avoid duplication.
+#[allow(deprecated)] // Still filled in for library users of the deprecated fields
 pub static STATS_PATTERNS: LazyLock<StatsPatterns> = LazyLock::new(|| StatsPatterns {
     error: ERROR_LEVEL_PATTERN.clone(),
     warn: Regex::new(r"(?i)\b(WARN|WARNING)\b").unwrap(),
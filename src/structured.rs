@@ -0,0 +1,496 @@
+//! Structured (JSON and logfmt) log lines.
+//!
+//! Services that log one JSON object or logfmt record per line are hard to
+//! read as raw text. [`parse`] splits such a line into [`Field`]s and
+//! [`render`] lays them out compactly as `timestamp LEVEL message key=value ...`,
+//! marking the [`Part`]s the [`Colorizer`](crate::Colorizer) styles.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use phos::structured::{self, FieldNames};
+//!
+//! let line = r#"{"time":"10:00:01","level":"info","msg":"Synced","slot":123}"#;
+//! let fields = structured::parse(line).unwrap();
+//! let rendered = structured::render(&fields, &FieldNames::default());
+//! assert_eq!(rendered.text, "10:00:01 INFO  Synced slot=123");
+//!
+//! // Lines that are neither JSON nor logfmt are left to the normal rules
+//! assert!(structured::parse("INFO Synced slot=123").is_none());
+//! ```
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use crate::colors::SemanticColor;
+
+/// Type of a field value, used to pick its color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    String,
+    Number,
+    Boolean,
+    Null,
+    /// Nested JSON object or array (kept as raw text)
+    Object,
+}
+
+/// A key/value pair of a structured line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field<'a> {
+    /// Field name
+    pub key: Cow<'a, str>,
+    /// Value text (JSON strings are unescaped)
+    pub value: Cow<'a, str>,
+    /// Position of the raw value in the line (inside the quotes for strings)
+    pub span: Range<usize>,
+    pub kind: ValueKind,
+}
+
+/// Field names holding the timestamp, level and message.
+///
+/// Each role lists candidate names in order of preference; the first one
+/// present in a line is used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldNames {
+    pub timestamp: Vec<String>,
+    pub level: Vec<String>,
+    pub message: Vec<String>,
+}
+
+impl Default for FieldNames {
+    fn default() -> Self {
+        let names = |list: &[&str]| list.iter().map(|s| (*s).to_string()).collect();
+        Self {
+            timestamp: names(&["time", "timestamp", "ts", "@timestamp", "t", "datetime"]),
+            level: names(&["level", "lvl", "severity", "levelname", "log.level"]),
+            message: names(&["msg", "message", "event"]),
+        }
+    }
+}
+
+impl FieldNames {
+    /// Prefer `name` for the timestamp.
+    #[must_use]
+    pub fn with_timestamp_field(mut self, name: &str) -> Self {
+        self.timestamp.insert(0, name.to_string());
+        self
+    }
+
+    /// Prefer `name` for the log level.
+    #[must_use]
+    pub fn with_level_field(mut self, name: &str) -> Self {
+        self.level.insert(0, name.to_string());
+        self
+    }
+
+    /// Prefer `name` for the message.
+    #[must_use]
+    pub fn with_message_field(mut self, name: &str) -> Self {
+        self.message.insert(0, name.to_string());
+        self
+    }
+}
+
+/// A styled part of a rendered line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Part {
+    Timestamp,
+    /// Log level, with its semantic color if recognized
+    Level(Option<SemanticColor>),
+    Key,
+    Value(ValueKind),
+}
+
+/// A structured line laid out for reading.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rendered {
+    /// The compact text
+    pub text: String,
+    /// Styled parts as (start, end, part), sorted and disjoint
+    pub parts: Vec<(usize, usize, Part)>,
+    /// Position of each field's value in the text, by key
+    pub fields: Vec<(String, Range<usize>)>,
+}
+
+impl Rendered {
+    fn push(&mut self, text: &str, part: Option<Part>) {
+        let start = self.text.len();
+        self.text.push_str(text);
+        if let Some(part) = part {
+            self.parts.push((start, self.text.len(), part));
+        }
+    }
+
+    /// Push a field's value, recording where it landed.
+    fn push_value(&mut self, key: &str, text: &str, part: Option<Part>) {
+        let start = self.text.len();
+        self.push(text, part);
+        self.fields.push((key.to_string(), start..self.text.len()));
+    }
+
+    fn separate(&mut self) {
+        if !self.text.is_empty() {
+            self.text.push(' ');
+        }
+    }
+}
+
+/// Parse a JSON object or logfmt line into fields.
+///
+/// Returns None for anything else (plain text, JSON arrays, lines mixing
+/// free text with `key=value` pairs).
+#[must_use]
+pub fn parse(line: &str) -> Option<Vec<Field<'_>>> {
+    if line.trim_start().starts_with('{') {
+        parse_json(line)
+    } else {
+        parse_logfmt(line)
+    }
+}
+
+/// Lay out fields as `timestamp LEVEL message key=value ...`.
+#[must_use]
+pub fn render(fields: &[Field<'_>], names: &FieldNames) -> Rendered {
+    let find = |candidates: &[String]| {
+        candidates
+            .iter()
+            .find_map(|name| fields.iter().position(|f| f.key == name.as_str()))
+    };
+    let timestamp = find(&names.timestamp);
+    let level = find(&names.level);
+    let message = find(&names.message);
+
+    let mut out = Rendered {
+        text: String::with_capacity(fields.iter().map(|f| f.key.len() + f.value.len() + 2).sum()),
+        parts: Vec::with_capacity(fields.len() * 2),
+        fields: Vec::with_capacity(fields.len()),
+    };
+    if let Some(idx) = timestamp {
+        let field = &fields[idx];
+        out.push_value(&field.key, &field.value, Some(Part::Timestamp));
+    }
+    if let Some(idx) = level {
+        let (key, value) = (&fields[idx].key, &fields[idx].value);
+        out.separate();
+        let part = Part::Level(level_color(value));
+        out.push_value(key, &value.to_uppercase(), Some(part));
+        // Pad short levels so messages line up
+        let width = value.chars().count();
+        for _ in width..5 {
+            out.text.push(' ');
+        }
+    }
+    if let Some(idx) = message.filter(|&idx| !fields[idx].value.is_empty()) {
+        out.separate();
+        out.push_value(&fields[idx].key, &fields[idx].value, None);
+    }
+
+    let rest = fields
+        .iter()
+        .enumerate()
+        .filter(|&(idx, _)| ![timestamp, level, message].contains(&Some(idx)));
+    for (_, field) in rest {
+        out.separate();
+        out.push(&field.key, Some(Part::Key));
+        out.push("=", None);
+        let value = match field.kind {
+            ValueKind::String if needs_quotes(&field.value) => {
+                Cow::Owned(format!("{:?}", field.value))
+            }
+            _ => Cow::Borrowed(field.value.as_ref()),
+        };
+        let start = out.text.len();
+        out.push(&value, Some(Part::Value(field.kind)));
+        // Field rules see a quoted value without its quotes, as in the source line
+        let quoted = usize::from(matches!(value, Cow::Owned(_)));
+        let range = start + quoted..out.text.len() - quoted;
+        out.fields.push((field.key.to_string(), range));
+    }
+    out
+}
+
+/// Semantic color for a level value (names, or pino/bunyan numeric levels).
+#[must_use]
+pub fn level_color(value: &str) -> Option<SemanticColor> {
+    match value.to_ascii_lowercase().as_str() {
+        "error" | "err" | "eror" | "fatal" | "crit" | "critical" | "panic" | "alert" | "emerg"
+        | "50" | "60" => Some(SemanticColor::Error),
+        "warn" | "warning" | "wrn" | "40" => Some(SemanticColor::Warn),
+        "info" | "inf" | "notice" | "30" => Some(SemanticColor::Info),
+        "debug" | "dbug" | "dbg" | "20" => Some(SemanticColor::Debug),
+        "trace" | "trce" | "10" => Some(SemanticColor::Trace),
+        _ => None,
+    }
+}
+
+/// Whether a string value must be quoted to stay one `key=value` token.
+fn needs_quotes(value: &str) -> bool {
+    value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '"' || c == '=')
+}
+
+/// Classify an unquoted scalar.
+fn scalar_kind(value: &str) -> ValueKind {
+    match value {
+        "true" | "false" => ValueKind::Boolean,
+        "null" | "nil" => ValueKind::Null,
+        _ if value.parse::<f64>().is_ok() => ValueKind::Number,
+        _ => ValueKind::String,
+    }
+}
+
+/// Index of the closing quote of a string starting after the quote at `pos`.
+fn string_end(bytes: &[u8], mut pos: usize) -> Option<usize> {
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'\\' => pos += 2,
+            b'"' => return Some(pos),
+            _ => pos += 1,
+        }
+    }
+    None
+}
+
+/// Unescape the JSON string whose content is `line[span]`.
+fn json_string(line: &str, span: Range<usize>) -> Option<Cow<'_, str>> {
+    let raw = &line[span.clone()];
+    if !raw.contains('\\') {
+        return Some(Cow::Borrowed(raw));
+    }
+    serde_json::from_str(&line[span.start - 1..=span.end])
+        .ok()
+        .map(Cow::Owned)
+}
+
+/// Parse the top-level fields of a single-line JSON object.
+fn parse_json(line: &str) -> Option<Vec<Field<'_>>> {
+    let bytes = line.as_bytes();
+    let skip_ws = |mut pos: usize| {
+        while bytes.get(pos).is_some_and(u8::is_ascii_whitespace) {
+            pos += 1;
+        }
+        pos
+    };
+
+    let mut fields = Vec::new();
+    let mut pos = skip_ws(0) + 1; // past '{'
+    loop {
+        pos = skip_ws(pos);
+        if bytes.get(pos) != Some(&b'"') {
+            return None;
+        }
+        let key_end = string_end(bytes, pos + 1)?;
+        let key = json_string(line, pos + 1..key_end)?;
+        pos = skip_ws(key_end + 1);
+        if bytes.get(pos) != Some(&b':') {
+            return None;
+        }
+        pos = skip_ws(pos + 1);
+
+        let (span, kind) = match bytes.get(pos)? {
+            b'"' => {
+                let end = string_end(bytes, pos + 1)?;
+                (pos + 1..end, ValueKind::String)
+            }
+            b'{' | b'[' => (pos..nested_end(bytes, pos)?, ValueKind::Object),
+            _ => {
+                let end = bytes[pos..]
+                    .iter()
+                    .position(|&b| b == b',' || b == b'}' || b.is_ascii_whitespace())
+                    .map_or(bytes.len(), |n| pos + n);
+                let kind = scalar_kind(&line[pos..end]);
+                if kind == ValueKind::String {
+                    return None;
+                }
+                (pos..end, kind)
+            }
+        };
+        let value = match kind {
+            ValueKind::String => json_string(line, span.clone())?,
+            _ => Cow::Borrowed(&line[span.clone()]),
+        };
+        pos = skip_ws(span.end + usize::from(kind == ValueKind::String));
+        fields.push(Field {
+            key,
+            value,
+            span,
+            kind,
+        });
+
+        match bytes.get(pos)? {
+            b',' => pos += 1,
+            b'}' => break,
+            _ => return None,
+        }
+    }
+
+    // Nothing but whitespace may follow the object
+    (!fields.is_empty() && skip_ws(pos + 1) == bytes.len()).then_some(fields)
+}
+
+/// End (exclusive) of the JSON object or array starting at `start`.
+fn nested_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut pos = start;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'"' => pos = string_end(bytes, pos + 1)?,
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(pos + 1);
+                }
+            }
+            _ => {}
+        }
+        pos += 1;
+    }
+    None
+}
+
+/// Parse a logfmt line: whitespace-separated `key=value` pairs, values
+/// optionally double-quoted. Needs at least two pairs and no free text.
+fn parse_logfmt(line: &str) -> Option<Vec<Field<'_>>> {
+    let bytes = line.as_bytes();
+    let mut fields = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        if bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+            continue;
+        }
+        let key_len = bytes[pos..]
+            .iter()
+            .position(|&b| !(b.is_ascii_alphanumeric() || b"_.-/@".contains(&b)))
+            .unwrap_or(bytes.len() - pos);
+        let key_end = pos + key_len;
+        if key_len == 0 || bytes.get(key_end) != Some(&b'=') {
+            return None;
+        }
+        let key = &line[pos..key_end];
+        pos = key_end + 1;
+
+        let field = if bytes.get(pos) == Some(&b'"') {
+            let end = string_end(bytes, pos + 1)?;
+            let span = pos + 1..end;
+            pos = end + 1;
+            Field {
+                key: Cow::Borrowed(key),
+                value: json_string(line, span.clone())?,
+                span,
+                kind: ValueKind::String,
+            }
+        } else {
+            let end = bytes[pos..]
+                .iter()
+                .position(u8::is_ascii_whitespace)
+                .map_or(bytes.len(), |n| pos + n);
+            let span = pos..end;
+            pos = end;
+            Field {
+                key: Cow::Borrowed(key),
+                value: Cow::Borrowed(&line[span.clone()]),
+                kind: scalar_kind(&line[span.clone()]),
+                span,
+            }
+        };
+        fields.push(field);
+    }
+
+    (fields.len() >= 2).then_some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json() {
+        let line = r#"{"msg": "a \"quoted\" word", "slot": 12, "ok": true, "peer": null, "meta": {"a": [1, "}"]}}"#;
+        let fields = parse(line).unwrap();
+        let summary: Vec<_> = fields
+            .iter()
+            .map(|f| (f.key.as_ref(), f.value.as_ref(), f.kind))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("msg", r#"a "quoted" word"#, ValueKind::String),
+                ("slot", "12", ValueKind::Number),
+                ("ok", "true", ValueKind::Boolean),
+                ("peer", "null", ValueKind::Null),
+                ("meta", r#"{"a": [1, "}"]}"#, ValueKind::Object),
+            ]
+        );
+        assert_eq!(&line[fields[1].span.clone()], "12");
+    }
+
+    #[test]
+    fn test_parse_rejects_other_lines() {
+        assert!(parse(r#"{"a": 1} trailing"#).is_none());
+        assert!(parse(r#"{"a": bare}"#).is_none());
+        assert!(parse("[1, 2]").is_none());
+        assert!(parse("INFO msg=hello slot=1").is_none());
+        assert!(parse("key=value").is_none());
+    }
+
+    #[test]
+    fn test_parse_logfmt() {
+        let line = r#"level=warn msg="peer dropped" peer_id=16Uiu2 count=3"#;
+        let fields = parse(line).unwrap();
+        assert_eq!(fields.len(), 4);
+        assert_eq!(fields[1].value, "peer dropped");
+        assert_eq!(fields[3].kind, ValueKind::Number);
+        assert_eq!(&line[fields[2].span.clone()], "16Uiu2");
+    }
+
+    #[test]
+    fn test_render_layout_and_parts() {
+        let line = r#"ts=12:00 lvl=error msg=failed err="bad block" slot=7"#;
+        let fields = parse(line).unwrap();
+        let rendered = render(&fields, &FieldNames::default());
+        assert_eq!(
+            rendered.text,
+            r#"12:00 ERROR failed err="bad block" slot=7"#
+        );
+
+        let parts: Vec<_> = rendered
+            .parts
+            .iter()
+            .map(|&(start, end, part)| (&rendered.text[start..end], part))
+            .collect();
+        assert_eq!(parts[0], ("12:00", Part::Timestamp));
+        assert_eq!(parts[1], ("ERROR", Part::Level(Some(SemanticColor::Error))));
+        assert_eq!(parts[2], ("err", Part::Key));
+        assert_eq!(parts[3], ("\"bad block\"", Part::Value(ValueKind::String)));
+        assert_eq!(parts[5], ("7", Part::Value(ValueKind::Number)));
+
+        let values: Vec<_> = rendered
+            .fields
+            .iter()
+            .map(|(key, range)| (key.as_str(), &rendered.text[range.clone()]))
+            .collect();
+        assert_eq!(
+            values,
+            [
+                ("ts", "12:00"),
+                ("lvl", "ERROR"),
+                ("msg", "failed"),
+                ("err", "bad block"),
+                ("slot", "7")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_field_names() {
+        let line = r#"{"severity_text":"DEBUG","body":"hello","level":3}"#;
+        let names = FieldNames::default()
+            .with_level_field("severity_text")
+            .with_message_field("body");
+        let rendered = render(&parse(line).unwrap(), &names);
+        assert_eq!(rendered.text, "DEBUG hello level=3");
+    }
+}
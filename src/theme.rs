@@ -42,9 +42,23 @@
 use std::collections::HashMap;
 use std::path::Path;
 
+use nu_ansi_term::Style;
 use serde::Deserialize;
 
-use crate::colors::{Color, SemanticColor};
+use crate::colors::{parse_hex_rgb, Attributes, Color, SemanticColor};
+
+/// Theme colors hashed colors are drawn from, in hue order (orange, green,
+/// cyan, blue, purple) so neighbours blend into the hues between them.
+const HASHED_SOURCES: [SemanticColor; 5] = [
+    SemanticColor::Warn,
+    SemanticColor::String,
+    SemanticColor::Identifier,
+    SemanticColor::Info,
+    SemanticColor::Number,
+];
+
+/// Hashed palette of themes that define none of [`HASHED_SOURCES`].
+const HASHED_FALLBACK: [&str; 6] = ["cyan", "green", "yellow", "blue", "magenta", "red"];
 
 /// Configuration for a user-defined theme.
 #[derive(Debug, Deserialize)]
@@ -60,6 +74,37 @@ pub struct ThemeConfig {
     /// Optional base palette
     #[serde(default)]
     pub palette: Option<PaletteConfig>,
+    /// Extra styling per semantic color (semantic name -> background/attributes)
+    #[serde(default)]
+    pub styles: HashMap<String, StyleConfig>,
+}
+
+/// Background and attribute configuration for a semantic color.
+///
+/// ```yaml
+/// styles:
+///   error:
+///     background: "#5F0000"
+///     bold: true
+///     reverse: true
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub struct StyleConfig {
+    /// Background color (hex, ANSI name, or semantic name)
+    #[serde(default)]
+    pub background: Option<String>,
+    /// Text attributes (bold, dim, italic, underline, reverse, strikethrough)
+    #[serde(flatten)]
+    pub attributes: Attributes,
+}
+
+/// Extra styling a theme attaches to a semantic color on top of its foreground.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SemanticStyle {
+    /// Background color (semantic colors are resolved through the theme)
+    pub background: Option<Color>,
+    /// Text attributes
+    pub attributes: Attributes,
 }
 
 /// Palette configuration for user themes.
@@ -145,6 +190,14 @@ impl From<crate::config::ConfigError> for ThemeLoadError {
 /// // Create a custom theme
 /// let mut custom = Theme::new("my-theme");
 /// custom.set(SemanticColor::Error, Color::hex("#FF0000"));
+///
+/// // Make errors stand out as a reversed bar
+/// use phos::theme::SemanticStyle;
+/// use phos::colors::Attributes;
+/// custom.set_style(SemanticColor::Error, SemanticStyle {
+///     background: None,
+///     attributes: Attributes { reverse: true, ..Attributes::default() },
+/// });
 /// ```
 #[derive(Debug, Clone)]
 pub struct Theme {
@@ -154,6 +207,8 @@ pub struct Theme {
     pub description: String,
     /// Semantic color mappings
     colors: HashMap<SemanticColor, Color>,
+    /// Extra background/attribute styling per semantic color
+    styles: HashMap<SemanticColor, SemanticStyle>,
 }
 
 /// A color palette defining the base colors for a theme.
@@ -436,6 +491,7 @@ impl Theme {
             name: name.to_string(),
             description: String::new(),
             colors: HashMap::new(),
+            styles: HashMap::new(),
         }
     }
 
@@ -449,6 +505,7 @@ impl Theme {
                 .iter()
                 .map(|(sem, hex)| (*sem, Color::hex(hex)))
                 .collect(),
+            styles: HashMap::new(),
         }
     }
 
@@ -457,6 +514,37 @@ impl Theme {
         self.colors.insert(semantic, color);
     }
 
+    /// Attach background and attribute styling to a semantic color.
+    pub fn set_style(&mut self, semantic: SemanticColor, style: SemanticStyle) {
+        self.styles.insert(semantic, style);
+    }
+
+    /// Get the extra styling attached to a semantic color, if any.
+    #[must_use]
+    pub fn style(&self, semantic: SemanticColor) -> Option<&SemanticStyle> {
+        self.styles.get(&semantic)
+    }
+
+    /// Resolve a color to a full terminal style.
+    ///
+    /// Semantic colors pick up the theme's foreground plus any background and
+    /// attributes attached with [`Theme::set_style`].
+    #[must_use]
+    pub fn resolve_style(&self, color: &Color) -> Style {
+        let style = self.resolve_color(color).to_style();
+        let Color::Semantic(semantic) = color else {
+            return style;
+        };
+        self.styles.get(semantic).map_or(style, |extra| {
+            let style = extra.attributes.apply(style);
+            extra
+                .background
+                .as_ref()
+                .and_then(|bg| self.resolve_color(bg).to_background_style().background)
+                .map_or(style, |bg| style.on(bg))
+        })
+    }
+
     /// Resolve a semantic color to its actual color.
     #[must_use]
     pub fn resolve(&self, semantic: SemanticColor) -> Option<&Color> {
@@ -472,6 +560,43 @@ impl Theme {
         }
     }
 
+    /// Colors [`Color::Hashed`] picks from.
+    ///
+    /// Taken from the theme's hues, with a blend of each neighbouring pair
+    /// in between, so they keep the theme's contrast. Reds, grays and the
+    /// foreground are left out so identifiers do not read as errors or plain text.
+    #[must_use]
+    pub fn hashed_palette(&self) -> Vec<Color> {
+        let mut hues: Vec<&Color> = Vec::new();
+        for color in HASHED_SOURCES.iter().filter_map(|&s| self.resolve(s)) {
+            if !hues.contains(&color) {
+                hues.push(color);
+            }
+        }
+        if hues.is_empty() {
+            return HASHED_FALLBACK
+                .iter()
+                .map(|name| Color::named(name))
+                .collect();
+        }
+
+        let rgb = |color: &Color| match color {
+            Color::Hex(hex) => parse_hex_rgb(hex),
+            Color::Rgb { r, g, b } => Some((*r, *g, *b)),
+            _ => None,
+        };
+        let mut palette = Vec::with_capacity(hues.len() * 2);
+        for (i, &hue) in hues.iter().enumerate() {
+            palette.push(hue.clone());
+            let next = hues[(i + 1) % hues.len()];
+            if let Some((a, b)) = rgb(hue).zip(rgb(next)).filter(|_| next != hue) {
+                let mix = |x: u8, y: u8| x / 2 + y / 2 + (x & y & 1);
+                palette.push(Color::rgb(mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2)));
+            }
+        }
+        palette
+    }
+
     /// Validate that all semantic colors are defined in this theme.
     ///
     /// Returns a list of missing semantic colors. An empty list means the theme
@@ -582,10 +707,39 @@ impl Theme {
         // Merge: overrides take precedence over palette
         let colors = palette_colors.into_iter().chain(override_colors).collect();
 
+        let styles = config
+            .styles
+            .into_iter()
+            .filter_map(|(name, style)| {
+                SemanticColor::from_name(&name).map(|sem| {
+                    let background = style.background.as_deref().map(|bg| {
+                        SemanticColor::from_name(bg).map_or_else(
+                            || {
+                                if bg.starts_with('#') {
+                                    Color::hex(bg)
+                                } else {
+                                    Color::named(bg)
+                                }
+                            },
+                            Color::Semantic,
+                        )
+                    });
+                    (
+                        sem,
+                        SemanticStyle {
+                            background,
+                            attributes: style.attributes,
+                        },
+                    )
+                })
+            })
+            .collect();
+
         Self {
             name: config.name,
             description: config.description,
             colors,
+            styles,
         }
     }
 
@@ -746,6 +900,74 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_semantic_style_applied() {
+        let mut theme = Theme::default_dark();
+        theme.set_style(
+            SemanticColor::Error,
+            SemanticStyle {
+                background: Some(Color::named("red")),
+                attributes: Attributes {
+                    bold: true,
+                    ..Attributes::default()
+                },
+            },
+        );
+
+        let style = theme.resolve_style(&Color::Semantic(SemanticColor::Error));
+        assert!(style.is_bold);
+        assert_eq!(style.background, Some(nu_ansi_term::Color::Red));
+        assert!(style.foreground.is_some());
+
+        // Other semantic colors are unaffected
+        let style = theme.resolve_style(&Color::Semantic(SemanticColor::Info));
+        assert!(!style.is_bold && style.background.is_none());
+    }
+
+    #[test]
+    fn test_theme_config_styles() {
+        let yaml = r##"
+name: loud
+colors:
+  error: "#FF0000"
+styles:
+  error:
+    background: "#550000"
+    reverse: true
+  warn:
+    background: error
+    underline: true
+"##;
+        let config: ThemeConfig = serde_yaml::from_str(yaml).unwrap();
+        let theme = Theme::from_config(config);
+
+        let error = theme.style(SemanticColor::Error).unwrap();
+        assert_eq!(error.background, Some(Color::hex("#550000")));
+        assert!(error.attributes.reverse && !error.attributes.bold);
+
+        let warn = theme.resolve_style(&Color::Semantic(SemanticColor::Warn));
+        assert!(warn.is_underline);
+        assert_eq!(warn.background, Some(nu_ansi_term::Color::Rgb(255, 0, 0)));
+    }
+
+    #[test]
+    fn test_hashed_palette() {
+        // Dracula's four distinct hues plus a blend between each neighbouring pair
+        let theme = Theme::dracula();
+        let palette = theme.hashed_palette();
+        assert_eq!(palette.len(), 8);
+        assert_eq!(palette[1], Color::rgb(0xF8, 0xD9, 0x7C));
+        assert!(!palette.contains(theme.resolve(SemanticColor::Error).unwrap()));
+
+        // Blends need RGB values; named colors are used as they are
+        let mut theme = Theme::new("ansi");
+        theme.set(SemanticColor::Identifier, Color::named("cyan"));
+        theme.set(SemanticColor::String, Color::named("green"));
+        let expected = [Color::named("green"), Color::named("cyan")];
+        assert_eq!(theme.hashed_palette(), expected);
+        assert_eq!(Theme::new("empty").hashed_palette().len(), 6);
+    }
+
     #[test]
     fn test_palette_generates_all_colors() {
         let palette = Palette {
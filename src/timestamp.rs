@@ -0,0 +1,322 @@
+//! Timestamp parsing and normalization.
+//!
+//! Logs mix many timestamp formats: ISO 8601 with or without a zone, syslog
+//! `Dec 05 00:12:36`, geth `[12-05|00:12:36.557]`, log4j `2024-12-05 00:12:36,557`,
+//! redis `05 Dec 2024 00:12:36.557` and epoch times. This module finds
+//! and parses them into instants, and rewrites them in one [`TimeFormat`] so a
+//! fleet's logs line up. Stats and alerts use the parsed times too.
+//!
+//! Timestamps without a zone are taken as local time, and those without a year
+//! (syslog, geth) as the most recent such date that is not in the future.
+//! Epoch times in a line count only after a time key (`ts=`, `"timestamp":`),
+//! since bare numbers are as likely block numbers or amounts.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use phos::timestamp::{self, TimeFormat};
+//!
+//! let line = "2024-12-05T00:12:36.557+02:00 INFO Synced";
+//! let found = timestamp::find(line).unwrap();
+//! assert_eq!(found.range, 0..29);
+//! assert_eq!(
+//!     TimeFormat::Iso.format(found.time, found.time),
+//!     "2024-12-04T22:12:36.557Z"
+//! );
+//! assert_eq!(
+//!     timestamp::rewrite(line, TimeFormat::Utc),
+//!     "2024-12-04 22:12:36.557 UTC INFO Synced"
+//! );
+//! ```
+
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::LazyLock;
+
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDateTime, TimeDelta, TimeZone, Utc};
+use clap::ValueEnum;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+
+/// All supported formats, one named group each; the leftmost match wins.
+static TIMESTAMP_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        // ISO 8601 / RFC 3339, log4j and database variants
+        r"(?P<iso>\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:[.,]\d{1,9})?(?:Z|[+-]\d{2}:?\d{2}| ?UTC)?)",
+        // Syslog: Dec 05 00:12:36
+        r"|(?P<syslog>\b[A-Z][a-z]{2} +\d{1,2} \d{2}:\d{2}:\d{2}(?:\.\d{1,9})?)",
+        // Geth: 12-05|00:12:36.557
+        r"|(?P<geth>\b\d{2}-\d{2}\|\d{2}:\d{2}:\d{2}(?:\.\d{1,9})?)",
+        // Redis: 05 Dec 2024 00:12:36.557
+        r"|(?P<redis>\b\d{2} [A-Z][a-z]{2} \d{4} \d{2}:\d{2}:\d{2}(?:\.\d{1,9})?)",
+        // Epoch milliseconds (2001-2033), or seconds with a fraction, after a time key
+        r#"|\b(?i:ts|time|timestamp)"?\s*[=:]\s*"?"#,
+        r"(?:(?P<millis>1\d{12})|(?P<seconds>1\d{9}\.\d{1,9}))\b",
+    ))
+    .expect("timestamp regex is valid")
+});
+
+/// Names of the format groups, whose match is the timestamp text.
+const FORMATS: [&str; 6] = ["iso", "syslog", "geth", "redis", "millis", "seconds"];
+
+/// Output format for rewritten timestamps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeFormat {
+    /// Local time: 2024-12-05 01:12:36.557
+    Local,
+    /// UTC: 2024-12-05 00:12:36.557 UTC
+    Utc,
+    /// RFC 3339 in UTC: 2024-12-05T00:12:36.557Z
+    Iso,
+    /// Age relative to now: 3m04s ago
+    Relative,
+}
+
+impl TimeFormat {
+    /// Format `time`, with `now` as the reference for relative times.
+    #[must_use]
+    pub fn format(self, time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+        match self {
+            Self::Local => time
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S%.3f")
+                .to_string(),
+            Self::Utc => time.format("%Y-%m-%d %H:%M:%S%.3f UTC").to_string(),
+            Self::Iso => time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            Self::Relative if time > now => format!("in {}", format_duration(time - now)),
+            Self::Relative => format!("{} ago", format_duration(now - time)),
+        }
+    }
+}
+
+/// A timestamp found in a line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timestamp {
+    /// Byte range of the timestamp text
+    pub range: Range<usize>,
+    /// The instant it denotes
+    pub time: DateTime<Utc>,
+}
+
+/// Find and parse the first timestamp in `line`.
+#[must_use]
+pub fn find(line: &str) -> Option<Timestamp> {
+    find_at(line, Utc::now())
+}
+
+/// Like [`find`], inferring missing years relative to `now`.
+#[must_use]
+pub fn find_at(line: &str, now: DateTime<Utc>) -> Option<Timestamp> {
+    TIMESTAMP_REGEX.captures_iter(line).find_map(|caps| {
+        let range = FORMATS.iter().find_map(|name| caps.name(name))?.range();
+        let time = parse_captures(&caps, now)?;
+        Some(Timestamp { range, time })
+    })
+}
+
+/// Parse `text` as a whole, such as a structured timestamp field.
+///
+/// Besides the formats found in lines, this accepts bare epoch seconds and
+/// milliseconds.
+#[must_use]
+pub fn parse(text: &str) -> Option<DateTime<Utc>> {
+    let text = text.trim();
+    if let Some(time) = parse_epoch(text) {
+        return Some(time);
+    }
+    let caps = TIMESTAMP_REGEX.captures(text)?;
+    let whole = caps.get(0)?;
+    if whole.range() != (0..text.len()) {
+        return None;
+    }
+    parse_captures(&caps, Utc::now())
+}
+
+/// Rewrite the first timestamp in `line` in `format`.
+#[must_use]
+pub fn rewrite(line: &str, format: TimeFormat) -> Cow<'_, str> {
+    let now = Utc::now();
+    match find_at(line, now) {
+        Some(Timestamp { range, time }) => {
+            let mut out = String::with_capacity(line.len() + 8);
+            out.push_str(&line[..range.start]);
+            out.push_str(&format.format(time, now));
+            out.push_str(&line[range.end..]);
+            Cow::Owned(out)
+        }
+        None => Cow::Borrowed(line),
+    }
+}
+
+/// Format a duration compactly: `850ms`, `42s`, `3m04s`, `2h05m`, `3d04h`.
+#[must_use]
+pub fn format_duration(delta: TimeDelta) -> String {
+    let millis = delta.num_milliseconds().unsigned_abs();
+    let secs = millis / 1000;
+    match secs {
+        0 => format!("{millis}ms"),
+        1..60 => format!("{secs}s"),
+        60..3600 => format!("{}m{:02}s", secs / 60, secs % 60),
+        3600..86400 => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d{:02}h", secs / 86400, secs % 86400 / 3600),
+    }
+}
+
+/// Parse the format group that matched.
+fn parse_captures(caps: &Captures<'_>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if let Some(m) = caps.name("iso") {
+        return parse_iso(m.as_str());
+    }
+    if let Some(m) = caps.name("syslog") {
+        let text = m.as_str().split_whitespace().collect::<Vec<_>>().join(" ");
+        return with_inferred_year(now, |year| {
+            NaiveDateTime::parse_from_str(&format!("{year} {text}"), "%Y %b %d %H:%M:%S%.f").ok()
+        });
+    }
+    if let Some(m) = caps.name("geth") {
+        return with_inferred_year(now, |year| {
+            let text = format!("{year}-{}", m.as_str());
+            NaiveDateTime::parse_from_str(&text, "%Y-%m-%d|%H:%M:%S%.f").ok()
+        });
+    }
+    if let Some(m) = caps.name("redis") {
+        let naive = NaiveDateTime::parse_from_str(m.as_str(), "%d %b %Y %H:%M:%S%.f").ok()?;
+        return local(naive);
+    }
+    let epoch = caps.name("millis").or_else(|| caps.name("seconds"))?;
+    parse_epoch(epoch.as_str())
+}
+
+/// Parse epoch seconds (10 digits, optionally with a fraction) or milliseconds (13 digits).
+fn parse_epoch(text: &str) -> Option<DateTime<Utc>> {
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    match text.split_once('.') {
+        None if digits(text) && text.len() == 13 => {
+            DateTime::from_timestamp_millis(text.parse().ok()?)
+        }
+        None if digits(text) && text.len() == 10 => DateTime::from_timestamp(text.parse().ok()?, 0),
+        Some((secs, fraction))
+            if digits(secs) && secs.len() == 10 && digits(fraction) && fraction.len() <= 9 =>
+        {
+            let nanos = format!("{fraction:0<9}").parse().ok()?;
+            DateTime::from_timestamp(secs.parse().ok()?, nanos)
+        }
+        _ => None,
+    }
+}
+
+/// Parse an ISO 8601 timestamp; without a zone it is local time.
+fn parse_iso(text: &str) -> Option<DateTime<Utc>> {
+    // The date is always 10 bytes, followed by `T` or a space
+    let text = format!("{} {}", &text[..10], &text[11..]).replacen(',', ".", 1);
+    if let Some(naive) = text
+        .strip_suffix('Z')
+        .or_else(|| text.strip_suffix("UTC").map(str::trim_end))
+    {
+        let naive = NaiveDateTime::parse_from_str(naive, "%Y-%m-%d %H:%M:%S%.f").ok()?;
+        return Some(naive.and_utc());
+    }
+    if let Ok(time) = DateTime::<FixedOffset>::parse_from_str(&text, "%Y-%m-%d %H:%M:%S%.f%z") {
+        return Some(time.to_utc());
+    }
+    local(NaiveDateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S%.f").ok()?)
+}
+
+/// A local wall-clock time as an instant.
+fn local(naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|time| time.to_utc())
+}
+
+/// Parse a date without a year, taking the latest year that is not in the future.
+///
+/// A day of slack allows for clock skew between hosts.
+fn with_inferred_year(
+    now: DateTime<Utc>,
+    parse: impl Fn(i32) -> Option<NaiveDateTime>,
+) -> Option<DateTime<Utc>> {
+    let year = now.with_timezone(&Local).year();
+    let time = local(parse(year)?)?;
+    if time - now > TimeDelta::days(1) {
+        local(parse(year - 1)?)
+    } else {
+        Some(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().to_utc()
+    }
+
+    #[test]
+    fn test_iso_variants() {
+        let expected = utc("2024-12-05T00:12:36.557Z");
+        for line in [
+            "2024-12-05T00:12:36.557Z INFO x",
+            "2024-12-05 00:12:36.557 UTC LOG: x",
+            "[2024-12-05 02:12:36,557+02:00] x",
+            "ts=2024-12-05T00:12:36.557+0000 x",
+        ] {
+            assert_eq!(find(line).map(|t| t.time), Some(expected), "{line}");
+        }
+        let found = find("[2024-12-05 00:12:36,557] INFO").unwrap();
+        assert_eq!(found.range, 1..24);
+    }
+
+    #[test]
+    fn test_year_inference() {
+        let now = utc("2025-01-02T00:00:00Z");
+        let syslog = find_at("Dec 31 12:00:00 host sshd[1]: x", now).unwrap();
+        assert_eq!(syslog.time.with_timezone(&Local).year(), 2024);
+        let geth = find_at("INFO [01-01|12:00:00.123] Imported", now).unwrap();
+        assert_eq!(geth.time.with_timezone(&Local).year(), 2025);
+        assert_eq!(geth.range, 6..24);
+    }
+
+    #[test]
+    fn test_epoch_and_redis() {
+        let expected = utc("2024-12-05T00:12:36.557Z");
+        assert_eq!(find(r#"{"ts":1733357556557}"#).unwrap().time, expected);
+        let found = find("ts=1733357556.557 msg=x").unwrap();
+        assert_eq!((found.time, found.range), (expected, 3..17));
+        assert_eq!(
+            find(r#""timestamp": "1733357556557""#).unwrap().time,
+            expected
+        );
+        assert_eq!(parse("1733357556"), Some(utc("2024-12-05T00:12:36Z")));
+        assert_eq!(parse("1733357556557"), Some(expected));
+        assert!(find("1:M 05 Dec 2024 00:12:36.557 * Ready").is_some());
+        // Slot numbers, short ids and unkeyed epoch-sized numbers are not timestamps
+        assert_eq!(find("slot=10250000 root=0xabc"), None);
+        let line = "block=1234567890123 gas=1733357556557 value=1733357556.557";
+        assert_eq!(find(line), None);
+        assert_eq!(rewrite(line, TimeFormat::Iso), line);
+        assert_eq!(parse("2024-12-05T00:12:36Z trailing"), None);
+    }
+
+    #[test]
+    fn test_formats() {
+        let time = utc("2024-12-05T00:12:36.557Z");
+        assert_eq!(
+            TimeFormat::Utc.format(time, time),
+            "2024-12-05 00:12:36.557 UTC"
+        );
+        assert_eq!(
+            TimeFormat::Iso.format(time, time),
+            "2024-12-05T00:12:36.557Z"
+        );
+        let later = time + TimeDelta::seconds(184);
+        assert_eq!(TimeFormat::Relative.format(time, later), "3m04s ago");
+        assert_eq!(TimeFormat::Relative.format(later, time), "in 3m04s");
+        assert_eq!(format_duration(TimeDelta::milliseconds(850)), "850ms");
+        assert_eq!(format_duration(TimeDelta::hours(50)), "2d02h");
+        assert_eq!(rewrite("no time here", TimeFormat::Iso), "no time here");
+    }
+}
@@ -3,7 +3,8 @@
 //! These tests verify the command-line interface behavior.
 
 use std::io::Write;
-use std::process::{Command, Stdio};
+use std::process::{Child, Command, Stdio};
+use std::thread;
 
 /// Get the path to the phos binary
 fn phos_bin() -> Command {
@@ -34,13 +35,9 @@ fn run_phos_with_stdin(args: &[&str], input: &str) -> (String, String, bool) {
         .spawn()
         .expect("Failed to spawn phos");
 
-    if let Some(mut stdin) = child.stdin.take() {
-        stdin
-            .write_all(input.as_bytes())
-            .expect("Failed to write to stdin");
-    }
-
+    let writer = write_stdin(&mut child, input.as_bytes());
     let output = child.wait_with_output().expect("Failed to wait for phos");
+    writer.join().expect("Failed to write to stdin");
 
     (
         String::from_utf8_lossy(&output.stdout).to_string(),
@@ -49,6 +46,33 @@ fn run_phos_with_stdin(args: &[&str], input: &str) -> (String, String, bool) {
     )
 }
 
+/// Run phos with raw stdin bytes and return (stdout bytes, success)
+fn run_phos_with_stdin_bytes(args: &[&str], input: &[u8]) -> (Vec<u8>, bool) {
+    let mut child = phos_bin()
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn phos");
+
+    let writer = write_stdin(&mut child, input);
+    let output = child.wait_with_output().expect("Failed to wait for phos");
+    writer.join().expect("Failed to write to stdin");
+    (output.stdout, output.status.success())
+}
+
+/// Feed stdin from a thread, so large inputs cannot deadlock on a full stdout pipe
+fn write_stdin(child: &mut Child, input: &[u8]) -> thread::JoinHandle<()> {
+    let stdin = child.stdin.take();
+    let input = input.to_vec();
+    thread::spawn(move || {
+        if let Some(mut stdin) = stdin {
+            stdin.write_all(&input).expect("Failed to write to stdin");
+        }
+    })
+}
+
 // =============================================================================
 // Version and Help Tests
 // =============================================================================
@@ -208,6 +232,51 @@ mod info_command {
     }
 }
 
+// =============================================================================
+// Detect Subcommand Tests
+// =============================================================================
+
+mod detect_command {
+    use super::*;
+
+    const GETH_LOG: &str = "INFO [12-05|00:12:36.000] Imported new potential chain segment number=21,345,678\n\
+                            WARN [12-05|00:12:37.000] Snapshot extension registration failed peer=1a2b3c4d\n";
+
+    #[test]
+    fn test_detect_ranks_programs() {
+        let (stdout, _, success) = run_phos_with_stdin(&["detect"], GETH_LOG);
+        assert!(success);
+        assert!(
+            stdout.starts_with("Detected: ethereum.geth (2 lines sampled)"),
+            "{stdout}"
+        );
+        assert!(stdout.contains("CONFIDENCE"));
+    }
+
+    #[test]
+    fn test_detect_json_and_sample_size() {
+        let path = std::env::temp_dir().join(format!("phos-detect-{}.log", std::process::id()));
+        std::fs::write(&path, format!("plain text\n{GETH_LOG}")).unwrap();
+        let file = path.to_str().unwrap();
+
+        let (stdout, _, success) = run_phos(&["detect", file, "-f", "json"]);
+        assert!(success);
+        let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        assert_eq!(json["lines"], 3);
+        assert_eq!(json["detected"], "ethereum.geth");
+        assert_eq!(json["candidates"][0]["id"], "ethereum.geth");
+
+        // The first line alone says nothing
+        let (stdout, _, success) = run_phos(&["detect", file, "--detect-lines", "1"]);
+        assert!(success);
+        assert!(
+            stdout.starts_with("No program detected (1 lines sampled)"),
+            "{stdout}"
+        );
+        std::fs::remove_file(path).ok();
+    }
+}
+
 // =============================================================================
 // Colors Subcommand Tests
 // =============================================================================
@@ -342,6 +411,147 @@ mod pipe_input {
         assert_eq!(lines.len(), 3);
     }
 
+    #[test]
+    fn test_pipe_multiline_records() {
+        let input = "ERROR failed\njava.lang.IllegalStateException: boom\n\tat Foo.bar(Foo.java:1)\nINFO ok\n";
+        let (stdout, stderr, success) =
+            run_phos_with_stdin(&["-p", "cargo", "--multiline", "--stats"], input);
+        assert!(success);
+        assert_eq!(stdout, input);
+        // The stack trace is counted with the line it belongs to
+        assert!(stderr.contains("Lines processed: 2"), "{stderr}");
+    }
+
+    #[test]
+    fn test_pipe_structured() {
+        let input = "{\"level\":\"info\",\"msg\":\"Synced\",\"slot\":12}\nplain line\n";
+        let (stdout, _, success) = run_phos_with_stdin(&["--structured"], input);
+        assert!(success);
+        assert_eq!(stdout, "INFO  Synced slot=12\nplain line\n");
+    }
+
+    #[test]
+    fn test_pipe_highlight() {
+        let input = "validator 4821 attested";
+        let (plain, _, _) = run_phos_with_stdin(&["-p", "cargo", "--color"], input);
+        let (stdout, _, success) =
+            run_phos_with_stdin(&["-p", "cargo", "--color", "-H", "4821"], input);
+        assert!(success);
+        assert_ne!(stdout, plain, "highlight should style the match");
+
+        let (_, stderr, success) = run_phos_with_stdin(&["-p", "cargo", "-H", "("], "");
+        assert!(!success);
+        assert!(stderr.contains("--highlight"), "{stderr}");
+    }
+
+    #[test]
+    fn test_pipe_composed_programs() {
+        let input = "app-1  | LOG:  connection received: host=10.0.0.5";
+        let (docker, _, _) = run_phos_with_stdin(&["-p", "docker", "--color"], input);
+        let (stdout, _, success) =
+            run_phos_with_stdin(&["-p", "docker,postgres", "--color"], input);
+        assert!(success);
+        assert_ne!(stdout, docker, "postgres rules should apply too");
+
+        let (_, stderr, success) = run_phos_with_stdin(&["-p", "docker, nope"], input);
+        assert!(!success);
+        assert!(stderr.contains("Unknown program: nope."), "{stderr}");
+
+        let (_, stderr, success) = run_phos_with_stdin(&["-p", "docker,"], input);
+        assert!(!success);
+        assert!(stderr.contains("Empty program name"), "{stderr}");
+    }
+
+    #[test]
+    fn test_pipe_highlight_hashed() {
+        let input = "req-a1 start\nreq-b2 start\nreq-a1 done";
+        let args = ["-p", "cargo", "--color", "-H", r"req-\w+:hashed"];
+        let (stdout, _, success) = run_phos_with_stdin(&args, input);
+        assert!(success);
+        // Each line opens with the escape sequence painting its ID
+        let prefixes: Vec<&str> = stdout
+            .lines()
+            .map(|line| line.split("req-").next().unwrap())
+            .collect();
+        assert_eq!(prefixes[0], prefixes[2], "same ID, same color");
+        assert_ne!(prefixes[0], prefixes[1], "{stdout:?}");
+    }
+
+    #[test]
+    fn test_pipe_grep() {
+        let input = "a\nERROR one\nb\nc\nd\nERROR two\ne";
+        let (stdout, stderr, success) = run_phos_with_stdin(
+            &["-p", "cargo", "--grep", "ERROR", "-A", "1", "--stats"],
+            input,
+        );
+        assert!(success);
+        assert_eq!(stdout, "ERROR one\nb\n--\nERROR two\ne");
+        // Hidden lines are still counted
+        assert!(stderr.contains("Lines processed: 7"), "{stderr}");
+
+        let (stdout, _, success) =
+            run_phos_with_stdin(&["-p", "cargo", "--grep", "ERROR", "-v"], input);
+        assert!(success);
+        assert_eq!(stdout, "a\nb\nc\nd\ne");
+
+        let (_, _, success) = run_phos_with_stdin(&["-p", "cargo", "-C", "2"], "");
+        assert!(!success, "context flags require --grep");
+    }
+
+    #[test]
+    fn test_pipe_level() {
+        let input = "DEBG noisy\n  detail\nWARN slow\nCRIT corrupt\n  at frame\nINFO ok";
+        let args = ["-c", "lighthouse", "--level", "warn", "--no-multiline"];
+        let (stdout, _, success) = run_phos_with_stdin(&args, input);
+        assert!(success);
+        // Indented lines inherit the level of the line they continue
+        assert_eq!(stdout, "WARN slow\nCRIT corrupt\n  at frame\n");
+
+        let (_, _, success) = run_phos_with_stdin(&["-p", "cargo", "--level", "loud"], "");
+        assert!(!success);
+    }
+
+    #[test]
+    fn test_pipe_time_format() {
+        let input = "2024-12-05T02:12:36.557+02:00 INFO Synced\nno time here";
+        let args = ["-p", "cargo", "--time-format", "utc"];
+        let (stdout, _, success) = run_phos_with_stdin(&args, input);
+        assert!(success);
+        assert_eq!(
+            stdout,
+            "2024-12-05 00:12:36.557 UTC INFO Synced\nno time here"
+        );
+    }
+
+    #[test]
+    fn test_pipe_delta() {
+        let input = "[12-05|00:12:24.000] Slot 1\n[12-05|00:12:36.800] Slot 2\n";
+        let (stdout, _, success) = run_phos_with_stdin(&["-p", "cargo", "--delta"], input);
+        assert!(success);
+        assert_eq!(
+            stdout,
+            "+0.000s [12-05|00:12:24.000] Slot 1\n +12.8s [12-05|00:12:36.800] Slot 2\n"
+        );
+
+        let (_, _, success) = run_phos_with_stdin(&["-p", "cargo", "--delta-warn", "5"], "");
+        assert!(!success, "thresholds require --delta");
+    }
+
+    #[test]
+    fn test_pipe_dedup() {
+        let input = "peer 1 dropped\npeer 2 dropped\npeer 3 dropped\nsynced\n";
+        let args = ["-p", "cargo", "--dedup", "--stats"];
+        let (stdout, stderr, success) = run_phos_with_stdin(&args, input);
+        assert!(success);
+        assert_eq!(stdout, "peer 1 dropped\n(x2 repeated)\nsynced\n");
+        // Collapsed lines are still counted
+        assert!(stderr.contains("Lines processed: 4"), "{stderr}");
+
+        let args = ["-p", "cargo", "--dedup=exact"];
+        let (stdout, _, _) = run_phos_with_stdin(&args, input);
+        assert_eq!(stdout, input);
+    }
+
     #[test]
     fn test_pipe_with_color_flag() {
         // Use a pattern that cargo program actually matches (error codes)
@@ -363,6 +573,26 @@ mod pipe_input {
         // Without --color and piped, should NOT have ANSI codes
         assert!(!stdout.contains("\x1b["));
     }
+
+    #[test]
+    fn test_pipe_with_jobs() {
+        let input: String = (0..20_000).map(|i| format!("line {i}\n")).collect();
+        let (stdout, _, success) = run_phos_with_stdin(&["-p", "cargo", "-j", "4"], &input);
+        assert!(success);
+        assert_eq!(stdout, input);
+    }
+
+    #[test]
+    fn test_file_input() {
+        let path = std::env::temp_dir().join(format!("phos-file-input-{}.log", std::process::id()));
+        std::fs::write(&path, "line 1\nline 2\n").expect("Failed to write temp file");
+
+        let (stdout, _, success) =
+            run_phos_with_stdin(&["-p", "cargo", "--file", path.to_str().unwrap()], "");
+        let _ = std::fs::remove_file(&path);
+        assert!(success);
+        assert_eq!(stdout, "line 1\nline 2\n");
+    }
 }
 
 // =============================================================================
@@ -560,6 +790,27 @@ rules:
         assert!(success, "Config test failed: {stderr}");
         assert!(stdout.contains("TEST"));
     }
+
+    #[test]
+    fn test_validate_count_modes() {
+        let path = env::temp_dir().join(format!("phos-count-{}.yaml", std::process::id()));
+        let config = |count: &str| {
+            format!(
+                "name: plan\nrules:\n  - regex: '^Plan:'\n    colors: [info]\n    count: {count}\n"
+            )
+        };
+
+        fs::write(&path, config("block")).expect("Failed to write test config");
+        let (stdout, stderr, success) = run_phos(&["config", "validate", path.to_str().unwrap()]);
+        assert!(success, "{stderr}");
+        assert!(stdout.contains("Valid"), "{stdout}");
+
+        fs::write(&path, config("blok")).expect("Failed to write test config");
+        let (_, stderr, success) = run_phos(&["config", "validate", path.to_str().unwrap()]);
+        let _ = fs::remove_file(&path);
+        assert!(!success);
+        assert!(stderr.contains("unknown variant `blok`"), "{stderr}");
+    }
 }
 
 // =============================================================================
@@ -612,6 +863,40 @@ mod edge_cases {
         assert!(success);
         assert!(stdout.ends_with('\n'));
     }
+
+    #[test]
+    fn test_invalid_utf8_passthrough() {
+        let input = b"before\nbinary \xff\xfe junk\nafter\n";
+        let (stdout, success) = run_phos_with_stdin_bytes(&["-p", "docker"], input);
+        assert!(success);
+        assert_eq!(stdout, input);
+    }
+
+    #[test]
+    fn test_invalid_utf8_processed() {
+        let input = b"a\xffb ERROR\n";
+        let (stdout, success) = run_phos_with_stdin_bytes(&["-p", "cargo", "--delta"], input);
+        assert!(success);
+        assert_eq!(stdout, "+0.000s a\u{FFFD}b ERROR\n".as_bytes());
+
+        let path = std::env::temp_dir().join(format!("phos-skip-{}.yaml", std::process::id()));
+        let config = "name: skip\ndescription: Skip\nrules:\n  - regex: DEBUG\n    skip: true\n";
+        std::fs::write(&path, config).expect("Failed to write test config");
+        let input = b"keep\nDEBUG \xff drop\nafter\n";
+        let (stdout, success) =
+            run_phos_with_stdin_bytes(&["--config", path.to_str().unwrap()], input);
+        let _ = std::fs::remove_file(&path);
+        assert!(success);
+        assert_eq!(stdout, b"keep\nafter\n");
+    }
+
+    #[test]
+    fn test_crlf_and_carriage_return_preserved() {
+        let input = b"Downloading 10%\rDownloading 60%\rDone\r\nnext line\r\n";
+        let (stdout, success) = run_phos_with_stdin_bytes(&["-p", "docker"], input);
+        assert!(success);
+        assert_eq!(stdout, input);
+    }
 }
 
 // =============================================================================